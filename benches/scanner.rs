@@ -0,0 +1,53 @@
+//! Compares the throughput of the zero-copy `Scanner` against the original
+//! eager, allocating tokenizer. Run with `cargo bench`.
+
+mod legacy;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use scanner::Scanner;
+
+const PROGRAM: &str = "
+int main() {
+    int a = 10;
+    int c;
+    char* message = \"Hello World!\";
+    float ratio = 10.5f;
+    // Count up to ten
+    for (int i = 0; i <= 10; i++) {
+        /* Only small values */
+        if (i + a <= 15) {
+            c = i;
+        }
+    }
+    return 0;
+}
+";
+
+fn measure(name: &str, src: &str, mut run: impl FnMut(&str) -> usize) {
+    const ITERATIONS: u32 = 20;
+
+    let mut tokens = 0;
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        tokens = black_box(run(black_box(src)));
+        elapsed += start.elapsed();
+    }
+
+    let per_iteration = elapsed / ITERATIONS;
+    let throughput = src.len() as f64 / per_iteration.as_secs_f64() / (1024.0 * 1024.0);
+    println!(
+        "{:<10} {:>8} tokens {:>12.3?}/iter {:>10.1} MiB/s",
+        name, tokens, per_iteration, throughput
+    );
+}
+
+fn main() {
+    let src = PROGRAM.repeat(20_000);
+    println!("scanning {} KiB of C", src.len() / 1024);
+
+    measure("legacy", &src, |src| legacy::tokenize_str(src).len());
+    measure("scanner", &src, |src| Scanner::new(src).count());
+}
@@ -0,0 +1,25 @@
+use std::cell::RefCell;
+
+/// Storage for text that doesn't come from the source, such as pasted tokens,
+/// so that tokens can borrow their spelling from it like from the source.
+#[derive(Debug, Default)]
+pub struct Arena {
+    strings: RefCell<Vec<Box<str>>>,
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves `text` into the arena, returning a reference that lives as long
+    /// as the arena.
+    pub fn alloc(&self, text: impl Into<Box<str>>) -> &str {
+        let text = text.into();
+        let ptr: *const str = &*text;
+        self.strings.borrow_mut().push(text);
+        // SAFETY: the strings are never dropped or mutated before the arena
+        // is, and moving a `Box` doesn't move the text it points to.
+        unsafe { &*ptr }
+    }
+}
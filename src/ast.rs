@@ -0,0 +1,417 @@
+use crate::{CharLiteral, NumberLiteral, Span, StringLiteral};
+
+/// A whole source file: the functions and declarations at file scope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslationUnit<'src> {
+    pub items: Vec<ExternalDeclaration<'src>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExternalDeclaration<'src> {
+    Function(FunctionDef<'src>),
+    Declaration(Declaration<'src>),
+    StaticAssert(StaticAssert<'src>),
+}
+
+/// A function definition like `int main(void) { return 0; }`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDef<'src> {
+    pub specifiers: DeclarationSpecifiers<'src>,
+    /// The declarator naming the function, whose innermost derived
+    /// declarator is a `DerivedDeclarator::Function`.
+    pub declarator: Declarator<'src>,
+    pub body: Block<'src>,
+    pub span: Span,
+}
+
+/// A declaration like `static int a = 1, *b;`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration<'src> {
+    pub specifiers: DeclarationSpecifiers<'src>,
+    pub declarators: Vec<InitDeclarator<'src>>,
+    pub span: Span,
+}
+
+/// The specifiers and qualifiers starting a declaration, which C allows in
+/// any order. Each list keeps the order they were written in.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeclarationSpecifiers<'src> {
+    pub storage_classes: Vec<StorageClass>,
+    pub type_specifiers: Vec<TypeSpecifier<'src>>,
+    pub qualifiers: Vec<TypeQualifier>,
+    pub function_specifiers: Vec<FunctionSpecifier>,
+    pub alignment_specifiers: Vec<AlignmentSpecifier<'src>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageClass {
+    Typedef,
+    Extern,
+    Static,
+    ThreadLocal,
+    Auto,
+    Register,
+}
+
+/// A type specifier. `unsigned long int` is three of them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeSpecifier<'src> {
+    Void,
+    Char,
+    Short,
+    Int,
+    Long,
+    Float,
+    Double,
+    Signed,
+    Unsigned,
+    Bool,
+    Complex,
+    /// A name declared by `typedef`.
+    TypedefName(&'src str),
+    Struct(StructType<'src>),
+    Enum(EnumType<'src>),
+}
+
+/// A `struct` or `union` specifier. Tags like the `node` of `struct node`
+/// have a namespace of their own, apart from typedef names and variables.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructType<'src> {
+    pub kind: StructKind,
+    pub tag: Option<&'src str>,
+    /// The members of a definition, or `None` when the type is only named,
+    /// as in `struct node *next` or the forward declaration `struct node;`.
+    pub members: Option<Vec<Member<'src>>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructKind {
+    Struct,
+    Union,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Member<'src> {
+    Declaration(MemberDeclaration<'src>),
+    StaticAssert(StaticAssert<'src>),
+}
+
+/// A member declaration like `unsigned flags : 3, *next;`. An anonymous
+/// struct or union member has no declarators.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberDeclaration<'src> {
+    pub specifiers: DeclarationSpecifiers<'src>,
+    pub declarators: Vec<MemberDeclarator<'src>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberDeclarator<'src> {
+    /// The declarator of the member, which has no name in an unnamed
+    /// bit-field like `int : 0`.
+    pub declarator: Declarator<'src>,
+    pub bit_width: Option<Expr<'src>>,
+}
+
+/// An `enum` specifier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumType<'src> {
+    pub tag: Option<&'src str>,
+    /// The constants of a definition, or `None` when the type is only named.
+    pub enumerators: Option<Vec<Enumerator<'src>>>,
+    pub span: Span,
+}
+
+/// An enumeration constant, whose value is one more than the previous
+/// one's unless it is given explicitly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enumerator<'src> {
+    pub name: &'src str,
+    pub value: Option<Expr<'src>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeQualifier {
+    Const,
+    Restrict,
+    Volatile,
+    Atomic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionSpecifier {
+    Inline,
+    Noreturn,
+}
+
+/// `_Alignas(type)` or `_Alignas(expr)`, spelled `alignas` since C23.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlignmentSpecifier<'src> {
+    Type(TypeName<'src>),
+    Expr(Expr<'src>),
+}
+
+/// `_Static_assert(condition, "message");`, spelled `static_assert` since
+/// C23, which also makes the message optional.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticAssert<'src> {
+    pub condition: Expr<'src>,
+    pub message: Option<StringLiteral<'src>>,
+    pub span: Span,
+}
+
+/// A declarator together with its optional `= initializer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InitDeclarator<'src> {
+    pub declarator: Declarator<'src>,
+    pub initializer: Option<Initializer<'src>>,
+}
+
+/// A declarator like `*argv[]`, naming what is declared and deriving its
+/// type from the one of the specifiers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declarator<'src> {
+    /// `None` in an abstract declarator, like the one of an unnamed
+    /// parameter.
+    pub name: Option<&'src str>,
+    /// The pointers, arrays and functions around the name, innermost first:
+    /// `*argv[]` is an array of pointers.
+    pub derived: Vec<DerivedDeclarator<'src>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DerivedDeclarator<'src> {
+    /// `*`, followed by the qualifiers of the pointer itself.
+    Pointer(Vec<TypeQualifier>),
+    /// `[size]`, where `static` and qualifiers may only appear in parameters.
+    Array {
+        qualifiers: Vec<TypeQualifier>,
+        is_static: bool,
+        size: Option<Box<Expr<'src>>>,
+    },
+    /// A parameter list. `f(void)` has a single `void` parameter, while the
+    /// parameters of `f()` are unspecified.
+    Function {
+        parameters: Vec<ParameterDeclaration<'src>>,
+        is_variadic: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterDeclaration<'src> {
+    pub specifiers: DeclarationSpecifiers<'src>,
+    pub declarator: Declarator<'src>,
+}
+
+/// The type of a cast or `sizeof`, like `const char *`, whose declarator is
+/// abstract.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeName<'src> {
+    pub specifiers: DeclarationSpecifiers<'src>,
+    pub declarator: Declarator<'src>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Initializer<'src> {
+    Expr(Expr<'src>),
+    /// A braced list like `{ 1, [4] = 2, .x = 3 }`.
+    List(Vec<DesignatedInitializer<'src>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesignatedInitializer<'src> {
+    pub designators: Vec<Designator<'src>>,
+    pub initializer: Initializer<'src>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Designator<'src> {
+    /// `[index]`.
+    Index(Expr<'src>),
+    /// `.member`.
+    Member(&'src str),
+}
+
+/// A compound statement, `{ ... }`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block<'src> {
+    pub items: Vec<BlockItem<'src>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockItem<'src> {
+    Declaration(Declaration<'src>),
+    StaticAssert(StaticAssert<'src>),
+    Stmt(Stmt<'src>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt<'src> {
+    pub kind: StmtKind<'src>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind<'src> {
+    Compound(Block<'src>),
+    /// An expression followed by `;`, or just `;`.
+    Expr(Option<Expr<'src>>),
+    If {
+        condition: Expr<'src>,
+        then: Box<Stmt<'src>>,
+        otherwise: Option<Box<Stmt<'src>>>,
+    },
+    Switch {
+        condition: Expr<'src>,
+        body: Box<Stmt<'src>>,
+    },
+    While {
+        condition: Expr<'src>,
+        body: Box<Stmt<'src>>,
+    },
+    DoWhile {
+        body: Box<Stmt<'src>>,
+        condition: Expr<'src>,
+    },
+    For {
+        init: Box<ForInit<'src>>,
+        condition: Option<Expr<'src>>,
+        step: Option<Expr<'src>>,
+        body: Box<Stmt<'src>>,
+    },
+    Goto(&'src str),
+    Continue,
+    Break,
+    Return(Option<Expr<'src>>),
+    /// A statement following `label:`.
+    Labeled {
+        label: &'src str,
+        body: Box<Stmt<'src>>,
+    },
+    Case {
+        value: Expr<'src>,
+        body: Box<Stmt<'src>>,
+    },
+    Default(Box<Stmt<'src>>),
+}
+
+/// The first clause of a `for`, which may declare the loop variables.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ForInit<'src> {
+    Expr(Option<Expr<'src>>),
+    Declaration(Declaration<'src>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr<'src> {
+    pub kind: ExprKind<'src>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind<'src> {
+    Id(&'src str),
+    Number(NumberLiteral<'src>),
+    Char(CharLiteral<'src>),
+    String(StringLiteral<'src>),
+    /// `true` or `false`, keywords since C23.
+    Bool(bool),
+    /// `nullptr`, a keyword since C23.
+    Nullptr,
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr<'src>>,
+    },
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr<'src>>,
+        right: Box<Expr<'src>>,
+    },
+    /// `target = value`, or a compound assignment like `target += value`
+    /// applying `op`.
+    Assign {
+        op: Option<BinaryOp>,
+        target: Box<Expr<'src>>,
+        value: Box<Expr<'src>>,
+    },
+    /// `condition ? then : otherwise`.
+    Conditional {
+        condition: Box<Expr<'src>>,
+        then: Box<Expr<'src>>,
+        otherwise: Box<Expr<'src>>,
+    },
+    /// Expressions separated by the comma operator, evaluated in order.
+    Comma(Vec<Expr<'src>>),
+    Cast {
+        type_name: Box<TypeName<'src>>,
+        operand: Box<Expr<'src>>,
+    },
+    /// A compound literal like `(int[]){ 1, 2 }`.
+    CompoundLiteral {
+        type_name: Box<TypeName<'src>>,
+        initializers: Vec<DesignatedInitializer<'src>>,
+    },
+    /// `sizeof expr`.
+    SizeofExpr(Box<Expr<'src>>),
+    /// `sizeof(type)`.
+    SizeofType(Box<TypeName<'src>>),
+    /// `_Alignof(type)`.
+    Alignof(Box<TypeName<'src>>),
+    Call {
+        callee: Box<Expr<'src>>,
+        arguments: Vec<Expr<'src>>,
+    },
+    /// `array[index]`.
+    Index {
+        array: Box<Expr<'src>>,
+        index: Box<Expr<'src>>,
+    },
+    /// `base.member`, or `base->member` when `is_arrow` is set.
+    Member {
+        base: Box<Expr<'src>>,
+        member: &'src str,
+        is_arrow: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Plus,
+    Minus,
+    Not,
+    BitNot,
+    /// `*`, following a pointer.
+    Deref,
+    /// `&`, taking the address.
+    AddressOf,
+    PreIncrement,
+    PreDecrement,
+    PostIncrement,
+    PostDecrement,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Multiply,
+    Divide,
+    Modulo,
+    Add,
+    Subtract,
+    ShiftLeft,
+    ShiftRight,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    Equals,
+    NotEqual,
+    BitAnd,
+    BitXor,
+    BitOr,
+    And,
+    Or,
+}
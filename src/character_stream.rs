@@ -1,45 +1,137 @@
+use crate::phases::{self, Edit, EditKind};
+use crate::Span;
+
+/// A cursor over the bytes of the source text that hands out characters,
+/// decoding UTF-8 only when it meets a non-ASCII byte.
+///
+/// When the text went through translation phases 1 and 2, lines and columns
+/// are counted in the original text, and `original_span` maps offsets back.
 #[derive(Debug)]
-pub struct CharacterStream {
-    pos: usize,
-    chars: Vec<char>,
-    // current_char: char,
-    is_end_of_file: bool
+pub struct CharacterStream<'src> {
+    text: &'src str,
+    offset: usize,
+    line: usize,
+    column: usize,
+    edits: Vec<Edit>,
+    /// The first edit at or after the cursor.
+    next_edit: usize,
 }
 
-impl CharacterStream {
-    pub fn new(text: &str) -> Self {
-        let chars = text.chars().collect::<Vec<char>>();
+impl<'src> CharacterStream<'src> {
+    /// A stream over `text`, the result of the phases 1 and 2 `edits`.
+    pub fn with_edits(text: &'src str, edits: Vec<Edit>) -> Self {
+        let mut cs = Self {
+            text,
+            offset: 0,
+            line: 1,
+            column: 1,
+            edits,
+            next_edit: 0,
+        };
+        cs.skip_splices();
+        cs
+    }
 
-        Self { pos: 0, chars, is_end_of_file: text.is_empty() }
+    pub fn current_char(&self) -> Option<char> {
+        self.char_at(self.offset)
     }
 
-    pub fn current_char(&self) -> char {
-        self.chars[self.pos]
+    pub fn next_char(&self) -> Option<char> {
+        self.nth_char(1)
     }
 
-    pub fn next_char(&self) -> Option<&char> {
-        self.chars.get(self.pos + 1)
+    /// The character `n` positions after the current one.
+    pub fn nth_char(&self, n: usize) -> Option<char> {
+        let mut offset = self.offset;
+        for _ in 0..n {
+            offset += self.char_at(offset)?.len_utf8();
+        }
+        self.char_at(offset)
     }
 
     pub fn advance_by(&mut self, offset: usize) {
-        self.pos += offset;
+        for _ in 0..offset {
+            let Some(char) = self.current_char() else {
+                break;
+            };
+            self.bump(char);
+        }
+    }
 
-        if self.pos >= self.chars.len() {
-            self.is_end_of_file = true;
+    /// Advances while `predicate` holds for the current character.
+    pub fn advance_while(&mut self, predicate: impl Fn(char) -> bool) {
+        while let Some(char) = self.current_char().filter(|&c| predicate(c)) {
+            self.bump(char);
         }
     }
 
-    pub fn is_eof(&self) -> bool {
-        self.is_end_of_file
+    /// An empty span at the current character.
+    pub fn position(&self) -> Span {
+        Span::new(self.offset, self.offset, self.line, self.column)
     }
 
-    pub fn skip_whitespace(&mut self) {
-        while !self.is_eof() && self.current_char().is_ascii_whitespace() {
-            self.advance_by(1)
+    /// The span from `start` up to the current character.
+    pub fn span_from(&self, start: Span) -> Span {
+        Span::new(start.start(), self.offset, start.line(), start.column())
+    }
+
+    /// Moves the offsets of `span` back to the text before phases 1 and 2.
+    pub fn original_span(&self, span: Span) -> Span {
+        Span::new(
+            phases::original_offset(&self.edits, span.start()),
+            phases::original_offset(&self.edits, span.end()),
+            span.line(),
+            span.column(),
+        )
+    }
+
+    /// The text covered by `span`.
+    pub fn slice(&self, span: Span) -> &'src str {
+        &self.text[span.start()..span.end()]
+    }
+
+    /// The whole text being scanned.
+    pub fn text(&self) -> &'src str {
+        self.text
+    }
+
+    /// The text from the current character on.
+    pub fn rest(&self) -> &'src str {
+        &self.text[self.offset..]
+    }
+
+    fn char_at(&self, offset: usize) -> Option<char> {
+        match *self.text.as_bytes().get(offset)? {
+            byte if byte.is_ascii() => Some(byte as char),
+            _ => self.text[offset..].chars().next(),
         }
     }
 
-    pub fn check_bounds(&self) -> bool {
-        self.pos < self.chars.len()
+    fn bump(&mut self, char: char) {
+        let edit = self.edits.get(self.next_edit);
+        let is_trigraph = edit.is_some_and(|edit| edit.at == self.offset);
+        self.offset += char.len_utf8();
+        if char == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else if is_trigraph {
+            self.column += 3;
+            self.next_edit += 1;
+        } else {
+            self.column += 1;
+        }
+        self.skip_splices();
+    }
+
+    /// Counts the lines spliced at the cursor.
+    fn skip_splices(&mut self) {
+        while let Some(edit) = self.edits.get(self.next_edit) {
+            if edit.at != self.offset || edit.kind == EditKind::Trigraph {
+                break;
+            }
+            self.line += 1;
+            self.column = 1;
+            self.next_edit += 1;
+        }
     }
 }
@@ -0,0 +1,168 @@
+use std::borrow::Cow;
+use std::iter::FusedIterator;
+
+use crate::{Arena, CodeUnits, Encoding, LexError, Span, SpannedToken, StringLiteral, Token};
+
+/// Translation phase 6: merges adjacent string literals of a token stream
+/// into a single `Token::String` whose span covers all of them.
+///
+/// Created by [`Scanner::concat_strings`](crate::Scanner::concat_strings),
+/// or by [`ConcatStrings::new`] for other tokens, like the preprocessor's.
+pub struct ConcatStrings<'src, I> {
+    tokens: I,
+    peeked: Option<SpannedToken<'src>>,
+    spelling: Spelling<'src>,
+    errors: Vec<LexError>,
+}
+
+/// Where the spelling of a merged literal comes from.
+enum Spelling<'src> {
+    /// The text the tokens were scanned from, after phases 1 and 2, which
+    /// spells the literal with everything between its parts.
+    Text(&'src str),
+    /// Storage for the spellings of the parts joined by spaces, as they may
+    /// come from different places.
+    Arena(&'src Arena),
+}
+
+impl<'src, I: Iterator<Item = SpannedToken<'src>>> ConcatStrings<'src, I> {
+    /// Merges the string literals of `tokens`, keeping the spellings of the
+    /// merged literals in `arena`.
+    pub fn new(tokens: I, arena: &'src Arena) -> Self {
+        Self::with_spelling(tokens, Spelling::Arena(arena))
+    }
+
+    /// Merges the string literals of `tokens`, which were all scanned from
+    /// `text`.
+    pub(crate) fn from_text(tokens: I, text: &'src str) -> Self {
+        Self::with_spelling(tokens, Spelling::Text(text))
+    }
+
+    fn with_spelling(tokens: I, spelling: Spelling<'src>) -> Self {
+        Self {
+            tokens,
+            peeked: None,
+            spelling,
+            errors: Vec::new(),
+        }
+    }
+
+    /// The errors found so far while merging. The ones of the tokens
+    /// themselves stay with their source, like the scanner.
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+
+    /// The underlying token stream.
+    pub fn get_ref(&self) -> &I {
+        &self.tokens
+    }
+
+    fn next_is_string(&mut self) -> bool {
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.next();
+        }
+        matches!(
+            self.peeked.as_ref().map(SpannedToken::token),
+            Some(Token::String(_))
+        )
+    }
+
+    fn merge(&mut self, parts: Vec<SpannedToken<'src>>) -> SpannedToken<'src> {
+        let first = parts[0].span();
+        let last = parts[parts.len() - 1].span();
+        let span = Span::new(first.start(), last.end(), first.line(), first.column());
+        let raw = self.spell(&parts);
+
+        let strings: Vec<&StringLiteral<'src>> = parts
+            .iter()
+            .filter_map(|part| match part.token() {
+                Token::String(string) => Some(string),
+                _ => None,
+            })
+            .collect();
+        let mut encoding = Encoding::Plain;
+        let mut is_compatible = true;
+        for string in &strings {
+            // A plain literal takes the prefix of the others, but literals
+            // with different prefixes can't be merged.
+            match (encoding, string.encoding()) {
+                (_, Encoding::Plain) => (),
+                (Encoding::Plain, other) => encoding = other,
+                (current, other) => is_compatible &= current == other,
+            }
+        }
+
+        let token = if is_compatible {
+            let units = merge_units(&strings, encoding);
+            Token::String(StringLiteral::new(raw, units).with_encoding(encoding))
+        } else {
+            self.errors
+                .push(LexError::IncompatibleStringEncodings { span });
+            Token::Invalid(raw)
+        };
+
+        let leading_trivia = parts[0].leading_trivia().to_vec();
+        let trailing_trivia = parts[parts.len() - 1].trailing_trivia().to_vec();
+        SpannedToken::new(token, span, raw).with_trivia(leading_trivia, trailing_trivia)
+    }
+
+    /// The spelling of the literal merging `parts`. The spans may point into
+    /// the text before phases 1 and 2, so it is found from the spellings of
+    /// the parts instead.
+    fn spell(&self, parts: &[SpannedToken<'src>]) -> &'src str {
+        let first = parts[0].spelling();
+        let last = parts[parts.len() - 1].spelling();
+        match self.spelling {
+            Spelling::Text(text) => spelling_between(text, first, last)
+                .expect("scanned tokens are spelled from the scanned text"),
+            Spelling::Arena(arena) => {
+                let spellings: Vec<&str> = parts.iter().map(SpannedToken::spelling).collect();
+                arena.alloc(spellings.join(" "))
+            }
+        }
+    }
+}
+
+/// The units of `strings` once merged into a literal of `encoding`.
+fn merge_units<'src>(strings: &[&StringLiteral<'_>], encoding: Encoding) -> CodeUnits<'src> {
+    if let Encoding::Plain | Encoding::Utf8 = encoding {
+        let mut bytes = Vec::new();
+        for string in strings {
+            if let CodeUnits::Bytes(part) = string.units() {
+                bytes.extend_from_slice(part);
+            }
+        }
+        return CodeUnits::Bytes(Cow::Owned(bytes));
+    }
+    let units = strings
+        .iter()
+        .flat_map(|string| string.units().widen(encoding));
+    CodeUnits::Wide(units.collect())
+}
+
+/// The part of `text` from the start of `first` to the end of `last`,
+/// provided both are slices of it.
+fn spelling_between<'src>(text: &'src str, first: &str, last: &str) -> Option<&'src str> {
+    let offset = |spelling: &str| (spelling.as_ptr() as usize).checked_sub(text.as_ptr() as usize);
+    text.get(offset(first)?..offset(last)? + last.len())
+}
+
+impl<'src, I: Iterator<Item = SpannedToken<'src>>> Iterator for ConcatStrings<'src, I> {
+    type Item = SpannedToken<'src>;
+
+    fn next(&mut self) -> Option<SpannedToken<'src>> {
+        let first = self.peeked.take().or_else(|| self.tokens.next())?;
+        if !matches!(first.token(), Token::String(_)) || !self.next_is_string() {
+            return Some(first);
+        }
+
+        let mut parts = vec![first];
+        while self.next_is_string() {
+            parts.extend(self.peeked.take());
+        }
+        Some(self.merge(parts))
+    }
+}
+
+impl<'src, I: FusedIterator<Item = SpannedToken<'src>>> FusedIterator for ConcatStrings<'src, I> {}
@@ -0,0 +1,64 @@
+use crate::{OperatorType, SpannedToken, Token};
+
+/// Pairs each doc comment of `tokens` with the token naming the declaration
+/// it documents.
+///
+/// A comment documents the next declaration, and its name is taken to be
+/// the last identifier before the first `(`, `[`, `{`, `=`, `:`, `,` or `;`.
+/// When there is no such identifier, as in `enum { ... }`, the first token
+/// of the declaration stands in for it. Consecutive comments document the
+/// same declaration. Comments starting with `<`, as in `int x; ///< doc`,
+/// document the identifier before them instead.
+pub fn attach_doc_comments<'a, 'src>(
+    tokens: &'a [SpannedToken<'src>],
+) -> Vec<(&'a SpannedToken<'src>, &'a SpannedToken<'src>)> {
+    let mut pairs = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        let Token::DocComment(text) = token.token() else {
+            continue;
+        };
+        let declaration = if text.starts_with('<') {
+            previous_identifier(&tokens[..index])
+        } else {
+            next_declaration(&tokens[index + 1..])
+        };
+        if let Some(declaration) = declaration {
+            pairs.push((token, declaration));
+        }
+    }
+    pairs
+}
+
+fn next_declaration<'a, 'src>(tokens: &'a [SpannedToken<'src>]) -> Option<&'a SpannedToken<'src>> {
+    let mut tokens = tokens
+        .iter()
+        .filter(|token| !matches!(token.token(), Token::DocComment(_)))
+        .take_while(|token| token.token() != &Token::Eof)
+        .peekable();
+    let first = *tokens.peek()?;
+
+    let mut name = None;
+    for token in tokens {
+        match token.token() {
+            Token::OpenParen
+            | Token::OpenBracket
+            | Token::OpenBrace
+            | Token::Colon
+            | Token::Comma
+            | Token::SemiColon
+            | Token::Operator(OperatorType::Assign) => break,
+            Token::Id(_) => name = Some(token),
+            _ => {}
+        }
+    }
+    name.or(Some(first))
+}
+
+fn previous_identifier<'a, 'src>(
+    tokens: &'a [SpannedToken<'src>],
+) -> Option<&'a SpannedToken<'src>> {
+    tokens
+        .iter()
+        .rev()
+        .find(|token| matches!(token.token(), Token::Id(_)))
+}
@@ -0,0 +1,332 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::Span;
+
+/// A lexical error found while scanning, together with the offending span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexError {
+    /// A number followed by letters that aren't a valid suffix, like `123abc`.
+    InvalidIdentifier { text: String, span: Span },
+    /// A malformed number, like `0x`, `1e+` or `09`.
+    InvalidNumber { text: String, span: Span },
+    /// An integer constant too large for any integer type.
+    IntegerOverflow { text: String, span: Span },
+    /// A floating constant too large for its type, like `1e999`.
+    FloatOverflow { text: String, span: Span },
+    /// A string literal without its closing `"`.
+    UnterminatedString { span: Span },
+    /// A character literal without its closing `'`.
+    UnterminatedChar { span: Span },
+    /// A `/*` comment without its closing `*/`.
+    UnterminatedComment { span: Span },
+    /// A character literal holding more than one character.
+    MultiCharacterChar { span: Span },
+    /// A character literal holding no character, `''`.
+    EmptyChar { span: Span },
+    /// An unknown or malformed escape sequence, like `\q` or `\u12`.
+    InvalidEscape { sequence: String, span: Span },
+    /// An octal or hexadecimal escape sequence too large for the literal's
+    /// character type.
+    EscapeOutOfRange { sequence: String, span: Span },
+    /// Adjacent string literals with different encoding prefixes, like
+    /// `u"a" U"b"`.
+    IncompatibleStringEncodings { span: Span },
+    /// A character that doesn't start any token.
+    InvalidSymbol { symbol: char, span: Span },
+}
+
+impl LexError {
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::InvalidIdentifier { span, .. }
+            | LexError::InvalidNumber { span, .. }
+            | LexError::IntegerOverflow { span, .. }
+            | LexError::FloatOverflow { span, .. }
+            | LexError::UnterminatedString { span }
+            | LexError::UnterminatedChar { span }
+            | LexError::UnterminatedComment { span }
+            | LexError::MultiCharacterChar { span }
+            | LexError::EmptyChar { span }
+            | LexError::InvalidEscape { span, .. }
+            | LexError::EscapeOutOfRange { span, .. }
+            | LexError::IncompatibleStringEncodings { span }
+            | LexError::InvalidSymbol { span, .. } => *span,
+        }
+    }
+
+    /// Moves the error to the span `map` returns, for text that the scanner
+    /// doesn't see at its actual position.
+    pub(crate) fn map_span(mut self, map: impl FnOnce(Span) -> Span) -> Self {
+        let span = match &mut self {
+            LexError::InvalidIdentifier { span, .. }
+            | LexError::InvalidNumber { span, .. }
+            | LexError::IntegerOverflow { span, .. }
+            | LexError::FloatOverflow { span, .. }
+            | LexError::UnterminatedString { span }
+            | LexError::UnterminatedChar { span }
+            | LexError::UnterminatedComment { span }
+            | LexError::MultiCharacterChar { span }
+            | LexError::EmptyChar { span }
+            | LexError::InvalidEscape { span, .. }
+            | LexError::EscapeOutOfRange { span, .. }
+            | LexError::IncompatibleStringEncodings { span }
+            | LexError::InvalidSymbol { span, .. } => span,
+        };
+        *span = map(*span);
+        self
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::InvalidIdentifier { text, .. } => {
+                write!(f, "Invalid identifier \"{}\"!", text)
+            }
+            LexError::InvalidNumber { text, .. } => write!(f, "Invalid number \"{}\"!", text),
+            LexError::IntegerOverflow { text, .. } => {
+                write!(f, "integer constant \"{}\" is too large", text)
+            }
+            LexError::FloatOverflow { text, .. } => {
+                write!(f, "floating constant \"{}\" is too large", text)
+            }
+            LexError::UnterminatedString { .. } => write!(f, "missing terminating \""),
+            LexError::UnterminatedChar { .. } => write!(f, "missing terminating '"),
+            LexError::UnterminatedComment { .. } => write!(f, "unterminated comment"),
+            LexError::MultiCharacterChar { .. } => {
+                write!(f, "char literals should only have one character")
+            }
+            LexError::EmptyChar { .. } => write!(f, "empty character constant"),
+            LexError::InvalidEscape { sequence, .. } => {
+                write!(f, "invalid escape sequence \"{}\"", sequence)
+            }
+            LexError::EscapeOutOfRange { sequence, .. } => {
+                write!(f, "escape sequence \"{}\" out of range", sequence)
+            }
+            LexError::IncompatibleStringEncodings { .. } => {
+                write!(
+                    f,
+                    "concatenation of string literals with different prefixes"
+                )
+            }
+            LexError::InvalidSymbol { symbol, .. } => write!(f, "Invalid symbol \"{}\"!", symbol),
+        }
+    }
+}
+
+impl Error for LexError {}
+
+/// An error found while preprocessing, together with the offending span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreprocessError {
+    /// An error of the scanner.
+    Lex(LexError),
+    /// A directive the preprocessor doesn't know, like `#foo`.
+    InvalidDirective { name: String, span: Span },
+    /// A `#define` or `#undef` that isn't followed by an identifier, or that
+    /// names the macro `defined`.
+    InvalidMacroName { span: Span },
+    /// A macro defined again with a different replacement list.
+    MacroRedefined { name: String, span: Span },
+    /// A malformed parameter list, like `(a, a)` or `(a`.
+    InvalidParameterList { span: Span },
+    /// A `#` in a function-like macro that isn't followed by a parameter.
+    StringifyNonParameter { span: Span },
+    /// A `##` at either end of a replacement list.
+    PasteAtEdge { span: Span },
+    /// A `##` that doesn't form a single valid token, like `+ ## -`.
+    InvalidPaste { text: String, span: Span },
+    /// `__VA_ARGS__` or `__VA_OPT__` outside a variadic macro.
+    VariadicOutsideVariadicMacro { span: Span },
+    /// A function-like macro call without its closing `)`.
+    UnterminatedMacroCall { name: String, span: Span },
+    /// A macro called with too many or too few arguments.
+    WrongArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    /// Macro calls nested in each other's arguments too deeply to expand.
+    MacroNestingTooDeep { span: Span },
+    /// An `#elif`, `#else` or `#endif` without a matching `#if`.
+    UnbalancedConditional { directive: String, span: Span },
+    /// An `#elif` or `#else` following the `#else` of its conditional.
+    ElseAfterElse { directive: String, span: Span },
+    /// An `#if` without its `#endif`.
+    UnterminatedConditional { directive: String, span: Span },
+    /// A malformed `#if` expression, or one that isn't an integer constant
+    /// expression.
+    InvalidExpression { span: Span },
+    /// A division by zero in an `#if` expression.
+    DivisionByZero { span: Span },
+    /// An `#include` that isn't followed by a header name.
+    InvalidInclude { span: Span },
+    /// An `#include` of a file that isn't in any of the search directories.
+    IncludeNotFound { name: String, span: Span },
+    /// An `#include` of a file that is being read already.
+    IncludeCycle { path: String, span: Span },
+    /// A `#line` that isn't followed by a line number and an optional file
+    /// name.
+    InvalidLineDirective { span: Span },
+    /// A known pragma used with the wrong arguments, like `#pragma pack(3)`,
+    /// or a `_Pragma` without its parenthesized string literal.
+    InvalidPragma { span: Span },
+}
+
+impl PreprocessError {
+    pub fn span(&self) -> Span {
+        match self {
+            PreprocessError::Lex(error) => error.span(),
+            PreprocessError::InvalidDirective { span, .. }
+            | PreprocessError::InvalidMacroName { span }
+            | PreprocessError::MacroRedefined { span, .. }
+            | PreprocessError::InvalidParameterList { span }
+            | PreprocessError::StringifyNonParameter { span }
+            | PreprocessError::PasteAtEdge { span }
+            | PreprocessError::InvalidPaste { span, .. }
+            | PreprocessError::VariadicOutsideVariadicMacro { span }
+            | PreprocessError::UnterminatedMacroCall { span, .. }
+            | PreprocessError::WrongArgumentCount { span, .. }
+            | PreprocessError::MacroNestingTooDeep { span }
+            | PreprocessError::UnbalancedConditional { span, .. }
+            | PreprocessError::ElseAfterElse { span, .. }
+            | PreprocessError::UnterminatedConditional { span, .. }
+            | PreprocessError::InvalidExpression { span }
+            | PreprocessError::DivisionByZero { span }
+            | PreprocessError::InvalidInclude { span }
+            | PreprocessError::IncludeNotFound { span, .. }
+            | PreprocessError::IncludeCycle { span, .. }
+            | PreprocessError::InvalidLineDirective { span }
+            | PreprocessError::InvalidPragma { span } => *span,
+        }
+    }
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreprocessError::Lex(error) => error.fmt(f),
+            PreprocessError::InvalidDirective { name, .. } => {
+                write!(f, "invalid preprocessing directive #{}", name)
+            }
+            PreprocessError::InvalidMacroName { .. } => write!(f, "invalid macro name"),
+            PreprocessError::MacroRedefined { name, .. } => write!(f, "\"{}\" redefined", name),
+            PreprocessError::InvalidParameterList { .. } => {
+                write!(f, "invalid macro parameter list")
+            }
+            PreprocessError::StringifyNonParameter { .. } => {
+                write!(f, "'#' is not followed by a macro parameter")
+            }
+            PreprocessError::PasteAtEdge { .. } => {
+                write!(f, "'##' cannot appear at either end of a macro expansion")
+            }
+            PreprocessError::InvalidPaste { text, .. } => {
+                write!(f, "pasting does not give a valid token: \"{}\"", text)
+            }
+            PreprocessError::VariadicOutsideVariadicMacro { .. } => {
+                write!(f, "__VA_ARGS__ can only appear in a variadic macro")
+            }
+            PreprocessError::UnterminatedMacroCall { name, .. } => {
+                write!(f, "unterminated argument list invoking macro \"{}\"", name)
+            }
+            PreprocessError::WrongArgumentCount {
+                name,
+                expected,
+                found,
+                ..
+            } => {
+                if found > expected {
+                    write!(
+                        f,
+                        "macro \"{}\" passed {} arguments, but takes just {}",
+                        name, found, expected
+                    )
+                } else {
+                    write!(
+                        f,
+                        "macro \"{}\" requires {} arguments, but only {} given",
+                        name, expected, found
+                    )
+                }
+            }
+            PreprocessError::MacroNestingTooDeep { .. } => {
+                write!(f, "macro calls nested too deeply")
+            }
+            PreprocessError::UnbalancedConditional { directive, .. } => {
+                write!(f, "#{} without #if", directive)
+            }
+            PreprocessError::ElseAfterElse { directive, .. } => {
+                write!(f, "#{} after #else", directive)
+            }
+            PreprocessError::UnterminatedConditional { directive, .. } => {
+                write!(f, "unterminated #{}", directive)
+            }
+            PreprocessError::InvalidExpression { .. } => write!(f, "invalid #if expression"),
+            PreprocessError::DivisionByZero { .. } => write!(f, "division by zero in #if"),
+            PreprocessError::InvalidInclude { .. } => {
+                write!(f, "#include expects \"FILENAME\" or <FILENAME>")
+            }
+            PreprocessError::IncludeNotFound { name, .. } => {
+                write!(f, "{}: No such file or directory", name)
+            }
+            PreprocessError::IncludeCycle { path, .. } => {
+                write!(
+                    f,
+                    "#include nested too deeply: \"{}\" includes itself",
+                    path
+                )
+            }
+            PreprocessError::InvalidLineDirective { .. } => write!(f, "invalid #line directive"),
+            PreprocessError::InvalidPragma { .. } => write!(f, "malformed #pragma"),
+        }
+    }
+}
+
+impl Error for PreprocessError {}
+
+/// An error found while parsing, together with the offending span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// An error of the scanner.
+    Lex(LexError),
+    /// A token that can't continue what is being parsed, like the `)` of
+    /// `x = );`.
+    UnexpectedToken {
+        expected: String,
+        found: String,
+        span: Span,
+    },
+    /// Parentheses, blocks or other constructs nested too deeply to parse,
+    /// like a thousand `(` in a row.
+    NestingTooDeep { span: Span },
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::Lex(error) => error.span(),
+            ParseError::UnexpectedToken { span, .. } | ParseError::NestingTooDeep { span } => *span,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Lex(error) => error.fmt(f),
+            ParseError::UnexpectedToken {
+                expected, found, ..
+            } if found.is_empty() => {
+                write!(f, "expected {} at end of input", expected)
+            }
+            ParseError::UnexpectedToken {
+                expected, found, ..
+            } => write!(f, "expected {}, found \"{}\"", expected, found),
+            ParseError::NestingTooDeep { .. } => write!(f, "nesting too deep"),
+        }
+    }
+}
+
+impl Error for ParseError {}
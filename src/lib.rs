@@ -1,22 +1,189 @@
+mod arena;
+mod ast;
 mod character_stream;
+mod concat;
+mod doc;
+mod error;
+mod literal;
+mod parser;
+mod phases;
+mod preprocessor;
+mod span;
+mod trivia;
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt;
+use std::iter::FusedIterator;
 
 use crate::character_stream::CharacterStream;
+use crate::literal::{parse_number, push_wide_char, NumberError};
+use crate::phases::Edit;
+
+pub use crate::arena::Arena;
+pub use crate::ast::{
+    AlignmentSpecifier, BinaryOp, Block, BlockItem, Declaration, DeclarationSpecifiers, Declarator,
+    DerivedDeclarator, DesignatedInitializer, Designator, EnumType, Enumerator, Expr, ExprKind,
+    ExternalDeclaration, ForInit, FunctionDef, FunctionSpecifier, InitDeclarator, Initializer,
+    Member, MemberDeclaration, MemberDeclarator, ParameterDeclaration, StaticAssert, Stmt,
+    StmtKind, StorageClass, StructKind, StructType, TranslationUnit, TypeName, TypeQualifier,
+    TypeSpecifier, UnaryOp,
+};
+pub use crate::concat::ConcatStrings;
+pub use crate::doc::attach_doc_comments;
+pub use crate::error::{LexError, ParseError, PreprocessError};
+pub use crate::literal::{
+    CharLiteral, CodeUnits, Encoding, FloatSuffix, IntegerSuffix, NumberKind, NumberLiteral, Radix,
+    StringLiteral,
+};
+pub use crate::parser::Parser;
+pub use crate::phases::SourceText;
+pub use crate::preprocessor::{
+    DiagnosticPragma, Expansion, FileSystem, Include, IncludeGraph, IncludeResolver, PackPragma,
+    PpToken, Pragma, PragmaSwitch, Preprocessor, RealFileSystem, SkipReason, Target,
+    VirtualFileSystem,
+};
+pub use crate::span::Span;
+pub use crate::trivia::{Trivia, TriviaKind};
+
+/// The revision of the C language the scanner follows. It decides which
+/// words are keywords, e.g. `bool` is an identifier before C23.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Standard {
+    C89,
+    C99,
+    #[default]
+    C11,
+    C17,
+    C23,
+}
 
-#[derive(Debug, PartialEq, Eq)]
+/// C keywords. Alternative spellings of the same keyword, like `_Bool` and
+/// C23's `bool`, share a variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeywordType {
+    Alignas,
+    Alignof,
+    Atomic,
+    Auto,
+    Bool,
+    Break,
+    Case,
     Char,
+    Complex,
+    Const,
+    Constexpr,
+    Continue,
+    Default,
+    Do,
     Double,
     Else,
+    Enum,
+    Extern,
+    False,
     Float,
     For,
+    Generic,
+    Goto,
     If,
+    Imaginary,
+    Inline,
     Int,
     Long,
+    Noreturn,
+    Nullptr,
+    Register,
+    Restrict,
     Return,
+    Short,
+    Signed,
+    Sizeof,
+    Static,
+    StaticAssert,
+    Struct,
+    Switch,
+    ThreadLocal,
+    True,
+    Typedef,
+    Typeof,
+    TypeofUnqual,
+    Union,
+    Unsigned,
+    Void,
+    Volatile,
     While,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl KeywordType {
+    /// Looks up `word` in the keyword table of `standard`.
+    pub fn lookup(word: &str, standard: Standard) -> Option<Self> {
+        use KeywordType::*;
+        use Standard::*;
+
+        let (keyword, since) = match word {
+            "auto" => (Auto, C89),
+            "break" => (Break, C89),
+            "case" => (Case, C89),
+            "char" => (Char, C89),
+            "const" => (Const, C89),
+            "continue" => (Continue, C89),
+            "default" => (Default, C89),
+            "do" => (Do, C89),
+            "double" => (Double, C89),
+            "else" => (Else, C89),
+            "enum" => (Enum, C89),
+            "extern" => (Extern, C89),
+            "float" => (Float, C89),
+            "for" => (For, C89),
+            "goto" => (Goto, C89),
+            "if" => (If, C89),
+            "int" => (Int, C89),
+            "long" => (Long, C89),
+            "register" => (Register, C89),
+            "return" => (Return, C89),
+            "short" => (Short, C89),
+            "signed" => (Signed, C89),
+            "sizeof" => (Sizeof, C89),
+            "static" => (Static, C89),
+            "struct" => (Struct, C89),
+            "switch" => (Switch, C89),
+            "typedef" => (Typedef, C89),
+            "union" => (Union, C89),
+            "unsigned" => (Unsigned, C89),
+            "void" => (Void, C89),
+            "volatile" => (Volatile, C89),
+            "while" => (While, C89),
+            "inline" => (Inline, C99),
+            "restrict" => (Restrict, C99),
+            "_Bool" => (Bool, C99),
+            "_Complex" => (Complex, C99),
+            "_Imaginary" => (Imaginary, C99),
+            "_Alignas" => (Alignas, C11),
+            "_Alignof" => (Alignof, C11),
+            "_Atomic" => (Atomic, C11),
+            "_Generic" => (Generic, C11),
+            "_Noreturn" => (Noreturn, C11),
+            "_Static_assert" => (StaticAssert, C11),
+            "_Thread_local" => (ThreadLocal, C11),
+            "alignas" => (Alignas, C23),
+            "alignof" => (Alignof, C23),
+            "bool" => (Bool, C23),
+            "constexpr" => (Constexpr, C23),
+            "false" => (False, C23),
+            "nullptr" => (Nullptr, C23),
+            "static_assert" => (StaticAssert, C23),
+            "thread_local" => (ThreadLocal, C23),
+            "true" => (True, C23),
+            "typeof" => (Typeof, C23),
+            "typeof_unqual" => (TypeofUnqual, C23),
+            _ => return None,
+        };
+
+        (standard >= since).then_some(keyword)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OperatorType {
     Plus,
     PlusEqual,
@@ -40,292 +207,920 @@ pub enum OperatorType {
     Or,
     Minus,
     MinusEqual,
+    Modulo,
+    ModuloEqual,
+    BitwiseAndEqual,
+    BitwiseOrEqual,
+    BitwiseXor,
+    BitwiseXorEqual,
+    BitwiseNot,
+    ShiftLeft,
+    ShiftLeftEqual,
+    ShiftRight,
+    ShiftRightEqual,
+    Arrow,
+    Dot,
+    Question,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum Token {
+/// A token of the source text. Identifiers, numbers, literals and invalid
+/// lexemes borrow their spelling from the text being scanned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token<'src> {
     CloseBrace,
     CloseBracket,
     CloseParen,
-    Char(char),
+    Char(CharLiteral<'src>),
+    Colon,
+    Comma,
+    /// A Doxygen-style `/** */`, `/*! */`, `///` or `//!` comment, holding
+    /// the text between its delimiters. Only produced when the scanner is
+    /// asked for doc comments.
+    DocComment(&'src str),
+    Ellipsis,
     Eof,
-    Id(String),
+    Hash,
+    HashHash,
+    /// The `<stdio.h>` or `"config.h"` operand of an include, delimiters
+    /// included. Only produced in preprocessing mode.
+    HeaderName(&'src str),
+    Id(&'src str),
     Keyword(KeywordType),
-    Number(String),
+    /// The end of a logical line. Only produced in preprocessing mode.
+    Newline,
+    Number(NumberLiteral<'src>),
+    /// A preprocessing number like `0x` or `1e`, which only has to be a
+    /// valid constant once it reaches translation phase 7. Only produced in
+    /// preprocessing mode.
+    PpNumber(&'src str),
     OpenBrace,
     OpenBracket,
     OpenParen,
     Operator(OperatorType),
+    /// A `#pragma` directive or `_Pragma` operator. Only produced by the
+    /// preprocessor.
+    Pragma(Pragma<'src>),
     SemiColon,
-    String(String),
-    Invalid(String),
+    String(StringLiteral<'src>),
+    Invalid(&'src str),
+}
+
+/// A token together with the span of source text it was read from.
+///
+/// In lossless mode it also carries the trivia around it: trailing trivia
+/// runs up to the end of the token's line, and leading trivia holds the rest.
+/// Formatting a token with `Display` writes its leading trivia, spelling and
+/// trailing trivia, so concatenating every token rebuilds the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken<'src> {
+    token: Token<'src>,
+    span: Span,
+    spelling: &'src str,
+    /// Boxed so that tokens without trivia, as outside of lossless mode,
+    /// stay small.
+    trivia: Option<Box<TokenTrivia<'src>>>,
 }
 
-pub struct Scanner {
-    index: usize,
-    tokens: Vec<Token>,
+#[derive(Debug, Clone, PartialEq)]
+struct TokenTrivia<'src> {
+    leading: Vec<Trivia<'src>>,
+    trailing: Vec<Trivia<'src>>,
 }
 
-impl Scanner {
-    pub fn new(text: &str) -> Self {
+impl<'src> SpannedToken<'src> {
+    pub fn new(token: Token<'src>, span: Span, spelling: &'src str) -> Self {
         Self {
-            index: 0,
-            tokens: tokenize_str(text),
+            token,
+            span,
+            spelling,
+            trivia: None,
         }
     }
 
-    pub fn next_token(&mut self) -> Option<&Token> {
-        let token = self.tokens.get(self.index);
-        self.index += 1;
-        token
+    pub fn with_trivia(mut self, leading: Vec<Trivia<'src>>, trailing: Vec<Trivia<'src>>) -> Self {
+        self.trivia = (!leading.is_empty() || !trailing.is_empty())
+            .then(|| Box::new(TokenTrivia { leading, trailing }));
+        self
+    }
+
+    pub fn token(&self) -> &Token<'src> {
+        &self.token
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The token exactly as written in the source.
+    pub fn spelling(&self) -> &'src str {
+        self.spelling
+    }
+
+    pub fn leading_trivia(&self) -> &[Trivia<'src>] {
+        self.trivia.as_ref().map_or(&[], |trivia| &trivia.leading)
     }
 
-    pub fn tokens(&self) -> &[Token] {
-        &self.tokens
+    pub fn trailing_trivia(&self) -> &[Trivia<'src>] {
+        self.trivia.as_ref().map_or(&[], |trivia| &trivia.trailing)
+    }
+
+    pub fn into_token(self) -> Token<'src> {
+        self.token
     }
 }
 
-fn tokenize_str(text: &str) -> Vec<Token> {
-    let mut tokens: Vec<Token> = Vec::new();
-    let mut cs = CharacterStream::new(text);
-    loop {
-        cs.skip_whitespace();
+impl fmt::Display for SpannedToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for trivia in self.leading_trivia() {
+            f.write_str(trivia.text())?;
+        }
+        f.write_str(self.spelling)?;
+        for trivia in self.trailing_trivia() {
+            f.write_str(trivia.text())?;
+        }
+        Ok(())
+    }
+}
 
-        // Gambiarra da boa
-        if cs.is_eof() {
-            tokens.push(Token::Eof);
-            break;
+/// A lexer that produces tokens on demand as it is iterated.
+///
+/// The stream always ends with a single `Token::Eof`, after which the
+/// iterator is exhausted.
+pub struct Scanner<'src> {
+    cs: CharacterStream<'src>,
+    lookahead: VecDeque<SpannedToken<'src>>,
+    errors: Vec<LexError>,
+    finished: bool,
+    standard: Standard,
+    lossless: bool,
+    doc_comments: bool,
+    preprocessing: bool,
+    line_state: LineState,
+}
+
+/// Where the scanner is within a logical line, so that it can tell when a
+/// header name may follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineState {
+    Start,
+    /// After the `#` opening a directive.
+    Directive,
+    /// After `#include` or `__has_include(`.
+    Include,
+    /// After `__has_include`.
+    HasInclude,
+    Other,
+}
+
+impl<'src> Scanner<'src> {
+    pub fn new(text: &'src str) -> Self {
+        Self::with_edits(text, Vec::new())
+    }
+
+    /// Scans the text of `source` after phases 1 and 2, so that trigraphs and
+    /// line splices work anywhere, inside tokens included. Spans still point
+    /// into the original text, while spellings and trivia come from the
+    /// translated one.
+    pub fn from_source(source: &'src SourceText<'_>) -> Self {
+        Self::with_edits(source.text(), source.edits().to_vec())
+    }
+
+    pub(crate) fn with_edits(text: &'src str, edits: Vec<Edit>) -> Self {
+        Self {
+            cs: CharacterStream::with_edits(text, edits),
+            lookahead: VecDeque::new(),
+            errors: Vec::new(),
+            finished: false,
+            standard: Standard::default(),
+            lossless: false,
+            doc_comments: false,
+            preprocessing: false,
+            line_state: LineState::Start,
         }
-        skip_comments(&mut cs);
-        if cs.is_eof() {
-            tokens.push(Token::Eof);
-            break;
+    }
+
+    /// Selects the language standard deciding which words are keywords.
+    pub fn with_standard(mut self, standard: Standard) -> Self {
+        self.standard = standard;
+        self
+    }
+
+    pub fn next_token(&mut self) -> Option<Token<'src>> {
+        self.next().map(SpannedToken::into_token)
+    }
+
+    /// Looks at the next token without consuming it.
+    pub fn peek(&mut self) -> Option<&SpannedToken<'src>> {
+        self.peek_nth(0)
+    }
+
+    /// Looks `n` tokens ahead without consuming anything, so `peek_nth(0)` is
+    /// the same as `peek()`.
+    pub fn peek_nth(&mut self, n: usize) -> Option<&SpannedToken<'src>> {
+        while self.lookahead.len() <= n {
+            match self.scan_token() {
+                Some(token) => self.lookahead.push_back(token),
+                None => break,
+            }
+        }
+        self.lookahead.get(n)
+    }
+
+    /// Keeps the whitespace and comments around each token, so the input can
+    /// be rebuilt from the tokens.
+    pub fn with_trivia(mut self, lossless: bool) -> Self {
+        self.lossless = lossless;
+        self
+    }
+
+    /// Emits documentation comments as `Token::DocComment` instead of
+    /// skipping them.
+    pub fn with_doc_comments(mut self, doc_comments: bool) -> Self {
+        self.doc_comments = doc_comments;
+        self
+    }
+
+    /// Scans preprocessing tokens, as in translation phase 3: the ends of
+    /// lines become `Token::Newline`, the operand of an include becomes a
+    /// `Token::HeaderName`, and keywords are left as identifiers.
+    pub fn with_preprocessing(mut self, preprocessing: bool) -> Self {
+        self.preprocessing = preprocessing;
+        self
+    }
+
+    /// Merges adjacent string literals, as in `"foo" "bar"`, into a single
+    /// token.
+    pub fn concat_strings(self) -> ConcatStrings<'src, Self> {
+        let text = self.cs.text();
+        ConcatStrings::from_text(self, text)
+    }
+
+    /// Scans the rest of the input, dropping the spans.
+    pub fn tokens(self) -> Vec<Token<'src>> {
+        self.map(SpannedToken::into_token).collect()
+    }
+
+    /// The lexical errors found so far, including the ones of peeked tokens.
+    /// Each of them also shows up in the token stream as a `Token::Invalid`
+    /// holding the offending text.
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+
+    fn scan_token(&mut self) -> Option<SpannedToken<'src>> {
+        if self.finished {
+            return None;
         }
 
-        let token = match cs.current_char() {
-            '_' | 'a'..='z' | 'A'..='Z' => lex_identifier_or_keyword(&mut cs),
-            '0'..='9' | '.' => lex_number(&mut cs),
-            '"' => lex_string(&mut cs),
-            '\'' => lex_char(&mut cs),
+        let leading_trivia = self.lex_trivia(false);
+
+        let cs = &mut self.cs;
+        let start = cs.position();
+        let Some(char) = cs.current_char() else {
+            self.finished = true;
+            let eof = SpannedToken::new(Token::Eof, cs.original_span(start), "");
+            return Some(eof.with_trivia(leading_trivia, Vec::new()));
+        };
+        let token = match char {
+            '<' | '"' if self.line_state == LineState::Include => match lex_header_name(cs) {
+                Some(header_name) => Ok(header_name),
+                None if char == '"' => lex_string(cs),
+                None => lex_operator(cs),
+            },
+            'L' | 'u' | 'U' if literal_quote(cs, self.standard) == Some('"') => lex_string(cs),
+            'L' | 'u' | 'U' if literal_quote(cs, self.standard) == Some('\'') => lex_char(cs),
+            '_' | 'a'..='z' | 'A'..='Z' if self.preprocessing => Ok(Token::Id(lex_identifier(cs))),
+            '_' | 'a'..='z' | 'A'..='Z' => Ok(lex_identifier_or_keyword(cs, self.standard)),
+            '0'..='9' => lex_number(cs, self.standard, self.preprocessing),
+            '.' if cs.next_char().is_some_and(|c| c.is_ascii_digit()) => {
+                lex_number(cs, self.standard, self.preprocessing)
+            }
+            '"' => lex_string(cs),
+            '\'' => lex_char(cs),
+            '/' if self.doc_comments && is_doc_comment(cs) => lex_doc_comment(cs),
             '(' => {
                 cs.advance_by(1);
-                Token::OpenParen
+                Ok(Token::OpenParen)
             }
             ')' => {
                 cs.advance_by(1);
-                Token::CloseParen
+                Ok(Token::CloseParen)
             }
             '{' => {
                 cs.advance_by(1);
-                Token::OpenBrace
+                Ok(Token::OpenBrace)
             }
             '}' => {
                 cs.advance_by(1);
-                Token::CloseBrace
+                Ok(Token::CloseBrace)
             }
             '[' => {
                 cs.advance_by(1);
-                Token::OpenBracket
+                Ok(Token::OpenBracket)
             }
             ']' => {
                 cs.advance_by(1);
-                Token::CloseBracket
+                Ok(Token::CloseBracket)
             }
             ';' => {
                 cs.advance_by(1);
-                Token::SemiColon
+                Ok(Token::SemiColon)
+            }
+            ',' => {
+                cs.advance_by(1);
+                Ok(Token::Comma)
             }
-            _ => lex_operator(&mut cs),
+            '\n' => {
+                cs.advance_by(1);
+                Ok(Token::Newline)
+            }
+            _ => lex_operator(cs),
         };
 
-        tokens.push(token);
-    }
+        let span = cs.span_from(start);
+        let spelling = cs.slice(span);
+        let token = token.unwrap_or_else(|error| {
+            self.errors
+                .push(error.map_span(|span| cs.original_span(span)));
+            Token::Invalid(spelling)
+        });
+        let span = cs.original_span(span);
 
-    tokens
-}
+        if self.preprocessing {
+            self.line_state = match (self.line_state, &token) {
+                (_, Token::Newline) => LineState::Start,
+                (LineState::Start, Token::Hash) => LineState::Directive,
+                (LineState::Directive, Token::Id("include" | "include_next")) => LineState::Include,
+                (_, Token::Id("__has_include" | "__has_include_next")) => LineState::HasInclude,
+                (LineState::HasInclude, Token::OpenParen) => LineState::Include,
+                _ => LineState::Other,
+            };
+        }
+
+        let token = SpannedToken::new(token, span, spelling);
+        if !self.lossless {
+            // The trivia after the token is skipped with the leading trivia
+            // of the next one.
+            return Some(token);
+        }
+        let trailing_trivia = self.lex_trivia(true);
+        Some(token.with_trivia(leading_trivia, trailing_trivia))
+    }
 
-fn skip_comments(cs: &mut CharacterStream) {
-    if cs.current_char() == '/' {
-        match cs.next_char() {
-            Some(&'/') => {
-                while cs.current_char() != '\n' {
+    /// Skips the whitespace and comments at the cursor, keeping them only in
+    /// lossless mode. Trailing trivia stops before the end of the line.
+    fn lex_trivia(&mut self, trailing: bool) -> Vec<Trivia<'src>> {
+        let mut trivia = Vec::new();
+        let cs = &mut self.cs;
+        loop {
+            let start = cs.position();
+            let kind = match (cs.current_char(), cs.next_char()) {
+                _ if self.doc_comments && is_doc_comment(cs) => break,
+                (Some('\n'), _) if trailing || self.preprocessing => break,
+                (Some('\n'), _) => {
                     cs.advance_by(1);
+                    TriviaKind::Newline
                 }
-            
-                cs.skip_whitespace();
-
-                if cs.check_bounds() {
-                    skip_comments(cs);
+                (Some('\\'), Some('\n')) => {
+                    cs.advance_by(2);
+                    TriviaKind::LineContinuation
                 }
-            }
-            Some(&'*') => {
-                loop {
-                    cs.advance_by(1);
-                    if cs.current_char() == '*' && cs.next_char() == Some(&'/') {
-                        cs.advance_by(2);
-                        cs.skip_whitespace();
-                        break;
+                (Some('\\'), Some('\r')) if cs.nth_char(2) == Some('\n') => {
+                    cs.advance_by(3);
+                    TriviaKind::LineContinuation
+                }
+                (Some(char), _) if char.is_ascii_whitespace() => {
+                    cs.advance_while(|c| c.is_ascii_whitespace() && c != '\n');
+                    TriviaKind::Whitespace
+                }
+                (Some('/'), Some('/')) => {
+                    cs.advance_while(|c| c != '\n');
+                    TriviaKind::LineComment
+                }
+                (Some('/'), Some('*')) => {
+                    if let Err(error) = lex_block_comment(cs) {
+                        self.errors
+                            .push(error.map_span(|span| cs.original_span(span)));
                     }
+                    TriviaKind::BlockComment
                 }
+                _ => break,
+            };
 
-                if cs.check_bounds() {
-                    skip_comments(cs);
-                }
+            if self.lossless {
+                let span = cs.span_from(start);
+                trivia.push(Trivia::new(kind, cs.original_span(span), cs.slice(span)));
             }
-            Some(_) | None => (),
         }
+        trivia
     }
 }
 
-fn lex_identifier_or_keyword(cs: &mut CharacterStream) -> Token {
-    let mut id = String::new();
-    while cs.current_char().is_alphanumeric() || cs.current_char() == '_' {
-        id.push(cs.current_char());
-        cs.advance_by(1);
+impl<'src> Iterator for Scanner<'src> {
+    type Item = SpannedToken<'src>;
+
+    fn next(&mut self) -> Option<SpannedToken<'src>> {
+        self.lookahead.pop_front().or_else(|| self.scan_token())
     }
+}
 
-    match id.as_str() {
-        "for" => Token::Keyword(KeywordType::For),
-        "while" => Token::Keyword(KeywordType::While),
-        "if" => Token::Keyword(KeywordType::If),
-        "else" => Token::Keyword(KeywordType::Else),
-        "int" => Token::Keyword(KeywordType::Int),
-        "float" => Token::Keyword(KeywordType::Float),
-        "double" => Token::Keyword(KeywordType::Double),
-        "long" => Token::Keyword(KeywordType::Long),
-        "return" => Token::Keyword(KeywordType::Return),
-        "char" => Token::Keyword(KeywordType::Char),
-        _ => Token::Id(id),
+impl FusedIterator for Scanner<'_> {}
+
+/// Skips the block comment starting at the cursor.
+fn lex_block_comment(cs: &mut CharacterStream<'_>) -> Result<(), LexError> {
+    let start = cs.position();
+    cs.advance_by(2);
+    loop {
+        match (cs.current_char(), cs.next_char()) {
+            (Some('*'), Some('/')) => {
+                cs.advance_by(2);
+                return Ok(());
+            }
+            (Some(_), _) => cs.advance_by(1),
+            (None, _) => {
+                return Err(LexError::UnterminatedComment {
+                    span: cs.span_from(start),
+                })
+            }
+        }
     }
 }
 
-fn lex_number(cs: &mut CharacterStream) -> Token {
-    let mut num = String::new();
-    while cs.current_char().is_ascii_digit() {
-        num.push(cs.current_char());
-        cs.advance_by(1);
+/// Whether the cursor is at a Doxygen comment. Banners such as `/*****` and
+/// `////` don't count, nor does the empty comment `/**/`.
+fn is_doc_comment(cs: &CharacterStream<'_>) -> bool {
+    match (cs.current_char(), cs.next_char(), cs.nth_char(2)) {
+        (Some('/'), Some('*'), Some('*')) => !matches!(cs.nth_char(3), Some('*' | '/')),
+        (Some('/'), Some('/'), Some('/')) => cs.nth_char(3) != Some('/'),
+        (Some('/'), Some('*' | '/'), Some('!')) => true,
+        _ => false,
     }
+}
 
-    if cs.current_char().is_alphanumeric() {
-        let mut invalid_id = String::with_capacity(num.len());
-        invalid_id.push_str(&num);
+fn lex_doc_comment<'src>(cs: &mut CharacterStream<'src>) -> Result<Token<'src>, LexError> {
+    let start = cs.position();
+    let is_block = cs.next_char() == Some('*');
+    if is_block {
+        lex_block_comment(cs)?;
+    } else {
+        cs.advance_while(|c| c != '\n');
+    }
 
-        while cs.current_char().is_alphanumeric() {
-            invalid_id.push(cs.current_char());
-            cs.advance_by(1);
-        }
-        return Token::Invalid(format!("Invalid identifier \"{}\"!", invalid_id));
+    let spelling = cs.slice(cs.span_from(start));
+    let end = if is_block {
+        spelling.len() - 2
+    } else {
+        spelling.len()
+    };
+    Ok(Token::DocComment(&spelling[3..end]))
+}
+
+fn lex_identifier<'src>(cs: &mut CharacterStream<'src>) -> &'src str {
+    let start = cs.position();
+    cs.advance_while(|c| c.is_alphanumeric() || c == '_');
+    cs.slice(cs.span_from(start))
+}
+
+fn lex_identifier_or_keyword<'src>(
+    cs: &mut CharacterStream<'src>,
+    standard: Standard,
+) -> Token<'src> {
+    let id = lex_identifier(cs);
+    match KeywordType::lookup(id, standard) {
+        Some(keyword) => Token::Keyword(keyword),
+        None => Token::Id(id),
     }
+}
 
-    if cs.current_char() == '.' {
-        num.push(cs.current_char());
-        cs.advance_by(1);
+/// Lexes a header name, provided it is closed on the same line.
+fn lex_header_name<'src>(cs: &mut CharacterStream<'src>) -> Option<Token<'src>> {
+    let rest = cs.rest();
+    let close = if rest.starts_with('<') { '>' } else { '"' };
+    let line = &rest[1..rest.find('\n').unwrap_or(rest.len())];
+    let header_name = &rest[..line.find(close)? + 2];
+    cs.advance_by(header_name.chars().count());
+    Some(Token::HeaderName(header_name))
+}
 
-        while cs.current_char().is_ascii_digit() {
-            num.push(cs.current_char());
-            cs.advance_by(1);
-        }
+fn lex_number<'src>(
+    cs: &mut CharacterStream<'src>,
+    standard: Standard,
+    preprocessing: bool,
+) -> Result<Token<'src>, LexError> {
+    let start = cs.position();
+    cs.advance_by(1);
 
-        if cs.current_char() == 'f' {
-            cs.advance_by(1);
+    // Grab everything that could belong to the number, like the standard's
+    // preprocessing numbers, and sort out what it means afterwards.
+    loop {
+        match (cs.current_char(), cs.next_char()) {
+            (Some('e' | 'E' | 'p' | 'P'), Some('+' | '-')) => cs.advance_by(2),
+            (Some('\''), Some(next)) if standard >= Standard::C23 && next.is_alphanumeric() => {
+                cs.advance_by(2)
+            }
+            (Some(char), _) if char.is_alphanumeric() || char == '_' || char == '.' => {
+                cs.advance_by(1)
+            }
+            _ => break,
         }
     }
 
-    Token::Number(num)
+    let span = cs.span_from(start);
+    let raw = cs.slice(span);
+    if preprocessing {
+        return Ok(Token::PpNumber(raw));
+    }
+    check_number(raw, span).map(Token::Number)
+}
+
+/// Checks that the preprocessing number `raw`, found at `span`, is a valid
+/// constant.
+pub(crate) fn check_number(raw: &str, span: Span) -> Result<NumberLiteral<'_>, LexError> {
+    match parse_number(raw) {
+        Ok((radix, kind)) => Ok(NumberLiteral::new(raw, radix, kind)),
+        Err(NumberError::InvalidSuffix) => Err(LexError::InvalidIdentifier {
+            text: raw.to_string(),
+            span,
+        }),
+        Err(NumberError::Malformed) => Err(LexError::InvalidNumber {
+            text: raw.to_string(),
+            span,
+        }),
+        Err(NumberError::Overflow) => Err(LexError::IntegerOverflow {
+            text: raw.to_string(),
+            span,
+        }),
+        Err(NumberError::FloatOverflow) => Err(LexError::FloatOverflow {
+            text: raw.to_string(),
+            span,
+        }),
+    }
 }
 
-fn lex_string(cs: &mut CharacterStream) -> Token {
-    let mut string = String::new();
+/// The quote opening a string literal or character constant after an
+/// encoding prefix at the cursor, if there is one.
+fn literal_quote(cs: &CharacterStream<'_>, standard: Standard) -> Option<char> {
+    match (cs.current_char()?, cs.next_char()?) {
+        ('L' | 'u' | 'U', quote @ ('"' | '\'')) => Some(quote),
+        ('u', '8') => match cs.nth_char(2)? {
+            '"' => Some('"'),
+            '\'' if standard >= Standard::C23 => Some('\''),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Skips the encoding prefix of a literal, if any, and the opening quote.
+fn lex_literal_start(cs: &mut CharacterStream<'_>) -> Encoding {
+    let encoding = match (cs.current_char(), cs.next_char()) {
+        (Some('u'), Some('8')) => Encoding::Utf8,
+        (Some('u'), _) => Encoding::Utf16,
+        (Some('U'), _) => Encoding::Utf32,
+        (Some('L'), _) => Encoding::Wide,
+        _ => Encoding::Plain,
+    };
+    cs.advance_by(encoding.prefix().len() + 1);
+    encoding
+}
+
+fn lex_string<'src>(cs: &mut CharacterStream<'src>) -> Result<Token<'src>, LexError> {
+    let start = cs.position();
+    let encoding = lex_literal_start(cs);
+    let is_wide = !matches!(encoding, Encoding::Plain | Encoding::Utf8);
+
+    let contents = cs.position();
+    // The bytes of a plain or `u8` literal are only copied once an escape
+    // sequence shows up.
+    let mut bytes: Option<Vec<u8>> = None;
+    let mut wide = Vec::new();
+    let mut error = None;
+    loop {
+        let char = match cs.current_char() {
+            Some('"') => break,
+            Some('\n') | None => {
+                return Err(LexError::UnterminatedString {
+                    span: cs.span_from(start),
+                })
+            }
+            Some('\\') => {
+                if !is_wide && bytes.is_none() {
+                    bytes = Some(cs.slice(cs.span_from(contents)).as_bytes().to_vec());
+                }
+                match lex_escape(cs, encoding.max_code_unit()) {
+                    Ok(Escape::Char(char)) => char,
+                    Ok(Escape::Unit(unit)) => {
+                        match &mut bytes {
+                            // The unit fits, as it is at most the maximum.
+                            Some(bytes) => bytes.push(unit as u8),
+                            None => wide.push(unit),
+                        }
+                        continue;
+                    }
+                    Err(escape_error) => {
+                        error.get_or_insert(escape_error);
+                        continue;
+                    }
+                }
+            }
+            Some(char) => {
+                cs.advance_by(1);
+                char
+            }
+        };
+        if is_wide {
+            push_wide_char(&mut wide, char, encoding);
+        } else if let Some(bytes) = &mut bytes {
+            bytes.extend_from_slice(char.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+    }
+    let contents = cs.span_from(contents);
     cs.advance_by(1);
 
-    while cs.current_char() != '"' {
-        string.push(cs.current_char());
-        cs.advance_by(1);
+    if let Some(error) = error {
+        return Err(error);
     }
+    let units = match bytes {
+        _ if is_wide => CodeUnits::Wide(wide),
+        Some(bytes) => CodeUnits::Bytes(Cow::Owned(bytes)),
+        None => CodeUnits::Bytes(Cow::Borrowed(cs.slice(contents).as_bytes())),
+    };
+
+    Ok(Token::String(StringLiteral::new(
+        cs.slice(cs.span_from(start)),
+        units,
+    )))
+}
 
-    if cs.current_char() != '"' {
-        return Token::Invalid("missing terminating \"".to_string());
+fn lex_char<'src>(cs: &mut CharacterStream<'src>) -> Result<Token<'src>, LexError> {
+    let start = cs.position();
+    let encoding = lex_literal_start(cs);
+
+    let mut value: u32 = 0;
+    let mut length = 0;
+    let mut push_unit = |unit: u32| {
+        value = match encoding {
+            Encoding::Plain => (value << 8) | unit,
+            _ => unit,
+        };
+        length += 1;
+    };
+    let mut error = None;
+    loop {
+        match cs.current_char() {
+            Some('\'') => break,
+            Some('\n') | None => {
+                return Err(LexError::UnterminatedChar {
+                    span: cs.span_from(start),
+                })
+            }
+            Some('\\') => match lex_escape(cs, encoding.max_code_unit()) {
+                Ok(Escape::Unit(unit)) => push_unit(unit),
+                Ok(Escape::Char(char)) => push_unit(char.into()),
+                Err(escape_error) => {
+                    error.get_or_insert(escape_error);
+                }
+            },
+            // Without a prefix, characters outside of ASCII take one byte
+            // per UTF-8 code unit, just like in a string.
+            Some(char) if encoding == Encoding::Plain => {
+                cs.advance_by(1);
+                char.encode_utf8(&mut [0; 4])
+                    .bytes()
+                    .for_each(|byte| push_unit(byte.into()));
+            }
+            Some(char) => {
+                cs.advance_by(1);
+                push_unit(char.into());
+            }
+        }
     }
     cs.advance_by(1);
 
-    string.push('\0');
+    if let Some(error) = error {
+        return Err(error);
+    }
+    if length == 0 {
+        return Err(LexError::EmptyChar {
+            span: cs.span_from(start),
+        });
+    }
+    if length > 1 && encoding != Encoding::Plain {
+        return Err(LexError::MultiCharacterChar {
+            span: cs.span_from(start),
+        });
+    }
+
+    Ok(Token::Char(CharLiteral::new(
+        cs.slice(cs.span_from(start)),
+        value,
+    )))
+}
 
-    Token::String(string)
+/// A decoded escape sequence.
+enum Escape {
+    /// The value of a simple, octal or hexadecimal escape, which is a code
+    /// unit of the literal.
+    Unit(u32),
+    /// A universal character name, which the literal encodes like any other
+    /// character.
+    Char(char),
 }
 
-fn lex_char(cs: &mut CharacterStream) -> Token {
+/// Decodes the escape sequence starting at the backslash under the cursor.
+/// Octal and hexadecimal escapes must not exceed `max`, the largest value of
+/// the literal's code unit.
+fn lex_escape(cs: &mut CharacterStream<'_>, max: u32) -> Result<Escape, LexError> {
+    let start = cs.position();
     cs.advance_by(1);
-    let char = if cs.current_char() == '\'' {
-        '\0'
-    } else {
-        cs.current_char()
+
+    let invalid = |cs: &CharacterStream<'_>| {
+        let span = cs.span_from(start);
+        LexError::InvalidEscape {
+            sequence: cs.slice(span).to_string(),
+            span,
+        }
+    };
+    let Some(char) = cs.current_char().filter(|&c| c != '\n') else {
+        return Err(invalid(cs));
+    };
+
+    let value = match char {
+        '\'' | '"' | '?' | '\\' => char.into(),
+        'a' => 0x07,
+        'b' => 0x08,
+        'f' => 0x0C,
+        'n' => 0x0A,
+        'r' => 0x0D,
+        't' => 0x09,
+        'v' => 0x0B,
+        '0'..='7' => {
+            let value = lex_digits(cs, 8, 3);
+            return check_escape_range(cs, start, value, max);
+        }
+        'x' => {
+            cs.advance_by(1);
+            if !cs.current_char().is_some_and(|c| c.is_ascii_hexdigit()) {
+                return Err(invalid(cs));
+            }
+            let value = lex_digits(cs, 16, usize::MAX);
+            return check_escape_range(cs, start, value, max);
+        }
+        'u' | 'U' => {
+            cs.advance_by(1);
+            let length = if char == 'u' { 4 } else { 8 };
+            let digits = cs.position();
+            let value = lex_digits(cs, 16, length);
+            // Universal character names can't name surrogates or characters
+            // of the basic character set, other than `$`, `@` and `` ` ``.
+            let char = char::from_u32(value)
+                .filter(|_| cs.span_from(digits).len() == length)
+                .filter(|_| value >= 0xA0 || matches!(value, 0x24 | 0x40 | 0x60));
+            return char.map(Escape::Char).ok_or_else(|| invalid(cs));
+        }
+        _ => {
+            cs.advance_by(1);
+            return Err(invalid(cs));
+        }
     };
     cs.advance_by(1);
 
-    if cs.current_char().is_ascii_alphanumeric() {
-        return Token::Invalid("char literals should only have one character".to_string());
-    }
+    Ok(Escape::Unit(value))
+}
 
-    if cs.current_char() != '\'' {
-        return Token::Invalid("missing terminating \'".to_string());
+/// Reads up to `max_length` digits in `radix`, saturating on overflow.
+fn lex_digits(cs: &mut CharacterStream<'_>, radix: u32, max_length: usize) -> u32 {
+    let mut value: u32 = 0;
+    let mut length = 0;
+    while let Some(digit) = cs.current_char().and_then(|c| c.to_digit(radix)) {
+        if length == max_length {
+            break;
+        }
+        value = value.saturating_mul(radix).saturating_add(digit);
+        length += 1;
+        cs.advance_by(1);
     }
-    cs.advance_by(1);
+    value
+}
 
-    Token::Char(char)
+fn check_escape_range(
+    cs: &CharacterStream<'_>,
+    start: Span,
+    value: u32,
+    max: u32,
+) -> Result<Escape, LexError> {
+    if value > max {
+        let span = cs.span_from(start);
+        return Err(LexError::EscapeOutOfRange {
+            sequence: cs.slice(span).to_string(),
+            span,
+        });
+    }
+    Ok(Escape::Unit(value))
 }
 
-fn lex_operator(cs: &mut CharacterStream) -> Token {
-    let next_char = match cs.next_char() {
-        Some(&next_char) => next_char,
-        None => return Token::Eof,
+fn lex_operator<'src>(cs: &mut CharacterStream<'src>) -> Result<Token<'src>, LexError> {
+    let start = cs.position();
+    let Some(char) = cs.current_char() else {
+        return Ok(Token::Eof);
     };
+    let next_char = cs.next_char();
+    let third_char = cs.nth_char(2);
 
-    let (token, advance_total) = match cs.current_char() {
+    // Digraphs spell some punctuators with other characters, e.g. `<:` is `[`
+    // and `%:%:` is `##`.
+    let (token, advance_total) = match char {
         '+' => match next_char {
-            '=' => (Token::Operator(OperatorType::PlusEqual), 2),
-            '+' => (Token::Operator(OperatorType::Increment), 2),
+            Some('=') => (Token::Operator(OperatorType::PlusEqual), 2),
+            Some('+') => (Token::Operator(OperatorType::Increment), 2),
             _ => (Token::Operator(OperatorType::Plus), 1),
         },
         '-' => match next_char {
-            '=' => (Token::Operator(OperatorType::MinusEqual), 2),
-            '-' => (Token::Operator(OperatorType::Decrement), 2),
+            Some('=') => (Token::Operator(OperatorType::MinusEqual), 2),
+            Some('-') => (Token::Operator(OperatorType::Decrement), 2),
+            Some('>') => (Token::Operator(OperatorType::Arrow), 2),
             _ => (Token::Operator(OperatorType::Minus), 1),
         },
         '>' => match next_char {
-            '=' => (Token::Operator(OperatorType::GreaterThanOrEqual), 2),
+            Some('=') => (Token::Operator(OperatorType::GreaterThanOrEqual), 2),
+            Some('>') => match third_char {
+                Some('=') => (Token::Operator(OperatorType::ShiftRightEqual), 3),
+                _ => (Token::Operator(OperatorType::ShiftRight), 2),
+            },
             _ => (Token::Operator(OperatorType::GreaterThan), 1),
         },
         '<' => match next_char {
-            '=' => (Token::Operator(OperatorType::LessThanOrEqual), 2),
+            Some('=') => (Token::Operator(OperatorType::LessThanOrEqual), 2),
+            Some('<') => match third_char {
+                Some('=') => (Token::Operator(OperatorType::ShiftLeftEqual), 3),
+                _ => (Token::Operator(OperatorType::ShiftLeft), 2),
+            },
+            Some(':') => (Token::OpenBracket, 2),
+            Some('%') => (Token::OpenBrace, 2),
             _ => (Token::Operator(OperatorType::LessThan), 1),
         },
         '&' => match next_char {
-            '&' => (Token::Operator(OperatorType::And), 2),
+            Some('&') => (Token::Operator(OperatorType::And), 2),
+            Some('=') => (Token::Operator(OperatorType::BitwiseAndEqual), 2),
             _ => (Token::Operator(OperatorType::BitwiseAnd), 1),
         },
         '|' => match next_char {
-            '|' => (Token::Operator(OperatorType::Or), 2),
+            Some('|') => (Token::Operator(OperatorType::Or), 2),
+            Some('=') => (Token::Operator(OperatorType::BitwiseOrEqual), 2),
             _ => (Token::Operator(OperatorType::BitwiseOr), 1),
         },
+        '^' => match next_char {
+            Some('=') => (Token::Operator(OperatorType::BitwiseXorEqual), 2),
+            _ => (Token::Operator(OperatorType::BitwiseXor), 1),
+        },
         '=' => match next_char {
-            '=' => (Token::Operator(OperatorType::Equals), 2),
+            Some('=') => (Token::Operator(OperatorType::Equals), 2),
             _ => (Token::Operator(OperatorType::Assign), 1),
         },
         '!' => match next_char {
-            '=' => (Token::Operator(OperatorType::NotEqual), 2),
+            Some('=') => (Token::Operator(OperatorType::NotEqual), 2),
             _ => (Token::Operator(OperatorType::ExclamationPoint), 1),
         },
         '*' => match next_char {
-            '=' => (Token::Operator(OperatorType::AsteriskEqual), 2),
+            Some('=') => (Token::Operator(OperatorType::AsteriskEqual), 2),
             _ => (Token::Operator(OperatorType::Asterisk), 1),
         },
         '/' => match next_char {
-            '=' => (Token::Operator(OperatorType::DivideEqual), 2),
+            Some('=') => (Token::Operator(OperatorType::DivideEqual), 2),
             _ => (Token::Operator(OperatorType::Divide), 1),
         },
-        char => (Token::Invalid(format!("Invalid symbol \"{}\"!", char)), 1),
+        '%' => match next_char {
+            Some('=') => (Token::Operator(OperatorType::ModuloEqual), 2),
+            Some('>') => (Token::CloseBrace, 2),
+            Some(':') => match (third_char, cs.nth_char(3)) {
+                (Some('%'), Some(':')) => (Token::HashHash, 4),
+                _ => (Token::Hash, 2),
+            },
+            _ => (Token::Operator(OperatorType::Modulo), 1),
+        },
+        ':' => match next_char {
+            Some('>') => (Token::CloseBracket, 2),
+            _ => (Token::Colon, 1),
+        },
+        '.' => match (next_char, third_char) {
+            (Some('.'), Some('.')) => (Token::Ellipsis, 3),
+            _ => (Token::Operator(OperatorType::Dot), 1),
+        },
+        '#' => match next_char {
+            Some('#') => (Token::HashHash, 2),
+            _ => (Token::Hash, 1),
+        },
+        '~' => (Token::Operator(OperatorType::BitwiseNot), 1),
+        '?' => (Token::Operator(OperatorType::Question), 1),
+        symbol => {
+            cs.advance_by(1);
+            return Err(LexError::InvalidSymbol {
+                symbol,
+                span: cs.span_from(start),
+            });
+        }
     };
     cs.advance_by(advance_total);
 
-    token
+    Ok(token)
 }
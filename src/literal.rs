@@ -0,0 +1,425 @@
+use std::borrow::Cow;
+
+/// The encoding prefix of a string literal or character constant, which
+/// decides the type of its elements.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// No prefix: `char`.
+    #[default]
+    Plain,
+    /// `L`: `wchar_t`.
+    Wide,
+    /// `u8`: `char` (`char8_t` in C23) holding UTF-8.
+    Utf8,
+    /// `u`: `char16_t`.
+    Utf16,
+    /// `U`: `char32_t`.
+    Utf32,
+}
+
+impl Encoding {
+    /// The encoding named by the prefix of a literal's spelling.
+    pub fn of_literal(raw: &str) -> Self {
+        if raw.starts_with("u8") {
+            Encoding::Utf8
+        } else if raw.starts_with('u') {
+            Encoding::Utf16
+        } else if raw.starts_with('U') {
+            Encoding::Utf32
+        } else if raw.starts_with('L') {
+            Encoding::Wide
+        } else {
+            Encoding::Plain
+        }
+    }
+
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Encoding::Plain => "",
+            Encoding::Wide => "L",
+            Encoding::Utf8 => "u8",
+            Encoding::Utf16 => "u",
+            Encoding::Utf32 => "U",
+        }
+    }
+
+    /// The largest value of a single element, assuming a 32-bit `wchar_t`.
+    pub fn max_code_unit(&self) -> u32 {
+        match self {
+            Encoding::Plain | Encoding::Utf8 => u8::MAX.into(),
+            Encoding::Utf16 => u16::MAX.into(),
+            Encoding::Wide | Encoding::Utf32 => u32::MAX,
+        }
+    }
+}
+
+/// A string literal, keeping both its spelling in the source and the code
+/// units it stands for once escape sequences are decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringLiteral<'src> {
+    raw: &'src str,
+    units: CodeUnits<'src>,
+    encoding: Encoding,
+}
+
+impl<'src> StringLiteral<'src> {
+    /// Creates a literal whose encoding comes from the prefix of `raw`.
+    pub fn new(raw: &'src str, units: CodeUnits<'src>) -> Self {
+        Self {
+            raw,
+            units,
+            encoding: Encoding::of_literal(raw),
+        }
+    }
+
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// The literal as written, prefix and quotes included.
+    pub fn raw(&self) -> &'src str {
+        self.raw
+    }
+
+    /// The decoded contents, without the terminating null.
+    pub fn units(&self) -> &CodeUnits<'src> {
+        &self.units
+    }
+
+    /// The decoded contents as text, or `None` if they aren't valid Unicode,
+    /// like the byte of `"\xff"` or the lone surrogate of `u"\xD800"`. It
+    /// borrows from the source unless the literal has escape sequences or a
+    /// prefix wider than `u8`.
+    pub fn value(&self) -> Option<Cow<'_, str>> {
+        match &self.units {
+            CodeUnits::Bytes(bytes) => std::str::from_utf8(bytes).ok().map(Cow::Borrowed),
+            CodeUnits::Wide(units) if self.encoding == Encoding::Utf16 => {
+                let units = units.iter().map(|&unit| unit as u16);
+                char::decode_utf16(units)
+                    .collect::<Result<String, _>>()
+                    .ok()
+                    .map(Cow::Owned)
+            }
+            CodeUnits::Wide(units) => units
+                .iter()
+                .map(|&unit| char::from_u32(unit))
+                .collect::<Option<String>>()
+                .map(Cow::Owned),
+        }
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+}
+
+/// The elements of a string literal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodeUnits<'src> {
+    /// The `char` elements of a plain or `u8` literal: the UTF-8 encoding of
+    /// each character, or the value of an octal or hexadecimal escape.
+    Bytes(Cow<'src, [u8]>),
+    /// The elements of a `u`, `U` or `L` literal: UTF-16 code units for `u`
+    /// and code points otherwise, unless escapes give other values.
+    Wide(Vec<u32>),
+}
+
+impl<'src> CodeUnits<'src> {
+    /// The units in a literal of the wider `encoding`. Bytes that aren't
+    /// part of a UTF-8 sequence, like those of `"\xff"`, keep their value.
+    pub(crate) fn widen(&self, encoding: Encoding) -> Vec<u32> {
+        let bytes = match self {
+            CodeUnits::Bytes(bytes) => bytes,
+            CodeUnits::Wide(units) => return units.clone(),
+        };
+        let mut units = Vec::with_capacity(bytes.len());
+        for chunk in bytes.utf8_chunks() {
+            for char in chunk.valid().chars() {
+                push_wide_char(&mut units, char, encoding);
+            }
+            units.extend(chunk.invalid().iter().map(|&byte| u32::from(byte)));
+        }
+        units
+    }
+}
+
+impl<'src> From<&'src str> for CodeUnits<'src> {
+    fn from(text: &'src str) -> Self {
+        CodeUnits::Bytes(Cow::Borrowed(text.as_bytes()))
+    }
+}
+
+/// Appends `char` to the units of a literal of the wide `encoding`.
+pub(crate) fn push_wide_char(units: &mut Vec<u32>, char: char, encoding: Encoding) {
+    if encoding == Encoding::Utf16 {
+        let mut buffer = [0; 2];
+        units.extend(
+            char.encode_utf16(&mut buffer)
+                .iter()
+                .map(|&unit| u32::from(unit)),
+        );
+    } else {
+        units.push(char.into());
+    }
+}
+
+/// A character constant, keeping both its spelling in the source and its
+/// decoded value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharLiteral<'src> {
+    raw: &'src str,
+    value: u32,
+    encoding: Encoding,
+}
+
+impl<'src> CharLiteral<'src> {
+    /// Creates a constant whose encoding comes from the prefix of `raw`.
+    pub fn new(raw: &'src str, value: u32) -> Self {
+        Self {
+            raw,
+            value,
+            encoding: Encoding::of_literal(raw),
+        }
+    }
+
+    /// The constant as written, prefix and quotes included.
+    pub fn raw(&self) -> &'src str {
+        self.raw
+    }
+
+    /// The value of the constant. Multi-character constants like `'ab'`
+    /// pack one byte per character, the first one being the most
+    /// significant, as GCC and Clang do.
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// The value as a `char`, if it is a Unicode scalar value.
+    pub fn as_char(&self) -> Option<char> {
+        char::from_u32(self.value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Radix {
+    Binary,
+    Octal,
+    Decimal,
+    Hexadecimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntegerSuffix {
+    None,
+    Unsigned,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FloatSuffix {
+    None,
+    Float,
+    LongDouble,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumberKind {
+    Integer { value: u64, suffix: IntegerSuffix },
+    Float { value: f64, suffix: FloatSuffix },
+}
+
+/// A numeric constant, classified by radix and suffix and holding its parsed
+/// value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberLiteral<'src> {
+    raw: &'src str,
+    radix: Radix,
+    kind: NumberKind,
+}
+
+impl<'src> NumberLiteral<'src> {
+    pub fn new(raw: &'src str, radix: Radix, kind: NumberKind) -> Self {
+        Self { raw, radix, kind }
+    }
+
+    /// The constant as written, suffix and digit separators included.
+    pub fn raw(&self) -> &'src str {
+        self.raw
+    }
+
+    pub fn radix(&self) -> Radix {
+        self.radix
+    }
+
+    pub fn kind(&self) -> NumberKind {
+        self.kind
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self.kind, NumberKind::Integer { .. })
+    }
+}
+
+/// Why the spelling of a number couldn't be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NumberError {
+    InvalidSuffix,
+    Malformed,
+    Overflow,
+    FloatOverflow,
+}
+
+/// Parses the spelling of a numeric constant, which may contain C23 digit
+/// separators.
+pub(crate) fn parse_number(raw: &str) -> Result<(Radix, NumberKind), NumberError> {
+    let text: Cow<str> = if raw.contains('\'') {
+        Cow::Owned(raw.replace('\'', ""))
+    } else {
+        Cow::Borrowed(raw)
+    };
+    let bytes = text.as_bytes();
+
+    let (mut radix, prefix) = match bytes {
+        [b'0', b'x' | b'X', ..] => (Radix::Hexadecimal, 2),
+        [b'0', b'b' | b'B', ..] => (Radix::Binary, 2),
+        _ => (Radix::Decimal, 0),
+    };
+    let is_digit = |byte: &u8| match radix {
+        Radix::Hexadecimal => byte.is_ascii_hexdigit(),
+        _ => byte.is_ascii_digit(),
+    };
+    let count_digits = |from: usize| bytes[from..].iter().take_while(|b| is_digit(b)).count();
+
+    let mut end = prefix;
+    let integer_digits = count_digits(end);
+    end += integer_digits;
+
+    let mut fraction_digits = 0;
+    let mut is_float = false;
+    if bytes.get(end) == Some(&b'.') && radix != Radix::Binary {
+        is_float = true;
+        fraction_digits = count_digits(end + 1);
+        end += 1 + fraction_digits;
+    }
+    if integer_digits + fraction_digits == 0 {
+        return Err(NumberError::Malformed);
+    }
+    let mantissa_end = end;
+
+    let mut exponent: Option<i32> = None;
+    let exponent_marker: &[u8] = match radix {
+        Radix::Hexadecimal => b"pP",
+        _ => b"eE",
+    };
+    if bytes.get(end).is_some_and(|b| exponent_marker.contains(b)) && radix != Radix::Binary {
+        is_float = true;
+        end += 1;
+        let sign_start = end;
+        if matches!(bytes.get(end), Some(b'+' | b'-')) {
+            end += 1;
+        }
+        let digits = bytes[end..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits == 0 {
+            return Err(NumberError::Malformed);
+        }
+        end += digits;
+        let saturated = if bytes[sign_start] == b'-' {
+            i32::MIN
+        } else {
+            i32::MAX
+        };
+        exponent = Some(text[sign_start..end].parse().unwrap_or(saturated));
+    }
+
+    let suffix = &text[end..];
+    if suffix.contains('.') {
+        return Err(NumberError::Malformed);
+    }
+    if is_float {
+        if radix == Radix::Hexadecimal && exponent.is_none() {
+            return Err(NumberError::Malformed);
+        }
+        let suffix = match suffix {
+            "" => FloatSuffix::None,
+            "f" | "F" => FloatSuffix::Float,
+            "l" | "L" => FloatSuffix::LongDouble,
+            _ => return Err(NumberError::InvalidSuffix),
+        };
+        let value = match radix {
+            Radix::Hexadecimal => {
+                let digits = text[prefix..mantissa_end].bytes().filter(|&b| b != b'.');
+                let mantissa = digits.fold(0.0, |value, digit| {
+                    value * 16.0 + f64::from((digit as char).to_digit(16).unwrap_or(0))
+                });
+                let fraction_bits = 4 * fraction_digits as i32;
+                mantissa * 2f64.powi(exponent.unwrap_or(0).saturating_sub(fraction_bits))
+            }
+            _ => text[..end].parse().map_err(|_| NumberError::Malformed)?,
+        };
+        // A `long double` may have a wider range than the `f64` holding its
+        // value, so only the other types are checked.
+        let is_finite = match suffix {
+            FloatSuffix::None => value.is_finite(),
+            FloatSuffix::Float => (value as f32).is_finite(),
+            FloatSuffix::LongDouble => true,
+        };
+        if !is_finite {
+            return Err(NumberError::FloatOverflow);
+        }
+        return Ok((radix, NumberKind::Float { value, suffix }));
+    }
+
+    let suffix = parse_integer_suffix(suffix).ok_or(NumberError::InvalidSuffix)?;
+    let digits = &text[prefix..end];
+    if radix == Radix::Decimal && digits.len() > 1 && digits.starts_with('0') {
+        radix = Radix::Octal;
+    }
+    let base = match radix {
+        Radix::Binary => 2,
+        Radix::Octal => 8,
+        Radix::Decimal => 10,
+        Radix::Hexadecimal => 16,
+    };
+    let mut value: u64 = 0;
+    for digit in digits.chars() {
+        let digit = digit.to_digit(base).ok_or(NumberError::Malformed)?;
+        value = value
+            .checked_mul(base.into())
+            .and_then(|value| value.checked_add(digit.into()))
+            .ok_or(NumberError::Overflow)?;
+    }
+
+    Ok((radix, NumberKind::Integer { value, suffix }))
+}
+
+fn parse_integer_suffix(suffix: &str) -> Option<IntegerSuffix> {
+    let (unsigned, long) = if let Some(long) = suffix.strip_prefix(['u', 'U']) {
+        (true, long)
+    } else if let Some(long) = suffix.strip_suffix(['u', 'U']) {
+        (true, long)
+    } else {
+        (false, suffix)
+    };
+
+    let suffix = match (unsigned, long) {
+        (false, "") => IntegerSuffix::None,
+        (true, "") => IntegerSuffix::Unsigned,
+        (false, "l" | "L") => IntegerSuffix::Long,
+        (true, "l" | "L") => IntegerSuffix::UnsignedLong,
+        (false, "ll" | "LL") => IntegerSuffix::LongLong,
+        (true, "ll" | "LL") => IntegerSuffix::UnsignedLongLong,
+        _ => return None,
+    };
+    Some(suffix)
+}
@@ -1,6 +1,92 @@
-use scanner::Scanner;
+use std::env;
+use std::fs;
+use std::process;
 
+use scanner::{Arena, IncludeResolver, Parser, Preprocessor, Scanner};
+
+/// Preprocesses the file given on the command line, taking `-D`, `-U`, `-I`
+/// and `-isystem` options like GCC. Without a file, scans an example.
 fn main() {
+    let mut defines = Vec::new();
+    let mut resolver = IncludeResolver::default();
+    let mut path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| match arg.strip_prefix(flag) {
+            Some("") => args
+                .next()
+                .unwrap_or_else(|| usage(&format!("{} needs a value", flag))),
+            Some(value) => value.to_string(),
+            None => unreachable!(),
+        };
+        match arg.as_str() {
+            _ if arg.starts_with("-D") => defines.push((true, value("-D"))),
+            _ if arg.starts_with("-U") => defines.push((false, value("-U"))),
+            _ if arg.starts_with("-isystem") => {
+                resolver = resolver.with_system_dir(value("-isystem"))
+            }
+            _ if arg.starts_with("-I") => resolver = resolver.with_user_dir(value("-I")),
+            _ if arg.starts_with('-') => usage(&format!("unknown option {}", arg)),
+            _ => path = Some(arg),
+        }
+    }
+
+    let Some(path) = path else {
+        scan_example();
+        return;
+    };
+    let src = fs::read_to_string(&path).unwrap_or_else(|error| {
+        eprintln!("{}: {}", path, error);
+        process::exit(1);
+    });
+
+    let arena = Arena::new();
+    let mut preprocessor = Preprocessor::new(&src, &arena)
+        .with_path(&path)
+        .with_include_resolver(resolver);
+    for (is_define, definition) in &defines {
+        preprocessor = if *is_define {
+            preprocessor.with_define(definition)
+        } else {
+            preprocessor.with_undef(definition)
+        };
+    }
+
+    let mut output = String::new();
+    for token in preprocessor.by_ref() {
+        if token.starts_line() {
+            output.push('\n');
+        } else if token.has_leading_space() {
+            output.push(' ');
+        }
+        output.push_str(token.spelling());
+    }
+    println!("{}", output.trim());
+
+    for error in preprocessor.errors() {
+        let span = error.span();
+        let file = preprocessor.presumed_path(span).unwrap_or(path.as_ref());
+        eprintln!(
+            "{}:{}:{}: error: {}",
+            file.display(),
+            span.line(),
+            span.column(),
+            error
+        );
+    }
+    if !preprocessor.errors().is_empty() {
+        process::exit(1);
+    }
+}
+
+fn usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("usage: scanner [-DNAME[=VALUE]] [-UNAME] [-IDIR] [-isystem DIR] [FILE]");
+    process::exit(2);
+}
+
+fn scan_example() {
     let src = "
 int main() {
     int a = 10;
@@ -21,4 +107,8 @@ int main() {
     }
 
     println!();
+    match Parser::new(src).parse() {
+        Ok(unit) => println!("AST:\n{:#?}", unit),
+        Err(error) => println!("error: {}", error),
+    }
 }
@@ -0,0 +1,246 @@
+mod declaration;
+mod expr;
+mod statement;
+mod tag;
+
+use std::collections::HashMap;
+
+use crate::ast::{Expr, Stmt, TranslationUnit};
+use crate::{
+    Arena, ConcatStrings, KeywordType, LexError, ParseError, Scanner, Span, SpannedToken, Token,
+};
+
+/// How deeply expressions, statements and declarators may nest, which bounds
+/// the recursion of the parser. It is low enough for the large frames of an
+/// unoptimized build to fit on a 2 MiB thread stack, yet no less than the 63
+/// levels of parentheses C requires.
+const MAX_DEPTH: usize = 64;
+
+/// A recursive-descent parser building the AST of a C translation unit.
+///
+/// It works on a token stream like the scanner's, merging adjacent string
+/// literals first. To tell declarations from expressions, it keeps track of
+/// the typedef names in scope.
+pub struct Parser<'src> {
+    tokens: Vec<SpannedToken<'src>>,
+    position: usize,
+    lex_errors: Vec<LexError>,
+    /// The identifiers declared in each scope, the file scope first, mapped
+    /// to whether they are typedef names.
+    scopes: Vec<HashMap<&'src str, bool>>,
+    /// How many constructs are being parsed within each other.
+    depth: usize,
+}
+
+impl<'src> Parser<'src> {
+    pub fn new(text: &'src str) -> Self {
+        Self::from_scanner(Scanner::new(text))
+    }
+
+    /// Parses the tokens of `scanner`, whose errors are reported before
+    /// any of the parser's own.
+    pub fn from_scanner(scanner: Scanner<'src>) -> Self {
+        let mut tokens = scanner.concat_strings();
+        let mut parser = Self::collect(tokens.by_ref());
+        let mut lex_errors = tokens.get_ref().errors().to_vec();
+        lex_errors.extend_from_slice(tokens.errors());
+        lex_errors.sort_by_key(|error| error.span().start());
+        parser.lex_errors = lex_errors;
+        parser
+    }
+
+    /// Parses `tokens`, such as the output of the preprocessor. Doc
+    /// comments, pragmas and newlines are skipped, and the spellings of
+    /// merged string literals are kept in `arena`. Literals with different
+    /// prefixes that can't be merged are reported first.
+    pub fn from_tokens(
+        tokens: impl IntoIterator<Item = SpannedToken<'src>>,
+        arena: &'src Arena,
+    ) -> Self {
+        let mut tokens = ConcatStrings::new(tokens.into_iter(), arena);
+        let mut parser = Self::collect(tokens.by_ref());
+        parser.lex_errors = tokens.errors().to_vec();
+        parser
+    }
+
+    fn collect(tokens: impl Iterator<Item = SpannedToken<'src>>) -> Self {
+        let mut tokens: Vec<SpannedToken<'src>> = tokens
+            .filter(|token| {
+                !matches!(
+                    token.token(),
+                    Token::DocComment(_) | Token::Pragma(_) | Token::Newline
+                )
+            })
+            .take_while(|token| token.token() != &Token::Eof)
+            .collect();
+        let end = tokens.last().map_or(Span::new(0, 0, 1, 1), |token| {
+            let span = token.span();
+            Span::new(span.end(), span.end(), span.line(), span.column())
+        });
+        tokens.push(SpannedToken::new(Token::Eof, end, ""));
+        Self {
+            tokens,
+            position: 0,
+            lex_errors: Vec::new(),
+            scopes: vec![HashMap::new()],
+            depth: 0,
+        }
+    }
+
+    /// Declares `name` as a typedef name at file scope, as if by a header
+    /// that isn't parsed, like `size_t`.
+    pub fn with_typedef(mut self, name: &'src str) -> Self {
+        self.scopes[0].insert(name, true);
+        self
+    }
+
+    /// Parses the whole input as a translation unit.
+    pub fn parse(mut self) -> Result<TranslationUnit<'src>, ParseError> {
+        self.check_lex_errors()?;
+        let mut items = Vec::new();
+        while self.peek() != &Token::Eof {
+            items.push(self.external_declaration()?);
+        }
+        Ok(TranslationUnit { items })
+    }
+
+    /// Parses the whole input as a single expression.
+    pub fn parse_expression(mut self) -> Result<Expr<'src>, ParseError> {
+        self.check_lex_errors()?;
+        let expr = self.expr()?;
+        self.expect(&Token::Eof, "end of input")?;
+        Ok(expr)
+    }
+
+    /// Parses the whole input as a single statement.
+    pub fn parse_statement(mut self) -> Result<Stmt<'src>, ParseError> {
+        self.check_lex_errors()?;
+        let stmt = self.stmt()?;
+        self.expect(&Token::Eof, "end of input")?;
+        Ok(stmt)
+    }
+
+    fn check_lex_errors(&self) -> Result<(), ParseError> {
+        match self.lex_errors.first() {
+            Some(error) => Err(ParseError::Lex(error.clone())),
+            None => Ok(()),
+        }
+    }
+
+    fn peek(&self) -> &Token<'src> {
+        self.peek_nth(0)
+    }
+
+    /// Looks `n` tokens ahead, stopping at the final `Token::Eof`.
+    fn peek_nth(&self, n: usize) -> &Token<'src> {
+        let last = self.tokens.len() - 1;
+        self.tokens[(self.position + n).min(last)].token()
+    }
+
+    fn peek_keyword(&self) -> Option<KeywordType> {
+        match self.peek() {
+            Token::Keyword(keyword) => Some(*keyword),
+            _ => None,
+        }
+    }
+
+    /// The span of the next token.
+    fn span(&self) -> Span {
+        self.tokens[self.position].span()
+    }
+
+    /// Consumes the next token, returning its span. The final `Token::Eof`
+    /// is never consumed.
+    fn bump(&mut self) -> Span {
+        let span = self.span();
+        if self.position < self.tokens.len() - 1 {
+            self.position += 1;
+        }
+        span
+    }
+
+    fn eat(&mut self, token: &Token<'_>) -> bool {
+        let is_next = self.peek() == token;
+        if is_next {
+            self.bump();
+        }
+        is_next
+    }
+
+    fn eat_keyword(&mut self, keyword: KeywordType) -> bool {
+        self.eat(&Token::Keyword(keyword))
+    }
+
+    /// Consumes `token`, or fails saying that `expected` was.
+    fn expect(&mut self, token: &Token<'_>, expected: &str) -> Result<Span, ParseError> {
+        if self.peek() == token {
+            Ok(self.bump())
+        } else {
+            Err(self.error(expected))
+        }
+    }
+
+    fn identifier(&mut self) -> Result<&'src str, ParseError> {
+        match *self.peek() {
+            Token::Id(name) => {
+                self.bump();
+                Ok(name)
+            }
+            _ => Err(self.error("identifier")),
+        }
+    }
+
+    /// Parses a construct nested in the current one with `parse`, failing
+    /// instead past `MAX_DEPTH` levels.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        if self.depth == MAX_DEPTH {
+            return Err(ParseError::NestingTooDeep { span: self.span() });
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Records `name` in the innermost scope.
+    fn declare_name(&mut self, name: &'src str, is_typedef: bool) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, is_typedef);
+        }
+    }
+
+    /// Whether `name` is a typedef name in the current scope, rather than an
+    /// ordinary identifier hiding it.
+    fn is_typedef_name(&self, name: &str) -> bool {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .is_some_and(|is_typedef| *is_typedef)
+    }
+
+    /// An error saying that `expected` was expected instead of the next
+    /// token.
+    fn error(&self, expected: &str) -> ParseError {
+        let token = &self.tokens[self.position];
+        ParseError::UnexpectedToken {
+            expected: expected.to_string(),
+            found: token.spelling().to_string(),
+            span: token.span(),
+        }
+    }
+
+    /// The span of the tokens consumed since the position `start`, or an
+    /// empty span before the next token when there are none.
+    fn span_since(&self, start: usize) -> Span {
+        let first = self.tokens[start].span();
+        if self.position == start {
+            return Span::new(first.start(), first.start(), first.line(), first.column());
+        }
+        let last = self.tokens[self.position - 1].span();
+        Span::new(first.start(), last.end(), first.line(), first.column())
+    }
+}
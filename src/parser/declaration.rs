@@ -0,0 +1,483 @@
+use std::collections::HashMap;
+
+use crate::ast::{
+    AlignmentSpecifier, Declaration, DeclarationSpecifiers, Declarator, DerivedDeclarator,
+    DesignatedInitializer, Designator, ExternalDeclaration, FunctionDef, FunctionSpecifier,
+    InitDeclarator, Initializer, ParameterDeclaration, StaticAssert, StorageClass, TypeName,
+    TypeQualifier, TypeSpecifier,
+};
+use crate::{KeywordType, OperatorType, ParseError, Token};
+
+use super::Parser;
+
+/// Whether a declarator names what it declares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Naming {
+    Named,
+    /// As in a parameter, which may be unnamed.
+    Optional,
+    /// As in a type name.
+    Abstract,
+}
+
+impl<'src> Parser<'src> {
+    pub(super) fn external_declaration(&mut self) -> Result<ExternalDeclaration<'src>, ParseError> {
+        if self.peek_keyword() == Some(KeywordType::StaticAssert) {
+            return Ok(ExternalDeclaration::StaticAssert(self.static_assert()?));
+        }
+        let start = self.position;
+        let specifiers = self.specifiers(true)?;
+        if self.eat(&Token::SemiColon) {
+            return Ok(ExternalDeclaration::Declaration(Declaration {
+                specifiers,
+                declarators: Vec::new(),
+                span: self.span_since(start),
+            }));
+        }
+
+        let declarator = self.declarator(Naming::Named)?;
+        self.declare(&specifiers, &declarator);
+        let parameters = match declarator.derived.first() {
+            Some(DerivedDeclarator::Function { parameters, .. }) => Some(parameters),
+            _ => None,
+        };
+        if let Some(parameters) = parameters.filter(|_| self.peek() == &Token::OpenBrace) {
+            // The parameters are in scope in the body, hiding typedefs.
+            self.scopes.push(HashMap::new());
+            for parameter in parameters {
+                self.declare(&parameter.specifiers, &parameter.declarator);
+            }
+            let body = self.block();
+            self.scopes.pop();
+            return Ok(ExternalDeclaration::Function(FunctionDef {
+                specifiers,
+                declarator,
+                body: body?,
+                span: self.span_since(start),
+            }));
+        }
+        let declarators = self.init_declarators(&specifiers, declarator)?;
+        Ok(ExternalDeclaration::Declaration(Declaration {
+            specifiers,
+            declarators,
+            span: self.span_since(start),
+        }))
+    }
+
+    /// Parses a declaration within a function, through its `;`.
+    pub(super) fn declaration(&mut self) -> Result<Declaration<'src>, ParseError> {
+        let start = self.position;
+        let specifiers = self.specifiers(true)?;
+        let declarators = if self.eat(&Token::SemiColon) {
+            Vec::new()
+        } else {
+            let declarator = self.declarator(Naming::Named)?;
+            self.declare(&specifiers, &declarator);
+            self.init_declarators(&specifiers, declarator)?
+        };
+        Ok(Declaration {
+            specifiers,
+            declarators,
+            span: self.span_since(start),
+        })
+    }
+
+    /// Parses a `_Static_assert` declaration, from its keyword.
+    pub(super) fn static_assert(&mut self) -> Result<StaticAssert<'src>, ParseError> {
+        let start = self.position;
+        self.bump();
+        self.expect(&Token::OpenParen, "`(`")?;
+        let condition = self.constant_expr()?;
+        let message = if self.eat(&Token::Comma) {
+            let Token::String(message) = self.peek().clone() else {
+                return Err(self.error("string literal"));
+            };
+            self.bump();
+            Some(message)
+        } else {
+            None
+        };
+        self.expect(&Token::CloseParen, "`)`")?;
+        self.expect(&Token::SemiColon, "`;`")?;
+        Ok(StaticAssert {
+            condition,
+            message,
+            span: self.span_since(start),
+        })
+    }
+
+    /// Whether the next token starts a declaration rather than a statement.
+    /// This is where typedef names matter: `T * x;` declares `x` if `T` is
+    /// one, and multiplies otherwise.
+    pub(super) fn starts_declaration(&self) -> bool {
+        match *self.peek() {
+            Token::Keyword(keyword) => {
+                storage_class(keyword).is_some()
+                    || starts_type_specifier(keyword)
+                    || type_qualifier(keyword).is_some()
+                    || function_specifier(keyword).is_some()
+                    || keyword == KeywordType::Alignas
+            }
+            // A typedef name followed by `:` is a label.
+            Token::Id(name) => self.is_typedef_name(name) && self.peek_nth(1) != &Token::Colon,
+            _ => false,
+        }
+    }
+
+    /// Parses the init-declarators following `first`, through the `;`
+    /// ending the declaration.
+    fn init_declarators(
+        &mut self,
+        specifiers: &DeclarationSpecifiers<'src>,
+        first: Declarator<'src>,
+    ) -> Result<Vec<InitDeclarator<'src>>, ParseError> {
+        let mut declarators = Vec::new();
+        let mut declarator = first;
+        loop {
+            let initializer = if self.eat(&Token::Operator(OperatorType::Assign)) {
+                Some(self.initializer()?)
+            } else {
+                None
+            };
+            declarators.push(InitDeclarator {
+                declarator,
+                initializer,
+            });
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+            declarator = self.declarator(Naming::Named)?;
+            self.declare(specifiers, &declarator);
+        }
+        self.expect(&Token::SemiColon, "`;`")?;
+        Ok(declarators)
+    }
+
+    /// Records the name of `declarator` in the innermost scope, as a typedef
+    /// name if `specifiers` has the `typedef` storage class and as an
+    /// ordinary identifier hiding any typedef otherwise.
+    fn declare(&mut self, specifiers: &DeclarationSpecifiers<'src>, declarator: &Declarator<'src>) {
+        let Some(name) = declarator.name else {
+            return;
+        };
+        let is_typedef = specifiers.storage_classes.contains(&StorageClass::Typedef);
+        self.declare_name(name, is_typedef);
+    }
+
+    /// Whether the token `n` ahead starts a type name, as in a cast.
+    pub(super) fn starts_type_name(&self, n: usize) -> bool {
+        match *self.peek_nth(n) {
+            Token::Keyword(keyword) => {
+                starts_type_specifier(keyword) || type_qualifier(keyword).is_some()
+            }
+            Token::Id(name) => self.is_typedef_name(name),
+            _ => false,
+        }
+    }
+
+    pub(super) fn type_name(&mut self) -> Result<TypeName<'src>, ParseError> {
+        Ok(TypeName {
+            specifiers: self.specifiers(false)?,
+            declarator: self.declarator(Naming::Abstract)?,
+        })
+    }
+
+    /// Parses the specifiers of a declaration or, unless `is_declaration`
+    /// is set, the type specifiers and qualifiers of a type name.
+    pub(super) fn specifiers(
+        &mut self,
+        is_declaration: bool,
+    ) -> Result<DeclarationSpecifiers<'src>, ParseError> {
+        let mut specifiers = DeclarationSpecifiers::default();
+        loop {
+            let keyword = match *self.peek() {
+                Token::Keyword(keyword) => keyword,
+                // After another type specifier, as in `unsigned T`, a typedef
+                // name is the declared identifier instead.
+                Token::Id(name)
+                    if specifiers.type_specifiers.is_empty() && self.is_typedef_name(name) =>
+                {
+                    specifiers
+                        .type_specifiers
+                        .push(TypeSpecifier::TypedefName(name));
+                    self.bump();
+                    continue;
+                }
+                _ => break,
+            };
+            match keyword {
+                KeywordType::Struct | KeywordType::Union => {
+                    let specifier = TypeSpecifier::Struct(self.nested(Self::struct_specifier)?);
+                    specifiers.type_specifiers.push(specifier);
+                    continue;
+                }
+                KeywordType::Enum => {
+                    let specifier = TypeSpecifier::Enum(self.nested(Self::enum_specifier)?);
+                    specifiers.type_specifiers.push(specifier);
+                    continue;
+                }
+                KeywordType::Alignas => {
+                    let specifier = self.nested(Self::alignment_specifier)?;
+                    specifiers.alignment_specifiers.push(specifier);
+                    continue;
+                }
+                _ => {}
+            }
+            if let Some(specifier) = type_specifier(keyword) {
+                specifiers.type_specifiers.push(specifier);
+            } else if let Some(qualifier) = type_qualifier(keyword) {
+                specifiers.qualifiers.push(qualifier);
+            } else if let Some(class) = storage_class(keyword).filter(|_| is_declaration) {
+                specifiers.storage_classes.push(class);
+            } else if let Some(specifier) = function_specifier(keyword).filter(|_| is_declaration) {
+                specifiers.function_specifiers.push(specifier);
+            } else {
+                break;
+            }
+            self.bump();
+        }
+        if specifiers == DeclarationSpecifiers::default() {
+            let expected = if is_declaration {
+                "declaration specifiers"
+            } else {
+                "type name"
+            };
+            return Err(self.error(expected));
+        }
+        Ok(specifiers)
+    }
+
+    /// Parses `_Alignas(type)` or `_Alignas(expr)`, from its keyword.
+    fn alignment_specifier(&mut self) -> Result<AlignmentSpecifier<'src>, ParseError> {
+        self.bump();
+        self.expect(&Token::OpenParen, "`(`")?;
+        let specifier = if self.starts_type_name(0) {
+            AlignmentSpecifier::Type(self.type_name()?)
+        } else {
+            AlignmentSpecifier::Expr(self.constant_expr()?)
+        };
+        self.expect(&Token::CloseParen, "`)`")?;
+        Ok(specifier)
+    }
+
+    fn type_qualifiers(&mut self) -> Vec<TypeQualifier> {
+        let mut qualifiers = Vec::new();
+        while let Some(qualifier) = self.peek_keyword().and_then(type_qualifier) {
+            self.bump();
+            qualifiers.push(qualifier);
+        }
+        qualifiers
+    }
+
+    /// Parses a declarator like `*const p[10]` or `(*fp)(int)`, whose
+    /// parentheses only group.
+    pub(super) fn declarator(&mut self, naming: Naming) -> Result<Declarator<'src>, ParseError> {
+        let start = self.position;
+        let mut pointers = Vec::new();
+        while self.eat(&Token::Operator(OperatorType::Asterisk)) {
+            pointers.push(DerivedDeclarator::Pointer(self.type_qualifiers()));
+        }
+
+        let (name, mut derived) = if self.starts_nested_declarator(naming) {
+            self.bump();
+            let inner = self.nested(|parser| parser.declarator(naming))?;
+            self.expect(&Token::CloseParen, "`)`")?;
+            (inner.name, inner.derived)
+        } else {
+            let name = match (self.peek(), naming) {
+                (Token::Id(_), Naming::Named | Naming::Optional) => Some(self.identifier()?),
+                (_, Naming::Named) => return Err(self.error("identifier")),
+                _ => None,
+            };
+            (name, Vec::new())
+        };
+
+        // The suffixes bind more tightly than the pointers, and the
+        // pointer nearest to the name applies first.
+        loop {
+            if self.eat(&Token::OpenBracket) {
+                derived.push(self.array_declarator()?);
+            } else if self.eat(&Token::OpenParen) {
+                derived.push(self.function_declarator()?);
+            } else {
+                break;
+            }
+        }
+        derived.extend(pointers.into_iter().rev());
+        Ok(Declarator {
+            name,
+            derived,
+            span: self.span_since(start),
+        })
+    }
+
+    /// Whether the next `(` groups a nested declarator rather than opening a
+    /// parameter list, as in `int (*)(void)` compared to `int (void)`.
+    fn starts_nested_declarator(&self, naming: Naming) -> bool {
+        if self.peek() != &Token::OpenParen {
+            return false;
+        }
+        match *self.peek_nth(1) {
+            _ if naming == Naming::Named => true,
+            Token::Operator(OperatorType::Asterisk) | Token::OpenParen | Token::OpenBracket => true,
+            Token::Id(name) => naming == Naming::Optional && !self.is_typedef_name(name),
+            _ => false,
+        }
+    }
+
+    /// Parses an array declarator following its `[`.
+    fn array_declarator(&mut self) -> Result<DerivedDeclarator<'src>, ParseError> {
+        let mut is_static = self.eat_keyword(KeywordType::Static);
+        let qualifiers = self.type_qualifiers();
+        is_static |= self.eat_keyword(KeywordType::Static);
+        let size = if self.peek() == &Token::CloseBracket {
+            None
+        } else {
+            Some(Box::new(self.nested(Self::assignment_expr)?))
+        };
+        self.expect(&Token::CloseBracket, "`]`")?;
+        Ok(DerivedDeclarator::Array {
+            qualifiers,
+            is_static,
+            size,
+        })
+    }
+
+    /// Parses a parameter list following its `(`. The parameters are in
+    /// scope until its `)`.
+    fn function_declarator(&mut self) -> Result<DerivedDeclarator<'src>, ParseError> {
+        self.scopes.push(HashMap::new());
+        let parameters = self.nested(Self::parameters);
+        self.scopes.pop();
+        let (parameters, is_variadic) = parameters?;
+        Ok(DerivedDeclarator::Function {
+            parameters,
+            is_variadic,
+        })
+    }
+
+    fn parameters(&mut self) -> Result<(Vec<ParameterDeclaration<'src>>, bool), ParseError> {
+        let mut parameters = Vec::new();
+        let mut is_variadic = false;
+        if !self.eat(&Token::CloseParen) {
+            loop {
+                if self.eat(&Token::Ellipsis) {
+                    is_variadic = true;
+                    break;
+                }
+                let specifiers = self.specifiers(true)?;
+                let declarator = self.declarator(Naming::Optional)?;
+                self.declare(&specifiers, &declarator);
+                parameters.push(ParameterDeclaration {
+                    specifiers,
+                    declarator,
+                });
+                if !self.eat(&Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(&Token::CloseParen, "`)`")?;
+        }
+        Ok((parameters, is_variadic))
+    }
+
+    fn initializer(&mut self) -> Result<Initializer<'src>, ParseError> {
+        if self.eat(&Token::OpenBrace) {
+            Ok(Initializer::List(self.nested(Self::initializer_list)?))
+        } else {
+            Ok(Initializer::Expr(self.assignment_expr()?))
+        }
+    }
+
+    /// Parses the initializers of a braced list following its `{`.
+    pub(super) fn initializer_list(
+        &mut self,
+    ) -> Result<Vec<DesignatedInitializer<'src>>, ParseError> {
+        let mut items = Vec::new();
+        while !self.eat(&Token::CloseBrace) {
+            let mut designators = Vec::new();
+            loop {
+                if self.eat(&Token::OpenBracket) {
+                    designators.push(Designator::Index(self.constant_expr()?));
+                    self.expect(&Token::CloseBracket, "`]`")?;
+                } else if self.eat(&Token::Operator(OperatorType::Dot)) {
+                    designators.push(Designator::Member(self.identifier()?));
+                } else {
+                    break;
+                }
+            }
+            if !designators.is_empty() {
+                self.expect(&Token::Operator(OperatorType::Assign), "`=`")?;
+            }
+            items.push(DesignatedInitializer {
+                designators,
+                initializer: self.initializer()?,
+            });
+            if !self.eat(&Token::Comma) {
+                self.expect(&Token::CloseBrace, "`}`")?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+}
+
+fn storage_class(keyword: KeywordType) -> Option<StorageClass> {
+    let class = match keyword {
+        KeywordType::Typedef => StorageClass::Typedef,
+        KeywordType::Extern => StorageClass::Extern,
+        KeywordType::Static => StorageClass::Static,
+        KeywordType::ThreadLocal => StorageClass::ThreadLocal,
+        KeywordType::Auto => StorageClass::Auto,
+        KeywordType::Register => StorageClass::Register,
+        _ => return None,
+    };
+    Some(class)
+}
+
+/// Whether `keyword` starts a type specifier, including the `struct`,
+/// `union` and `enum` ones.
+fn starts_type_specifier(keyword: KeywordType) -> bool {
+    type_specifier(keyword).is_some()
+        || matches!(
+            keyword,
+            KeywordType::Struct | KeywordType::Union | KeywordType::Enum
+        )
+}
+
+fn type_specifier(keyword: KeywordType) -> Option<TypeSpecifier<'static>> {
+    let specifier = match keyword {
+        KeywordType::Void => TypeSpecifier::Void,
+        KeywordType::Char => TypeSpecifier::Char,
+        KeywordType::Short => TypeSpecifier::Short,
+        KeywordType::Int => TypeSpecifier::Int,
+        KeywordType::Long => TypeSpecifier::Long,
+        KeywordType::Float => TypeSpecifier::Float,
+        KeywordType::Double => TypeSpecifier::Double,
+        KeywordType::Signed => TypeSpecifier::Signed,
+        KeywordType::Unsigned => TypeSpecifier::Unsigned,
+        KeywordType::Bool => TypeSpecifier::Bool,
+        KeywordType::Complex => TypeSpecifier::Complex,
+        _ => return None,
+    };
+    Some(specifier)
+}
+
+fn type_qualifier(keyword: KeywordType) -> Option<TypeQualifier> {
+    let qualifier = match keyword {
+        KeywordType::Const => TypeQualifier::Const,
+        KeywordType::Restrict => TypeQualifier::Restrict,
+        KeywordType::Volatile => TypeQualifier::Volatile,
+        KeywordType::Atomic => TypeQualifier::Atomic,
+        _ => return None,
+    };
+    Some(qualifier)
+}
+
+fn function_specifier(keyword: KeywordType) -> Option<FunctionSpecifier> {
+    match keyword {
+        KeywordType::Inline => Some(FunctionSpecifier::Inline),
+        KeywordType::Noreturn => Some(FunctionSpecifier::Noreturn),
+        _ => None,
+    }
+}
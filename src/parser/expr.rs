@@ -0,0 +1,354 @@
+use crate::ast::{BinaryOp, Expr, ExprKind, TypeName, UnaryOp};
+use crate::{KeywordType, OperatorType, ParseError, Token};
+
+use super::Parser;
+
+impl<'src> Parser<'src> {
+    /// Parses an expression, which may be a list joined by the comma
+    /// operator.
+    pub(super) fn expr(&mut self) -> Result<Expr<'src>, ParseError> {
+        let start = self.position;
+        let first = self.assignment_expr()?;
+        if self.peek() != &Token::Comma {
+            return Ok(first);
+        }
+        let mut exprs = vec![first];
+        while self.eat(&Token::Comma) {
+            exprs.push(self.assignment_expr()?);
+        }
+        Ok(Expr {
+            kind: ExprKind::Comma(exprs),
+            span: self.span_since(start),
+        })
+    }
+
+    pub(super) fn assignment_expr(&mut self) -> Result<Expr<'src>, ParseError> {
+        let start = self.position;
+        let target = self.conditional_expr()?;
+        let Token::Operator(operator) = *self.peek() else {
+            return Ok(target);
+        };
+        let Some(op) = assignment_op(operator) else {
+            return Ok(target);
+        };
+        self.bump();
+        // Assignments group right to left.
+        let value = self.nested(Self::assignment_expr)?;
+        Ok(Expr {
+            kind: ExprKind::Assign {
+                op,
+                target: Box::new(target),
+                value: Box::new(value),
+            },
+            span: self.span_since(start),
+        })
+    }
+
+    /// Parses an expression without assignments or commas, like the size of
+    /// an array.
+    pub(super) fn constant_expr(&mut self) -> Result<Expr<'src>, ParseError> {
+        self.conditional_expr()
+    }
+
+    fn conditional_expr(&mut self) -> Result<Expr<'src>, ParseError> {
+        let start = self.position;
+        let condition = self.binary_expr(1)?;
+        if !self.eat(&Token::Operator(OperatorType::Question)) {
+            return Ok(condition);
+        }
+        let then = self.nested(Self::expr)?;
+        self.expect(&Token::Colon, "`:`")?;
+        let otherwise = self.nested(Self::conditional_expr)?;
+        Ok(Expr {
+            kind: ExprKind::Conditional {
+                condition: Box::new(condition),
+                then: Box::new(then),
+                otherwise: Box::new(otherwise),
+            },
+            span: self.span_since(start),
+        })
+    }
+
+    /// Parses binary operators binding at least as tightly as `precedence`.
+    fn binary_expr(&mut self, precedence: u8) -> Result<Expr<'src>, ParseError> {
+        let start = self.position;
+        let mut left = self.cast_expr()?;
+        while let Token::Operator(operator) = *self.peek() {
+            let Some((operator_precedence, op)) = binary_op(operator) else {
+                break;
+            };
+            if operator_precedence < precedence {
+                break;
+            }
+            self.bump();
+            // Binary operators group left to right, so the right operand only
+            // takes the operators binding more tightly.
+            let right = self.binary_expr(operator_precedence + 1)?;
+            left = Expr {
+                kind: ExprKind::Binary {
+                    op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                },
+                span: self.span_since(start),
+            };
+        }
+        Ok(left)
+    }
+
+    /// Parses a cast like `(int)x`, or a unary expression. A parenthesized
+    /// type name followed by `{` starts a compound literal instead.
+    fn cast_expr(&mut self) -> Result<Expr<'src>, ParseError> {
+        let start = self.position;
+        if self.peek() != &Token::OpenParen || !self.starts_type_name(1) {
+            return self.unary_expr();
+        }
+        self.bump();
+        let type_name = self.type_name()?;
+        self.expect(&Token::CloseParen, "`)`")?;
+        if self.peek() == &Token::OpenBrace {
+            return self.compound_literal(start, type_name);
+        }
+        let operand = self.nested(Self::cast_expr)?;
+        Ok(Expr {
+            kind: ExprKind::Cast {
+                type_name: Box::new(type_name),
+                operand: Box::new(operand),
+            },
+            span: self.span_since(start),
+        })
+    }
+
+    fn unary_expr(&mut self) -> Result<Expr<'src>, ParseError> {
+        let start = self.position;
+        // In front of an operand, `*` and `&` are the unary operators rather
+        // than multiplication and bitwise and.
+        let op = match *self.peek() {
+            Token::Operator(OperatorType::Plus) => UnaryOp::Plus,
+            Token::Operator(OperatorType::Minus) => UnaryOp::Minus,
+            Token::Operator(OperatorType::ExclamationPoint) => UnaryOp::Not,
+            Token::Operator(OperatorType::BitwiseNot) => UnaryOp::BitNot,
+            Token::Operator(OperatorType::Asterisk) => UnaryOp::Deref,
+            Token::Operator(OperatorType::BitwiseAnd) => UnaryOp::AddressOf,
+            Token::Operator(OperatorType::Increment) => UnaryOp::PreIncrement,
+            Token::Operator(OperatorType::Decrement) => UnaryOp::PreDecrement,
+            Token::Keyword(KeywordType::Sizeof) => return self.sizeof_expr(),
+            Token::Keyword(KeywordType::Alignof) => {
+                self.bump();
+                self.expect(&Token::OpenParen, "`(`")?;
+                let type_name = self.type_name()?;
+                self.expect(&Token::CloseParen, "`)`")?;
+                return Ok(Expr {
+                    kind: ExprKind::Alignof(Box::new(type_name)),
+                    span: self.span_since(start),
+                });
+            }
+            _ => return self.postfix_expr(),
+        };
+        self.bump();
+        let operand = match op {
+            UnaryOp::PreIncrement | UnaryOp::PreDecrement => self.nested(Self::unary_expr)?,
+            _ => self.nested(Self::cast_expr)?,
+        };
+        Ok(Expr {
+            kind: ExprKind::Unary {
+                op,
+                operand: Box::new(operand),
+            },
+            span: self.span_since(start),
+        })
+    }
+
+    fn sizeof_expr(&mut self) -> Result<Expr<'src>, ParseError> {
+        let start = self.position;
+        self.bump();
+        let kind = if self.peek() == &Token::OpenParen && self.starts_type_name(1) {
+            let operand_start = self.position;
+            self.bump();
+            let type_name = self.type_name()?;
+            self.expect(&Token::CloseParen, "`)`")?;
+            if self.peek() == &Token::OpenBrace {
+                let literal = self.compound_literal(operand_start, type_name)?;
+                ExprKind::SizeofExpr(Box::new(literal))
+            } else {
+                ExprKind::SizeofType(Box::new(type_name))
+            }
+        } else {
+            ExprKind::SizeofExpr(Box::new(self.nested(Self::unary_expr)?))
+        };
+        Ok(Expr {
+            kind,
+            span: self.span_since(start),
+        })
+    }
+
+    /// Parses the braced initializers of a compound literal whose
+    /// parenthesized type started at `start`, and the postfix operators
+    /// applied to it.
+    fn compound_literal(
+        &mut self,
+        start: usize,
+        type_name: TypeName<'src>,
+    ) -> Result<Expr<'src>, ParseError> {
+        self.expect(&Token::OpenBrace, "`{`")?;
+        let initializers = self.nested(Self::initializer_list)?;
+        let literal = Expr {
+            kind: ExprKind::CompoundLiteral {
+                type_name: Box::new(type_name),
+                initializers,
+            },
+            span: self.span_since(start),
+        };
+        self.postfix_operators(start, literal)
+    }
+
+    fn postfix_expr(&mut self) -> Result<Expr<'src>, ParseError> {
+        let start = self.position;
+        let expr = self.primary_expr()?;
+        self.postfix_operators(start, expr)
+    }
+
+    /// Applies the postfix operators following `expr`, which started at
+    /// `start`.
+    fn postfix_operators(
+        &mut self,
+        start: usize,
+        mut expr: Expr<'src>,
+    ) -> Result<Expr<'src>, ParseError> {
+        loop {
+            let kind = match *self.peek() {
+                Token::OpenParen => {
+                    self.bump();
+                    ExprKind::Call {
+                        callee: Box::new(expr),
+                        arguments: self.nested(Self::arguments)?,
+                    }
+                }
+                Token::OpenBracket => {
+                    self.bump();
+                    let index = self.nested(Self::expr)?;
+                    self.expect(&Token::CloseBracket, "`]`")?;
+                    ExprKind::Index {
+                        array: Box::new(expr),
+                        index: Box::new(index),
+                    }
+                }
+                Token::Operator(operator @ (OperatorType::Dot | OperatorType::Arrow)) => {
+                    self.bump();
+                    ExprKind::Member {
+                        base: Box::new(expr),
+                        member: self.identifier()?,
+                        is_arrow: operator == OperatorType::Arrow,
+                    }
+                }
+                Token::Operator(OperatorType::Increment) => {
+                    self.bump();
+                    ExprKind::Unary {
+                        op: UnaryOp::PostIncrement,
+                        operand: Box::new(expr),
+                    }
+                }
+                Token::Operator(OperatorType::Decrement) => {
+                    self.bump();
+                    ExprKind::Unary {
+                        op: UnaryOp::PostDecrement,
+                        operand: Box::new(expr),
+                    }
+                }
+                _ => return Ok(expr),
+            };
+            expr = Expr {
+                kind,
+                span: self.span_since(start),
+            };
+        }
+    }
+
+    /// Parses the arguments of a call following its `(`.
+    fn arguments(&mut self) -> Result<Vec<Expr<'src>>, ParseError> {
+        let mut arguments = Vec::new();
+        if self.eat(&Token::CloseParen) {
+            return Ok(arguments);
+        }
+        loop {
+            arguments.push(self.assignment_expr()?);
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+        self.expect(&Token::CloseParen, "`)`")?;
+        Ok(arguments)
+    }
+
+    fn primary_expr(&mut self) -> Result<Expr<'src>, ParseError> {
+        let start = self.position;
+        let kind = match self.peek().clone() {
+            Token::Id(name) => ExprKind::Id(name),
+            Token::Number(number) => ExprKind::Number(number),
+            Token::Char(char) => ExprKind::Char(char),
+            Token::String(string) => ExprKind::String(string),
+            Token::Keyword(KeywordType::True) => ExprKind::Bool(true),
+            Token::Keyword(KeywordType::False) => ExprKind::Bool(false),
+            Token::Keyword(KeywordType::Nullptr) => ExprKind::Nullptr,
+            Token::OpenParen => {
+                self.bump();
+                let expr = self.nested(Self::expr)?;
+                self.expect(&Token::CloseParen, "`)`")?;
+                return Ok(expr);
+            }
+            _ => return Err(self.error("expression")),
+        };
+        self.bump();
+        Ok(Expr {
+            kind,
+            span: self.span_since(start),
+        })
+    }
+}
+
+/// The precedence of a binary operator, from 1 for `||` to 10 for `*`,
+/// together with the operation it stands for.
+fn binary_op(operator: OperatorType) -> Option<(u8, BinaryOp)> {
+    let op = match operator {
+        OperatorType::Or => (1, BinaryOp::Or),
+        OperatorType::And => (2, BinaryOp::And),
+        OperatorType::BitwiseOr => (3, BinaryOp::BitOr),
+        OperatorType::BitwiseXor => (4, BinaryOp::BitXor),
+        OperatorType::BitwiseAnd => (5, BinaryOp::BitAnd),
+        OperatorType::Equals => (6, BinaryOp::Equals),
+        OperatorType::NotEqual => (6, BinaryOp::NotEqual),
+        OperatorType::LessThan => (7, BinaryOp::LessThan),
+        OperatorType::LessThanOrEqual => (7, BinaryOp::LessThanOrEqual),
+        OperatorType::GreaterThan => (7, BinaryOp::GreaterThan),
+        OperatorType::GreaterThanOrEqual => (7, BinaryOp::GreaterThanOrEqual),
+        OperatorType::ShiftLeft => (8, BinaryOp::ShiftLeft),
+        OperatorType::ShiftRight => (8, BinaryOp::ShiftRight),
+        OperatorType::Plus => (9, BinaryOp::Add),
+        OperatorType::Minus => (9, BinaryOp::Subtract),
+        OperatorType::Asterisk => (10, BinaryOp::Multiply),
+        OperatorType::Divide => (10, BinaryOp::Divide),
+        OperatorType::Modulo => (10, BinaryOp::Modulo),
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// The operation of an assignment operator: `None` for `=`, and the one
+/// it combines with the assignment for compound ones like `+=`.
+fn assignment_op(operator: OperatorType) -> Option<Option<BinaryOp>> {
+    let op = match operator {
+        OperatorType::Assign => None,
+        OperatorType::PlusEqual => Some(BinaryOp::Add),
+        OperatorType::MinusEqual => Some(BinaryOp::Subtract),
+        OperatorType::AsteriskEqual => Some(BinaryOp::Multiply),
+        OperatorType::DivideEqual => Some(BinaryOp::Divide),
+        OperatorType::ModuloEqual => Some(BinaryOp::Modulo),
+        OperatorType::ShiftLeftEqual => Some(BinaryOp::ShiftLeft),
+        OperatorType::ShiftRightEqual => Some(BinaryOp::ShiftRight),
+        OperatorType::BitwiseAndEqual => Some(BinaryOp::BitAnd),
+        OperatorType::BitwiseXorEqual => Some(BinaryOp::BitXor),
+        OperatorType::BitwiseOrEqual => Some(BinaryOp::BitOr),
+        _ => return None,
+    };
+    Some(op)
+}
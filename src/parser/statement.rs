@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use crate::ast::{Block, BlockItem, Expr, ForInit, Stmt, StmtKind};
+use crate::{KeywordType, ParseError, Token};
+
+use super::Parser;
+
+impl<'src> Parser<'src> {
+    pub(super) fn block(&mut self) -> Result<Block<'src>, ParseError> {
+        self.scopes.push(HashMap::new());
+        let block = self.block_items();
+        self.scopes.pop();
+        block
+    }
+
+    fn block_items(&mut self) -> Result<Block<'src>, ParseError> {
+        let start = self.position;
+        self.expect(&Token::OpenBrace, "`{`")?;
+        let mut items = Vec::new();
+        while !self.eat(&Token::CloseBrace) {
+            if self.peek() == &Token::Eof {
+                return Err(self.error("`}`"));
+            }
+            let item = if self.peek_keyword() == Some(KeywordType::StaticAssert) {
+                BlockItem::StaticAssert(self.static_assert()?)
+            } else if self.starts_declaration() {
+                BlockItem::Declaration(self.declaration()?)
+            } else {
+                BlockItem::Stmt(self.nested(Self::stmt)?)
+            };
+            items.push(item);
+        }
+        Ok(Block {
+            items,
+            span: self.span_since(start),
+        })
+    }
+
+    pub(super) fn stmt(&mut self) -> Result<Stmt<'src>, ParseError> {
+        let start = self.position;
+        let kind = match *self.peek() {
+            Token::OpenBrace => StmtKind::Compound(self.block()?),
+            Token::Keyword(KeywordType::If) => {
+                self.bump();
+                let condition = self.parenthesized_expr()?;
+                let then = Box::new(self.nested(Self::stmt)?);
+                // An `else` belongs to the nearest `if`.
+                let otherwise = if self.eat_keyword(KeywordType::Else) {
+                    Some(Box::new(self.nested(Self::stmt)?))
+                } else {
+                    None
+                };
+                StmtKind::If {
+                    condition,
+                    then,
+                    otherwise,
+                }
+            }
+            Token::Keyword(KeywordType::Switch) => {
+                self.bump();
+                StmtKind::Switch {
+                    condition: self.parenthesized_expr()?,
+                    body: Box::new(self.nested(Self::stmt)?),
+                }
+            }
+            Token::Keyword(KeywordType::While) => {
+                self.bump();
+                StmtKind::While {
+                    condition: self.parenthesized_expr()?,
+                    body: Box::new(self.nested(Self::stmt)?),
+                }
+            }
+            Token::Keyword(KeywordType::Do) => {
+                self.bump();
+                let body = Box::new(self.nested(Self::stmt)?);
+                self.expect(&Token::Keyword(KeywordType::While), "`while`")?;
+                let condition = self.parenthesized_expr()?;
+                self.expect(&Token::SemiColon, "`;`")?;
+                StmtKind::DoWhile { body, condition }
+            }
+            Token::Keyword(KeywordType::For) => {
+                // The declarations of the first clause are only in scope in
+                // the loop.
+                self.scopes.push(HashMap::new());
+                let for_stmt = self.for_stmt();
+                self.scopes.pop();
+                for_stmt?
+            }
+            Token::Keyword(KeywordType::Goto) => {
+                self.bump();
+                let label = self.identifier()?;
+                self.expect(&Token::SemiColon, "`;`")?;
+                StmtKind::Goto(label)
+            }
+            Token::Keyword(KeywordType::Continue) => {
+                self.bump();
+                self.expect(&Token::SemiColon, "`;`")?;
+                StmtKind::Continue
+            }
+            Token::Keyword(KeywordType::Break) => {
+                self.bump();
+                self.expect(&Token::SemiColon, "`;`")?;
+                StmtKind::Break
+            }
+            Token::Keyword(KeywordType::Return) => {
+                self.bump();
+                StmtKind::Return(self.expr_until(&Token::SemiColon, "`;`")?)
+            }
+            Token::Keyword(KeywordType::Case) => {
+                self.bump();
+                let value = self.constant_expr()?;
+                self.expect(&Token::Colon, "`:`")?;
+                StmtKind::Case {
+                    value,
+                    body: Box::new(self.nested(Self::stmt)?),
+                }
+            }
+            Token::Keyword(KeywordType::Default) => {
+                self.bump();
+                self.expect(&Token::Colon, "`:`")?;
+                StmtKind::Default(Box::new(self.nested(Self::stmt)?))
+            }
+            Token::Id(label) if self.peek_nth(1) == &Token::Colon => {
+                self.bump();
+                self.bump();
+                StmtKind::Labeled {
+                    label,
+                    body: Box::new(self.nested(Self::stmt)?),
+                }
+            }
+            _ => StmtKind::Expr(self.expr_until(&Token::SemiColon, "`;`")?),
+        };
+        Ok(Stmt {
+            kind,
+            span: self.span_since(start),
+        })
+    }
+
+    fn for_stmt(&mut self) -> Result<StmtKind<'src>, ParseError> {
+        self.bump();
+        self.expect(&Token::OpenParen, "`(`")?;
+        let init = Box::new(if self.starts_declaration() {
+            ForInit::Declaration(self.declaration()?)
+        } else {
+            ForInit::Expr(self.expr_until(&Token::SemiColon, "`;`")?)
+        });
+        let condition = self.expr_until(&Token::SemiColon, "`;`")?;
+        let step = self.expr_until(&Token::CloseParen, "`)`")?;
+        Ok(StmtKind::For {
+            init,
+            condition,
+            step,
+            body: Box::new(self.nested(Self::stmt)?),
+        })
+    }
+
+    fn parenthesized_expr(&mut self) -> Result<Expr<'src>, ParseError> {
+        self.expect(&Token::OpenParen, "`(`")?;
+        let expr = self.expr()?;
+        self.expect(&Token::CloseParen, "`)`")?;
+        Ok(expr)
+    }
+
+    /// Parses an optional expression followed by `end`.
+    fn expr_until(
+        &mut self,
+        end: &Token<'_>,
+        expected: &str,
+    ) -> Result<Option<Expr<'src>>, ParseError> {
+        let expr = if self.peek() == end {
+            None
+        } else {
+            Some(self.expr()?)
+        };
+        self.expect(end, expected)?;
+        Ok(expr)
+    }
+}
@@ -0,0 +1,147 @@
+use crate::ast::{
+    Declarator, EnumType, Enumerator, Member, MemberDeclaration, MemberDeclarator, StructKind,
+    StructType,
+};
+use crate::{KeywordType, OperatorType, ParseError, Token};
+
+use super::declaration::Naming;
+use super::Parser;
+
+impl<'src> Parser<'src> {
+    /// Parses a `struct` or `union` specifier, from its keyword.
+    pub(super) fn struct_specifier(&mut self) -> Result<StructType<'src>, ParseError> {
+        let start = self.position;
+        let kind = match self.peek_keyword() {
+            Some(KeywordType::Union) => StructKind::Union,
+            _ => StructKind::Struct,
+        };
+        self.bump();
+        let tag = self.tag();
+        let members = if self.eat(&Token::OpenBrace) {
+            let mut members = Vec::new();
+            while !self.eat(&Token::CloseBrace) {
+                let member = if self.peek_keyword() == Some(KeywordType::StaticAssert) {
+                    Member::StaticAssert(self.static_assert()?)
+                } else {
+                    Member::Declaration(self.member_declaration()?)
+                };
+                members.push(member);
+            }
+            Some(members)
+        } else if tag.is_none() {
+            return Err(self.error("tag or `{`"));
+        } else {
+            None
+        };
+        Ok(StructType {
+            kind,
+            tag,
+            members,
+            span: self.span_since(start),
+        })
+    }
+
+    fn member_declaration(&mut self) -> Result<MemberDeclaration<'src>, ParseError> {
+        let start = self.position;
+        if !self.starts_type_name(0) && self.peek_keyword() != Some(KeywordType::Alignas) {
+            return Err(self.error("member declaration"));
+        }
+        // Members are not declared in any scope: they only live in the
+        // namespace of their struct, and hide nothing.
+        let specifiers = self.specifiers(false)?;
+        let mut declarators = Vec::new();
+        if !self.eat(&Token::SemiColon) {
+            loop {
+                declarators.push(self.member_declarator()?);
+                if !self.eat(&Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(&Token::SemiColon, "`;`")?;
+        }
+        Ok(MemberDeclaration {
+            specifiers,
+            declarators,
+            span: self.span_since(start),
+        })
+    }
+
+    fn member_declarator(&mut self) -> Result<MemberDeclarator<'src>, ParseError> {
+        let declarator = if self.peek() == &Token::Colon {
+            Declarator {
+                name: None,
+                derived: Vec::new(),
+                span: self.span_since(self.position),
+            }
+        } else {
+            self.declarator(Naming::Named)?
+        };
+        let bit_width = if self.eat(&Token::Colon) {
+            Some(self.constant_expr()?)
+        } else {
+            None
+        };
+        Ok(MemberDeclarator {
+            declarator,
+            bit_width,
+        })
+    }
+
+    /// Parses an `enum` specifier, from its keyword.
+    pub(super) fn enum_specifier(&mut self) -> Result<EnumType<'src>, ParseError> {
+        let start = self.position;
+        self.bump();
+        let tag = self.tag();
+        let enumerators = if self.eat(&Token::OpenBrace) {
+            let mut enumerators = Vec::new();
+            loop {
+                enumerators.push(self.enumerator()?);
+                // A trailing comma is allowed.
+                if !self.eat(&Token::Comma) || self.peek() == &Token::CloseBrace {
+                    break;
+                }
+            }
+            self.expect(&Token::CloseBrace, "`}`")?;
+            Some(enumerators)
+        } else if tag.is_none() {
+            return Err(self.error("tag or `{`"));
+        } else {
+            None
+        };
+        Ok(EnumType {
+            tag,
+            enumerators,
+            span: self.span_since(start),
+        })
+    }
+
+    fn enumerator(&mut self) -> Result<Enumerator<'src>, ParseError> {
+        let start = self.position;
+        let name = self.identifier()?;
+        let value = if self.eat(&Token::Operator(OperatorType::Assign)) {
+            Some(self.constant_expr()?)
+        } else {
+            None
+        };
+        // Enumeration constants are ordinary identifiers, which hide
+        // typedef names from the point they are declared.
+        self.declare_name(name, false);
+        Ok(Enumerator {
+            name,
+            value,
+            span: self.span_since(start),
+        })
+    }
+
+    /// Parses an optional tag. Tags have a namespace of their own, so a
+    /// typedef name may be one too.
+    fn tag(&mut self) -> Option<&'src str> {
+        match *self.peek() {
+            Token::Id(name) => {
+                self.bump();
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+}
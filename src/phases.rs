@@ -0,0 +1,162 @@
+use std::borrow::Cow;
+
+/// Translation phases 1 and 2: replaces trigraphs like `??=`, when enabled,
+/// and deletes each backslash followed by a newline, splicing the two lines.
+///
+/// The result is the text the lexer sees. Spans of tokens scanned from it
+/// with `Scanner::from_source` still point into the original text. Without
+/// anything to replace, the text is borrowed rather than copied.
+#[derive(Debug, Clone)]
+pub struct SourceText<'src> {
+    original: &'src str,
+    text: Cow<'src, str>,
+    edits: Vec<Edit>,
+}
+
+/// A place where the translated text differs from the original one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Edit {
+    /// The offset in the translated text.
+    pub(crate) at: usize,
+    pub(crate) kind: EditKind,
+    /// The number of bytes removed by this edit and the ones before it.
+    removed: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EditKind {
+    /// A line splice of this many bytes was deleted before `at`.
+    Splice(usize),
+    /// The character at `at` replaces a three character trigraph.
+    Trigraph,
+}
+
+impl Edit {
+    /// The first translated offset following the edit.
+    fn end(self) -> usize {
+        match self.kind {
+            EditKind::Splice(_) => self.at,
+            EditKind::Trigraph => self.at + 1,
+        }
+    }
+}
+
+impl<'src> SourceText<'src> {
+    /// Splices the lines of `original`, leaving trigraphs alone.
+    pub fn new(original: &'src str) -> Self {
+        Self::translate(original, false)
+    }
+
+    /// Also replaces trigraphs, which C23 removed from the language.
+    pub fn with_trigraphs(self, trigraphs: bool) -> Self {
+        Self::translate(self.original, trigraphs)
+    }
+
+    /// The text as the lexer sees it.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn original(&self) -> &'src str {
+        self.original
+    }
+
+    /// Maps an offset in `text()` back to `original()`. An offset right
+    /// after a splice maps to the start of the next line.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        original_offset(&self.edits, offset)
+    }
+
+    pub(crate) fn edits(&self) -> &[Edit] {
+        &self.edits
+    }
+
+    pub(crate) fn into_parts(self) -> (Cow<'src, str>, Vec<Edit>) {
+        (self.text, self.edits)
+    }
+
+    fn translate(original: &'src str, trigraphs: bool) -> Self {
+        let needs_translation = original.contains("\\\n")
+            || original.contains("\\\r\n")
+            || (trigraphs && original.contains("??"));
+        if !needs_translation {
+            return Self {
+                original,
+                text: Cow::Borrowed(original),
+                edits: Vec::new(),
+            };
+        }
+
+        let mut text = String::with_capacity(original.len());
+        let mut edits = Vec::new();
+        let mut removed = 0;
+        let mut rest = original;
+        while let Some(char) = rest.chars().next() {
+            let replaced = trigraph(rest).filter(|_| trigraphs);
+            let (char, len) = match replaced {
+                Some(replacement) => (replacement, 3),
+                None => (char, char.len_utf8()),
+            };
+            let after = &rest[len..];
+            let newline = if after.starts_with('\n') {
+                Some(1)
+            } else if after.starts_with("\r\n") {
+                Some(2)
+            } else {
+                None
+            };
+            match newline {
+                Some(newline) if char == '\\' => {
+                    removed += len + newline;
+                    edits.push(Edit {
+                        at: text.len(),
+                        kind: EditKind::Splice(len + newline),
+                        removed,
+                    });
+                    rest = &after[newline..];
+                    continue;
+                }
+                _ if replaced.is_some() => {
+                    removed += 2;
+                    edits.push(Edit {
+                        at: text.len(),
+                        kind: EditKind::Trigraph,
+                        removed,
+                    });
+                }
+                _ => {}
+            }
+            text.push(char);
+            rest = after;
+        }
+        Self {
+            original,
+            text: Cow::Owned(text),
+            edits,
+        }
+    }
+}
+
+/// The character the trigraph starting `text` stands for, if it starts with
+/// one.
+fn trigraph(text: &str) -> Option<char> {
+    let replacement = match text.as_bytes() {
+        [b'?', b'?', b'=', ..] => '#',
+        [b'?', b'?', b'(', ..] => '[',
+        [b'?', b'?', b'/', ..] => '\\',
+        [b'?', b'?', b')', ..] => ']',
+        [b'?', b'?', b'\'', ..] => '^',
+        [b'?', b'?', b'<', ..] => '{',
+        [b'?', b'?', b'!', ..] => '|',
+        [b'?', b'?', b'>', ..] => '}',
+        [b'?', b'?', b'-', ..] => '~',
+        _ => return None,
+    };
+    Some(replacement)
+}
+
+/// Maps an offset of the translated text back to the original one.
+pub(crate) fn original_offset(edits: &[Edit], offset: usize) -> usize {
+    let applied = edits.partition_point(|edit| edit.end() <= offset);
+    offset + applied.checked_sub(1).map_or(0, |last| edits[last].removed)
+}
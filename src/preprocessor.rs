@@ -0,0 +1,486 @@
+mod conditional;
+mod expr;
+mod include;
+mod line;
+mod macros;
+mod pragma;
+mod predefined;
+
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::iter::FusedIterator;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    check_number, Arena, KeywordType, PreprocessError, Scanner, SourceText, Span, SpannedToken,
+    Standard, Token,
+};
+
+use self::conditional::Conditional;
+use self::include::{Guard, SourceFile};
+use self::macros::Macro;
+
+pub use self::include::{
+    FileSystem, Include, IncludeGraph, IncludeResolver, RealFileSystem, SkipReason,
+    VirtualFileSystem,
+};
+pub use self::pragma::{DiagnosticPragma, PackPragma, Pragma, PragmaSwitch};
+pub use self::predefined::Target;
+
+/// The names of the macros a token must not expand again, because it comes
+/// from their own expansion.
+type HideSet<'src> = Rc<BTreeSet<&'src str>>;
+
+/// A token of the preprocessor's output.
+#[derive(Debug, Clone)]
+pub struct PpToken<'src> {
+    token: SpannedToken<'src>,
+    expansion: Option<Rc<Expansion<'src>>>,
+    hide_set: HideSet<'src>,
+    leading_space: bool,
+    starts_line: bool,
+}
+
+impl<'src> PpToken<'src> {
+    fn new(token: SpannedToken<'src>, leading_space: bool, starts_line: bool) -> Self {
+        Self {
+            token,
+            expansion: None,
+            hide_set: HideSet::default(),
+            leading_space,
+            starts_line,
+        }
+    }
+
+    pub fn token(&self) -> &Token<'src> {
+        self.token.token()
+    }
+
+    /// Where the token was written: in a macro's replacement list when it
+    /// comes from there, otherwise at the use site.
+    pub fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    pub fn spelling(&self) -> &'src str {
+        self.token.spelling()
+    }
+
+    /// The innermost macro expansion the token comes from, if any.
+    pub fn expansion(&self) -> Option<&Expansion<'src>> {
+        self.expansion.as_deref()
+    }
+
+    /// Whether whitespace separates the token from the one before it.
+    pub fn has_leading_space(&self) -> bool {
+        self.leading_space
+    }
+
+    /// Whether the token is the first of a line of the source.
+    pub fn starts_line(&self) -> bool {
+        self.starts_line
+    }
+}
+
+/// One step of the macro expansions that produced a token.
+#[derive(Debug, PartialEq)]
+pub struct Expansion<'src> {
+    name: &'src str,
+    definition: Span,
+    invocation: Span,
+    parent: Option<Rc<Expansion<'src>>>,
+}
+
+impl<'src> Expansion<'src> {
+    pub fn name(&self) -> &'src str {
+        self.name
+    }
+
+    /// The span of the macro's name in its `#define`.
+    pub fn definition(&self) -> Span {
+        self.definition
+    }
+
+    /// The span of the macro's name where it was expanded.
+    pub fn invocation(&self) -> Span {
+        self.invocation
+    }
+
+    /// The expansion the invocation itself comes from, if any.
+    pub fn parent(&self) -> Option<&Expansion<'src>> {
+        self.parent.as_deref()
+    }
+}
+
+/// Translation phase 4: executes the directives of a source file and expands
+/// its macros, yielding the tokens of the result.
+///
+/// Text that isn't in the source, such as the result of `##`, lives in the
+/// arena, as do the included files. Each file gets its own range of offsets,
+/// so a span tells which file it is in. Like the scanner, the output ends
+/// with a single `Token::Eof`.
+pub struct Preprocessor<'src> {
+    arena: &'src Arena,
+    standard: Standard,
+    trigraphs: bool,
+    target: Target,
+    /// The `#define` and `#undef` lines of the `-D` and `-U` options.
+    command_line: String,
+    predefined: bool,
+    /// The seconds since the Unix epoch `__DATE__` and `__TIME__` give.
+    timestamp: u64,
+    counter: usize,
+    resolver: IncludeResolver,
+    /// The files being read, the innermost last.
+    frames: Vec<Frame<'src>>,
+    files: Vec<SourceFile>,
+    next_base: usize,
+    include_graph: IncludeGraph,
+    /// The files with `#pragma once`.
+    once: HashSet<PathBuf>,
+    /// The include guards of the files read.
+    guards: HashMap<PathBuf, &'src str>,
+    macros: HashMap<&'src str, Rc<Macro<'src>>>,
+    /// Tokens to read before going back to the scanner, such as the result
+    /// of an expansion that has to be rescanned.
+    pending: VecDeque<PpToken<'src>>,
+    /// How many macro arguments are being expanded within each other. Reading
+    /// must not go past the innermost one.
+    arg_depth: usize,
+    conditionals: Vec<Conditional>,
+    /// Where the group being skipped starts.
+    skip_start: Option<Span>,
+    inactive_ranges: Vec<Span>,
+    errors: Vec<PreprocessError>,
+}
+
+/// A file being read.
+struct Frame<'src> {
+    /// The text of the file, before translation phases 1 and 2.
+    text: &'src str,
+    scanner: Scanner<'src>,
+    /// The index of the file in `files`.
+    file: usize,
+    lex_errors: usize,
+    previous_end: Option<usize>,
+    at_line_start: bool,
+    /// The number of conditionals open when the file was included.
+    conditionals: usize,
+    guard: Guard<'src>,
+    /// The pending tokens of the including file.
+    resume: VecDeque<PpToken<'src>>,
+}
+
+impl<'src> Preprocessor<'src> {
+    pub fn new(text: &'src str, arena: &'src Arena) -> Self {
+        let mut preprocessor = Self {
+            arena,
+            standard: Standard::default(),
+            trigraphs: false,
+            target: Target::default(),
+            command_line: String::new(),
+            predefined: false,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+            counter: 0,
+            resolver: IncludeResolver::default(),
+            frames: Vec::new(),
+            files: Vec::new(),
+            next_base: 0,
+            include_graph: IncludeGraph::default(),
+            once: HashSet::new(),
+            guards: HashMap::new(),
+            macros: HashMap::new(),
+            pending: VecDeque::new(),
+            arg_depth: 0,
+            conditionals: Vec::new(),
+            skip_start: None,
+            inactive_ranges: Vec::new(),
+            errors: Vec::new(),
+        };
+        preprocessor.push_file(PathBuf::from("<input>"), text, None, None, 0);
+        preprocessor
+    }
+
+    pub fn with_standard(mut self, standard: Standard) -> Self {
+        self.standard = standard;
+        self.rescan();
+        self
+    }
+
+    /// Replaces trigraphs like `??=` in translation phase 1.
+    pub fn with_trigraphs(mut self, trigraphs: bool) -> Self {
+        self.trigraphs = trigraphs;
+        self.rescan();
+        self
+    }
+
+    pub fn with_target(self, target: Target) -> Self {
+        Self { target, ..self }
+    }
+
+    /// Defines a macro like the `-D` option: `NAME` defines it as `1`, and
+    /// `NAME=value` or `NAME(params)=value` as the value.
+    pub fn with_define(mut self, definition: &str) -> Self {
+        let definition = definition.lines().next().unwrap_or_default();
+        let line = match definition.split_once('=') {
+            Some((name, value)) => format!("#define {} {}\n", name, value),
+            None => format!("#define {} 1\n", definition),
+        };
+        self.command_line.push_str(&line);
+        self
+    }
+
+    /// Undefines a macro like the `-U` option, after the predefined macros
+    /// and the `-D` options before it.
+    pub fn with_undef(mut self, name: &str) -> Self {
+        let name = name.lines().next().unwrap_or_default();
+        self.command_line.push_str(&format!("#undef {}\n", name));
+        self
+    }
+
+    /// Sets the time `__DATE__` and `__TIME__` give, in seconds since the
+    /// Unix epoch, rather than the current time.
+    pub fn with_timestamp(self, timestamp: u64) -> Self {
+        Self { timestamp, ..self }
+    }
+
+    /// Sets the path of the source file, which `"quoted"` includes are
+    /// searched next to.
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = include::normalize(&path.into());
+        self.include_graph.set_path(0, path.clone());
+        self.files[0].path = path;
+        self
+    }
+
+    pub fn with_include_resolver(self, resolver: IncludeResolver) -> Self {
+        Self { resolver, ..self }
+    }
+
+    /// The errors found so far, including the ones of the scanner.
+    pub fn errors(&self) -> &[PreprocessError] {
+        &self.errors
+    }
+
+    /// The spans of the groups skipped by conditional directives so far,
+    /// from the line after the directive skipping them up to the directive
+    /// ending them.
+    pub fn inactive_ranges(&self) -> &[Span] {
+        &self.inactive_ranges
+    }
+
+    /// The scanner of a file's text, after translation phases 1 and 2.
+    fn scanner(&self, text: &'src str) -> Scanner<'src> {
+        let source = SourceText::new(text).with_trigraphs(self.trigraphs);
+        let (translated, edits) = source.into_parts();
+        let translated = match translated {
+            Cow::Borrowed(translated) => translated,
+            Cow::Owned(translated) => self.arena.alloc(translated),
+        };
+        Scanner::with_edits(translated, edits)
+            .with_standard(self.standard)
+            .with_preprocessing(true)
+    }
+
+    /// Starts the files over once the options of the scanner changed.
+    fn rescan(&mut self) {
+        for index in 0..self.frames.len() {
+            self.frames[index].scanner = self.scanner(self.frames[index].text);
+        }
+    }
+
+    /// Reads the next token, without expanding it.
+    fn next_raw(&mut self) -> Option<PpToken<'src>> {
+        if let Some(token) = self.pending.pop_front() {
+            return Some(token);
+        }
+        if self.arg_depth > 0 {
+            return None;
+        }
+
+        let skipping = self.is_skipping();
+        let frame = self.frames.last_mut()?;
+        let file = &self.files[frame.file];
+        // Offsets become global and lines become the ones `#line` gives.
+        let shift = |span: Span| {
+            Span::new(
+                span.start() + file.base,
+                span.end() + file.base,
+                file.presumed_line(span.line()),
+                span.column(),
+            )
+        };
+        let token = frame.scanner.next()?;
+        // Skipped groups only need to be made of valid preprocessing tokens,
+        // which any text is to the scanner.
+        let errors = &frame.scanner.errors()[frame.lex_errors..];
+        frame.lex_errors += errors.len();
+        if !skipping {
+            self.errors.extend(
+                errors
+                    .iter()
+                    .map(|error| PreprocessError::Lex(error.clone().map_span(shift))),
+            );
+        }
+
+        let span = shift(token.span());
+        let spelling = token.spelling();
+        let token = SpannedToken::new(token.into_token(), span, spelling);
+        let leading_space = frame.previous_end.is_some_and(|end| end < span.start());
+        let starts_line = frame.at_line_start;
+        frame.previous_end = Some(span.end());
+        frame.at_line_start = token.token() == &Token::Newline;
+        Some(PpToken::new(token, leading_space, starts_line))
+    }
+
+    /// Reads the rest of a directive's line, returning it with the position
+    /// following it.
+    fn read_line(&mut self) -> (Vec<PpToken<'src>>, Span) {
+        let mut line = Vec::new();
+        while let Some(token) = self.next_raw() {
+            let span = token.span();
+            match token.token() {
+                Token::Newline => {
+                    let end = Span::new(span.end(), span.end(), span.line() + 1, 1);
+                    return (line, end);
+                }
+                Token::Eof => {
+                    self.pending.push_front(token);
+                    return (line, span);
+                }
+                _ => line.push(token),
+            }
+        }
+        let end = line.last().map_or(Span::new(0, 0, 1, 1), PpToken::span);
+        (line, end)
+    }
+
+    fn directive(&mut self, hash: &PpToken<'src>) {
+        let (line, end) = self.read_line();
+        let Some((name, rest)) = line.split_first() else {
+            return;
+        };
+        self.track_guard(name, rest);
+        match name.token() {
+            Token::Id(
+                "if" | "ifdef" | "ifndef" | "elif" | "elifdef" | "elifndef" | "else" | "endif",
+            ) => self.conditional(hash, name, rest, end),
+            _ if self.is_skipping() => {}
+            Token::Id("define") => self.define(name, rest),
+            Token::Id("undef") => self.undef(name, rest),
+            Token::Id("include") => self.include(name, rest, false),
+            Token::Id("include_next") => self.include(name, rest, true),
+            Token::Id("pragma") => self.pragma(hash, name, rest),
+            Token::Id("line") | Token::PpNumber(_) => self.line(name, rest, end),
+            _ => self.errors.push(PreprocessError::InvalidDirective {
+                name: name.spelling().to_string(),
+                span: name.span(),
+            }),
+        }
+    }
+
+    fn define(&mut self, directive: &PpToken<'src>, line: &[PpToken<'src>]) {
+        let Some((name, rest)) = line.split_first() else {
+            self.errors.push(PreprocessError::InvalidMacroName {
+                span: directive.span(),
+            });
+            return;
+        };
+        let definition = match Macro::parse(name, rest) {
+            Ok(definition) => definition,
+            Err(error) => {
+                self.errors.push(error);
+                return;
+            }
+        };
+
+        let name = name.spelling();
+        if let Some(previous) = self.macros.get(name) {
+            if !previous.is_same_as(&definition) {
+                self.errors.push(PreprocessError::MacroRedefined {
+                    name: name.to_string(),
+                    span: definition.span,
+                });
+            }
+        }
+        self.macros.insert(name, Rc::new(definition));
+    }
+
+    fn undef(&mut self, directive: &PpToken<'src>, line: &[PpToken<'src>]) {
+        match line.first().map(|name| (name, name.token())) {
+            Some((_, Token::Id(name))) if *name != "defined" => {
+                self.macros.remove(name);
+            }
+            Some((name, _)) => self
+                .errors
+                .push(PreprocessError::InvalidMacroName { span: name.span() }),
+            None => self.errors.push(PreprocessError::InvalidMacroName {
+                span: directive.span(),
+            }),
+        }
+    }
+
+    /// Translation phase 7 turns the identifiers that are keywords into
+    /// keywords, and checks that preprocessing numbers are constants.
+    fn finish(&mut self, mut token: PpToken<'src>) -> PpToken<'src> {
+        let span = token.span();
+        match *token.token() {
+            Token::Id(name) => {
+                if let Some(keyword) = KeywordType::lookup(name, self.standard) {
+                    token.token = SpannedToken::new(Token::Keyword(keyword), span, name);
+                }
+            }
+            Token::PpNumber(raw) => {
+                let number = check_number(raw, span).map_or_else(
+                    |error| {
+                        self.errors.push(PreprocessError::Lex(error));
+                        Token::Invalid(raw)
+                    },
+                    Token::Number,
+                );
+                token.token = SpannedToken::new(number, span, raw);
+            }
+            _ => {}
+        }
+        token
+    }
+}
+
+impl<'src> Iterator for Preprocessor<'src> {
+    type Item = PpToken<'src>;
+
+    fn next(&mut self) -> Option<PpToken<'src>> {
+        if !self.predefined {
+            self.predefine();
+        }
+        loop {
+            let token = self.next_raw()?;
+            match token.token() {
+                Token::Newline => {}
+                // A `#` from a macro expansion, which has a hide set, doesn't
+                // start a directive even at the start of a line.
+                Token::Hash if token.starts_line && token.hide_set.is_empty() => {
+                    self.directive(&token)
+                }
+                Token::Eof if self.frames.len() > 1 => self.end_file(&token),
+                Token::Eof => {
+                    self.close_conditionals(&token, 0);
+                    return Some(token);
+                }
+                _ if self.is_skipping() => {}
+                _ => {
+                    self.leave_guard();
+                    if let Some(token) = self.expand(token) {
+                        return Some(self.finish(token));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl FusedIterator for Preprocessor<'_> {}
@@ -0,0 +1,221 @@
+use crate::{PreprocessError, Span, SpannedToken, Token};
+
+use super::{expr, PpToken, Preprocessor};
+
+/// An `#if` whose `#endif` hasn't been reached yet.
+#[derive(Debug)]
+pub(super) struct Conditional {
+    /// The directive opening the conditional, like `ifdef`.
+    directive: String,
+    span: Span,
+    state: GroupState,
+    has_else: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GroupState {
+    /// The current group is being processed.
+    Active,
+    /// No group has been taken yet, one of the next ones may be.
+    Waiting,
+    /// A group was taken already, or the whole conditional is skipped.
+    Done,
+}
+
+impl<'src> Preprocessor<'src> {
+    /// Whether the tokens being read are in a group that is skipped.
+    pub(super) fn is_skipping(&self) -> bool {
+        self.conditionals
+            .last()
+            .is_some_and(|conditional| conditional.state != GroupState::Active)
+    }
+
+    /// Runs a conditional directive, where `hash` is the `#` starting it and
+    /// `end` the position following its line.
+    pub(super) fn conditional(
+        &mut self,
+        hash: &PpToken<'src>,
+        directive: &PpToken<'src>,
+        rest: &[PpToken<'src>],
+        end: Span,
+    ) {
+        let was_skipping = self.is_skipping();
+        let name = directive.spelling();
+        match name {
+            "if" | "ifdef" | "ifndef" => {
+                let state = if was_skipping {
+                    GroupState::Done
+                } else if self.condition(directive, rest) {
+                    GroupState::Active
+                } else {
+                    GroupState::Waiting
+                };
+                self.conditionals.push(Conditional {
+                    directive: name.to_string(),
+                    span: directive.span(),
+                    state,
+                    has_else: false,
+                });
+            }
+            "endif" => {
+                if self.conditionals.pop().is_none() {
+                    self.errors.push(PreprocessError::UnbalancedConditional {
+                        directive: name.to_string(),
+                        span: directive.span(),
+                    });
+                }
+            }
+            _ => {
+                let Some(conditional) = self.conditionals.last() else {
+                    self.errors.push(PreprocessError::UnbalancedConditional {
+                        directive: name.to_string(),
+                        span: directive.span(),
+                    });
+                    return;
+                };
+                if conditional.has_else {
+                    self.errors.push(PreprocessError::ElseAfterElse {
+                        directive: name.to_string(),
+                        span: directive.span(),
+                    });
+                    return;
+                }
+
+                let current = conditional.state;
+                let state = match current {
+                    GroupState::Active | GroupState::Done => GroupState::Done,
+                    GroupState::Waiting if name == "else" || self.condition(directive, rest) => {
+                        GroupState::Active
+                    }
+                    GroupState::Waiting => GroupState::Waiting,
+                };
+                if let Some(conditional) = self.conditionals.last_mut() {
+                    conditional.state = state;
+                    conditional.has_else = name == "else";
+                }
+            }
+        }
+
+        match (was_skipping, self.is_skipping()) {
+            (false, true) => self.skip_start = Some(end),
+            (true, false) => self.end_inactive_range(hash.span()),
+            _ => {}
+        }
+    }
+
+    /// Reports the conditionals left open at the end of a file, the ones
+    /// after the first `depth` which were opened by the files including it.
+    pub(super) fn close_conditionals(&mut self, eof: &PpToken<'src>, depth: usize) {
+        if self.is_skipping() {
+            self.end_inactive_range(eof.span());
+        }
+        for conditional in self.conditionals.drain(depth..) {
+            self.errors.push(PreprocessError::UnterminatedConditional {
+                directive: conditional.directive,
+                span: conditional.span,
+            });
+        }
+    }
+
+    fn end_inactive_range(&mut self, end: Span) {
+        if let Some(start) = self.skip_start.take() {
+            let range = Span::new(start.start(), end.start(), start.line(), start.column());
+            self.inactive_ranges.push(range);
+        }
+    }
+
+    fn condition(&mut self, directive: &PpToken<'src>, rest: &[PpToken<'src>]) -> bool {
+        match directive.spelling() {
+            "ifdef" | "elifdef" => self.is_defined(directive, rest),
+            "ifndef" | "elifndef" => !self.is_defined(directive, rest),
+            _ => self.evaluate(directive, rest),
+        }
+    }
+
+    fn is_defined(&mut self, directive: &PpToken<'src>, rest: &[PpToken<'src>]) -> bool {
+        match rest.first().map(PpToken::token) {
+            Some(Token::Id(name)) => self.is_macro(name),
+            _ => {
+                let span = rest.first().unwrap_or(directive).span();
+                self.errors.push(PreprocessError::InvalidMacroName { span });
+                false
+            }
+        }
+    }
+
+    /// Evaluates the expression of an `#if` or `#elif`.
+    fn evaluate(&mut self, directive: &PpToken<'src>, rest: &[PpToken<'src>]) -> bool {
+        // `defined` and `__has_include` take their operand unexpanded, so they
+        // are replaced before expanding the rest.
+        let mut tokens = Vec::new();
+        let mut index = 0;
+        while let Some(token) = rest.get(index) {
+            let operand = match token.token() {
+                Token::Id("defined") => operand(rest, index + 1, |operand| {
+                    matches!(operand.token(), Token::Id(_))
+                }),
+                // Unlike `defined`, `__has_include` needs its parentheses.
+                Token::Id("__has_include" | "__has_include_next") => {
+                    operand(rest, index + 1, |operand| {
+                        matches!(operand.token(), Token::HeaderName(_))
+                    })
+                    .filter(|&(_, next)| next == index + 4)
+                }
+                _ => {
+                    tokens.push(token.clone());
+                    index += 1;
+                    continue;
+                }
+            };
+            let Some((operand, next)) = operand else {
+                self.errors.push(PreprocessError::InvalidExpression {
+                    span: rest.get(index + 1).unwrap_or(token).span(),
+                });
+                return false;
+            };
+
+            let value = match operand.token() {
+                Token::Id(name) if token.spelling() == "defined" => self.is_macro(name),
+                Token::HeaderName(header_name) => {
+                    self.has_include(header_name, token.spelling() == "__has_include_next")
+                }
+                _ => false,
+            };
+            tokens.push(number(value, token));
+            index = next;
+        }
+
+        let tokens = self.expand_arg(tokens);
+        expr::evaluate(&tokens, directive.span(), self.standard).unwrap_or_else(|error| {
+            self.errors.push(error);
+            false
+        })
+    }
+}
+
+/// Finds the operand of `defined` or `__has_include` starting at `index`,
+/// either alone or in parentheses. Returns it with the index following it.
+fn operand<'a, 'src>(
+    tokens: &'a [PpToken<'src>],
+    index: usize,
+    is_operand: impl Fn(&PpToken<'src>) -> bool,
+) -> Option<(&'a PpToken<'src>, usize)> {
+    let token = tokens.get(index)?;
+    if token.token() != &Token::OpenParen {
+        return is_operand(token).then_some((token, index + 1));
+    }
+    let operand = tokens
+        .get(index + 1)
+        .filter(|&operand| is_operand(operand))?;
+    let close = tokens.get(index + 2)?;
+    (close.token() == &Token::CloseParen).then_some((operand, index + 3))
+}
+
+/// The `1` or `0` replacing `defined X` in an `#if`.
+fn number<'src>(value: bool, replaced: &PpToken<'src>) -> PpToken<'src> {
+    let spelling = if value { "1" } else { "0" };
+    PpToken {
+        token: SpannedToken::new(Token::PpNumber(spelling), replaced.span(), spelling),
+        ..replaced.clone()
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{
+    check_number, IntegerSuffix, NumberKind, OperatorType, PreprocessError, Span, Standard, Token,
+};
+
+use super::PpToken;
+
+/// A value of an `#if` expression, which has type `intmax_t` or `uintmax_t`.
+#[derive(Debug, Clone, Copy)]
+struct Value {
+    bits: u64,
+    unsigned: bool,
+}
+
+impl Value {
+    fn signed(value: i64) -> Self {
+        Self {
+            bits: value as u64,
+            unsigned: false,
+        }
+    }
+
+    fn is_true(self) -> bool {
+        self.bits != 0
+    }
+}
+
+/// Evaluates the controlling expression of an `#if` once its macros were
+/// expanded and `defined` was replaced.
+pub(super) fn evaluate(
+    tokens: &[PpToken<'_>],
+    directive: Span,
+    standard: Standard,
+) -> Result<bool, PreprocessError> {
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        directive,
+        standard,
+    };
+    let value = parser.expression(true)?;
+    match parser.peek() {
+        Some(token) => Err(PreprocessError::InvalidExpression { span: token.span() }),
+        None => Ok(value.is_true()),
+    }
+}
+
+struct Parser<'a, 'src> {
+    tokens: &'a [PpToken<'src>],
+    position: usize,
+    directive: Span,
+    standard: Standard,
+}
+
+impl<'a, 'src> Parser<'a, 'src> {
+    fn peek(&self) -> Option<&'a PpToken<'src>> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<&'a PpToken<'src>, PreprocessError> {
+        let token = self.peek().ok_or(PreprocessError::InvalidExpression {
+            span: self.tokens.last().map_or(self.directive, PpToken::span),
+        })?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &Token<'_>) -> Result<(), PreprocessError> {
+        let token = self.next()?;
+        if token.token() != expected {
+            return Err(PreprocessError::InvalidExpression { span: token.span() });
+        }
+        Ok(())
+    }
+
+    /// Parses an expression, only checking it when `evaluate` is false, as
+    /// in the unevaluated operand of `&&`.
+    fn expression(&mut self, evaluate: bool) -> Result<Value, PreprocessError> {
+        let mut value = self.conditional(evaluate)?;
+        while self.peek().map(PpToken::token) == Some(&Token::Comma) {
+            self.position += 1;
+            value = self.conditional(evaluate)?;
+        }
+        Ok(value)
+    }
+
+    fn conditional(&mut self, evaluate: bool) -> Result<Value, PreprocessError> {
+        let condition = self.binary(1, evaluate)?;
+        if self.peek().map(PpToken::token) != Some(&Token::Operator(OperatorType::Question)) {
+            return Ok(condition);
+        }
+        self.position += 1;
+        let then = self.expression(evaluate && condition.is_true())?;
+        self.expect(&Token::Colon)?;
+        let otherwise = self.conditional(evaluate && !condition.is_true())?;
+
+        let value = if condition.is_true() { then } else { otherwise };
+        Ok(Value {
+            bits: value.bits,
+            unsigned: then.unsigned || otherwise.unsigned,
+        })
+    }
+
+    /// Parses binary operators binding at least as tightly as `precedence`.
+    fn binary(&mut self, precedence: u8, evaluate: bool) -> Result<Value, PreprocessError> {
+        let mut lhs = self.unary(evaluate)?;
+        while let Some(token) = self.peek() {
+            let Token::Operator(operator) = *token.token() else {
+                break;
+            };
+            let Some(operator_precedence) = binary_precedence(operator) else {
+                break;
+            };
+            if operator_precedence < precedence {
+                break;
+            }
+            self.position += 1;
+
+            let evaluate_rhs = match operator {
+                OperatorType::And => evaluate && lhs.is_true(),
+                OperatorType::Or => evaluate && !lhs.is_true(),
+                _ => evaluate,
+            };
+            let rhs = self.binary(operator_precedence + 1, evaluate_rhs)?;
+            lhs = apply(operator, lhs, rhs, evaluate, token.span())?;
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self, evaluate: bool) -> Result<Value, PreprocessError> {
+        let token = self.next()?;
+        let value = match token.token() {
+            Token::Operator(OperatorType::Plus) => self.unary(evaluate)?,
+            Token::Operator(OperatorType::Minus) => {
+                let value = self.unary(evaluate)?;
+                Value {
+                    bits: value.bits.wrapping_neg(),
+                    ..value
+                }
+            }
+            Token::Operator(OperatorType::BitwiseNot) => {
+                let value = self.unary(evaluate)?;
+                Value {
+                    bits: !value.bits,
+                    ..value
+                }
+            }
+            Token::Operator(OperatorType::ExclamationPoint) => {
+                Value::signed(i64::from(!self.unary(evaluate)?.is_true()))
+            }
+            Token::OpenParen => {
+                let value = self.expression(evaluate)?;
+                self.expect(&Token::CloseParen)?;
+                value
+            }
+            Token::PpNumber(raw) => match check_number(raw, token.span())
+                .map_err(PreprocessError::Lex)?
+                .kind()
+            {
+                NumberKind::Integer { value, suffix } => Value {
+                    bits: value,
+                    unsigned: value > i64::MAX as u64
+                        || matches!(
+                            suffix,
+                            IntegerSuffix::Unsigned
+                                | IntegerSuffix::UnsignedLong
+                                | IntegerSuffix::UnsignedLongLong
+                        ),
+                },
+                NumberKind::Float { .. } => {
+                    return Err(PreprocessError::InvalidExpression { span: token.span() })
+                }
+            },
+            Token::Char(char) => Value::signed(char.value().into()),
+            // Identifiers left after expansion are not macros, except for
+            // C23's `true`.
+            Token::Id(name) => {
+                let is_true = *name == "true" && self.standard >= Standard::C23;
+                Value::signed(i64::from(is_true))
+            }
+            _ => return Err(PreprocessError::InvalidExpression { span: token.span() }),
+        };
+        Ok(value)
+    }
+}
+
+fn binary_precedence(operator: OperatorType) -> Option<u8> {
+    let precedence = match operator {
+        OperatorType::Or => 1,
+        OperatorType::And => 2,
+        OperatorType::BitwiseOr => 3,
+        OperatorType::BitwiseXor => 4,
+        OperatorType::BitwiseAnd => 5,
+        OperatorType::Equals | OperatorType::NotEqual => 6,
+        OperatorType::LessThan
+        | OperatorType::LessThanOrEqual
+        | OperatorType::GreaterThan
+        | OperatorType::GreaterThanOrEqual => 7,
+        OperatorType::ShiftLeft | OperatorType::ShiftRight => 8,
+        OperatorType::Plus | OperatorType::Minus => 9,
+        OperatorType::Asterisk | OperatorType::Divide | OperatorType::Modulo => 10,
+        _ => return None,
+    };
+    Some(precedence)
+}
+
+fn apply(
+    operator: OperatorType,
+    lhs: Value,
+    rhs: Value,
+    evaluate: bool,
+    span: Span,
+) -> Result<Value, PreprocessError> {
+    // The usual arithmetic conversions: one unsigned operand makes both
+    // unsigned.
+    let unsigned = lhs.unsigned || rhs.unsigned;
+    let (a, b) = (lhs.bits, rhs.bits);
+    let arithmetic = |bits| Value { bits, unsigned };
+    let boolean = |value: bool| Value::signed(value.into());
+    let compare = |a: u64, b: u64| {
+        if unsigned {
+            a.cmp(&b)
+        } else {
+            (a as i64).cmp(&(b as i64))
+        }
+    };
+
+    let value = match operator {
+        OperatorType::Or => boolean(lhs.is_true() || rhs.is_true()),
+        OperatorType::And => boolean(lhs.is_true() && rhs.is_true()),
+        OperatorType::BitwiseOr => arithmetic(a | b),
+        OperatorType::BitwiseXor => arithmetic(a ^ b),
+        OperatorType::BitwiseAnd => arithmetic(a & b),
+        OperatorType::Equals => boolean(a == b),
+        OperatorType::NotEqual => boolean(a != b),
+        OperatorType::LessThan => boolean(compare(a, b).is_lt()),
+        OperatorType::LessThanOrEqual => boolean(compare(a, b).is_le()),
+        OperatorType::GreaterThan => boolean(compare(a, b).is_gt()),
+        OperatorType::GreaterThanOrEqual => boolean(compare(a, b).is_ge()),
+        // The result of a shift has the type of its left operand.
+        OperatorType::ShiftLeft => Value {
+            bits: a.wrapping_shl(b as u32),
+            ..lhs
+        },
+        OperatorType::ShiftRight if lhs.unsigned => Value {
+            bits: a.wrapping_shr(b as u32),
+            ..lhs
+        },
+        OperatorType::ShiftRight => Value {
+            bits: (a as i64).wrapping_shr(b as u32) as u64,
+            ..lhs
+        },
+        OperatorType::Plus => arithmetic(a.wrapping_add(b)),
+        OperatorType::Minus => arithmetic(a.wrapping_sub(b)),
+        OperatorType::Asterisk => arithmetic(a.wrapping_mul(b)),
+        OperatorType::Divide | OperatorType::Modulo if b == 0 => {
+            if evaluate {
+                return Err(PreprocessError::DivisionByZero { span });
+            }
+            arithmetic(0)
+        }
+        OperatorType::Divide if unsigned => arithmetic(a / b),
+        OperatorType::Divide => arithmetic((a as i64).wrapping_div(b as i64) as u64),
+        OperatorType::Modulo if unsigned => arithmetic(a % b),
+        OperatorType::Modulo => arithmetic((a as i64).wrapping_rem(b as i64) as u64),
+        _ => unreachable!("{:?} is not a binary operator", operator),
+    };
+    Ok(value)
+}
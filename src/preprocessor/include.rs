@@ -0,0 +1,493 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::{OperatorType, PreprocessError, Token};
+
+use super::line::LineMarker;
+use super::{Frame, PpToken, Preprocessor};
+
+/// Where the preprocessor reads included files from.
+pub trait FileSystem {
+    /// The contents of the file at `path`, or `None` when there is none.
+    fn read(&self, path: &Path) -> Option<String>;
+
+    fn exists(&self, path: &Path) -> bool {
+        self.read(path).is_some()
+    }
+}
+
+/// The file system of the operating system.
+#[derive(Debug, Default)]
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn read(&self, path: &Path) -> Option<String> {
+        let bytes = fs::read(path).ok()?;
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+}
+
+/// A file system held in memory, so that headers don't need to be on disk.
+#[derive(Debug, Default)]
+pub struct VirtualFileSystem {
+    files: HashMap<PathBuf, String>,
+}
+
+impl VirtualFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_file(mut self, path: impl AsRef<Path>, contents: impl Into<String>) -> Self {
+        self.files.insert(normalize(path.as_ref()), contents.into());
+        self
+    }
+}
+
+impl FileSystem for VirtualFileSystem {
+    fn read(&self, path: &Path) -> Option<String> {
+        self.files.get(&normalize(path)).cloned()
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.contains_key(&normalize(path))
+    }
+}
+
+/// Finds the files named by `#include` directives.
+///
+/// `"quoted"` names are looked up in the directory of the including file,
+/// then like `<angled>` ones: in the `-I` directories, then in the
+/// `-isystem` ones.
+pub struct IncludeResolver {
+    file_system: Box<dyn FileSystem>,
+    user_dirs: Vec<PathBuf>,
+    system_dirs: Vec<PathBuf>,
+}
+
+impl IncludeResolver {
+    pub fn new(file_system: impl FileSystem + 'static) -> Self {
+        Self {
+            file_system: Box::new(file_system),
+            user_dirs: Vec::new(),
+            system_dirs: Vec::new(),
+        }
+    }
+
+    /// Adds a directory to search, like `-I`.
+    pub fn with_user_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.user_dirs.push(dir.into());
+        self
+    }
+
+    /// Adds a directory of system headers to search, like `-isystem`.
+    pub fn with_system_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.system_dirs.push(dir.into());
+        self
+    }
+
+    /// Finds the file `name` included from `includer`, returning its path and
+    /// the index of the search directory it was found in. For
+    /// `#include_next`, `after` is the index of the directory the includer
+    /// was found in.
+    fn find(
+        &self,
+        name: &str,
+        is_angled: bool,
+        includer: &Path,
+        after: Option<usize>,
+    ) -> Option<(PathBuf, Option<usize>)> {
+        if Path::new(name).is_absolute() {
+            let path = normalize(Path::new(name));
+            return self.file_system.exists(&path).then_some((path, None));
+        }
+
+        if !is_angled && after.is_none() {
+            let dir = includer.parent().unwrap_or(Path::new(""));
+            let path = normalize(&dir.join(name));
+            if self.file_system.exists(&path) {
+                return Some((path, None));
+            }
+        }
+        let first = after.map_or(0, |index| index + 1);
+        self.user_dirs
+            .iter()
+            .chain(&self.system_dirs)
+            .enumerate()
+            .skip(first)
+            .map(|(index, dir)| (normalize(&dir.join(name)), Some(index)))
+            .find(|(path, _)| self.file_system.exists(path))
+    }
+}
+
+impl Default for IncludeResolver {
+    fn default() -> Self {
+        Self::new(RealFileSystem)
+    }
+}
+
+/// Removes the `.` and `..` components of `path` where it can, so that a
+/// file has a single path.
+pub(super) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Why an `#include` didn't read its file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The file has `#pragma once` and was read already.
+    PragmaOnce,
+    /// The file is wrapped in an `#ifndef` of this macro, which is defined.
+    IncludeGuard(String),
+    /// The file is already being read, so including it again would never end.
+    Cycle,
+}
+
+/// An `#include` of the include graph, or the main file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Include {
+    path: PathBuf,
+    includer: Option<usize>,
+    line: usize,
+    skipped: Option<SkipReason>,
+}
+
+impl Include {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The index of the including file in the graph, `None` for the main
+    /// file.
+    pub fn includer(&self) -> Option<usize> {
+        self.includer
+    }
+
+    /// The line of the `#include` in the including file.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn skipped(&self) -> Option<&SkipReason> {
+        self.skipped.as_ref()
+    }
+}
+
+/// The files the preprocessor read, in order, each knowing which file
+/// included it. `Display` prints it as an indented tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IncludeGraph {
+    includes: Vec<Include>,
+}
+
+impl IncludeGraph {
+    pub fn includes(&self) -> &[Include] {
+        &self.includes
+    }
+
+    pub(super) fn set_path(&mut self, index: usize, path: PathBuf) {
+        self.includes[index].path = path;
+    }
+
+    fn push(&mut self, include: Include) -> usize {
+        self.includes.push(include);
+        self.includes.len() - 1
+    }
+}
+
+impl fmt::Display for IncludeGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for include in &self.includes {
+            let depth =
+                std::iter::successors(include.includer, |&index| self.includes[index].includer)
+                    .count();
+            write!(f, "{:1$}{2}", "", depth * 2, include.path.display())?;
+            match &include.skipped {
+                Some(SkipReason::PragmaOnce) => write!(f, " (#pragma once)")?,
+                Some(SkipReason::IncludeGuard(name)) => write!(f, " (guarded by {})", name)?,
+                Some(SkipReason::Cycle) => write!(f, " (cycle)")?,
+                None => {}
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// A file that was read, whose spans start at `base`.
+#[derive(Debug)]
+pub(super) struct SourceFile {
+    pub(super) path: PathBuf,
+    pub(super) base: usize,
+    pub(super) len: usize,
+    /// The index of the search directory the file was found in.
+    dir: Option<usize>,
+    /// The index of the file in the include graph.
+    node: Option<usize>,
+    pub(super) markers: Vec<LineMarker>,
+}
+
+/// How far a file is into the `#ifndef X ... #endif` pattern of an include
+/// guard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Guard<'src> {
+    /// Nothing but whitespace and comments were read.
+    Start,
+    /// Inside the `#ifndef` of the guard.
+    Open(&'src str),
+    /// Nothing but whitespace and comments followed the `#endif`.
+    Closed(&'src str),
+    /// The file isn't guarded.
+    None,
+}
+
+impl<'src> Preprocessor<'src> {
+    /// Starts reading `text`, the contents of the file at `path`, included
+    /// from the given line of the file at index `includer` of the graph.
+    pub(super) fn push_file(
+        &mut self,
+        path: PathBuf,
+        text: &'src str,
+        dir: Option<usize>,
+        includer: Option<usize>,
+        line: usize,
+    ) {
+        let node = self.include_graph.push(Include {
+            path: path.clone(),
+            includer,
+            line,
+            skipped: None,
+        });
+        self.push_frame(path, text, dir, Some(node));
+    }
+
+    /// Starts reading text that isn't a file, like the predefined macros.
+    /// It is left out of the include graph.
+    pub(super) fn push_buffer(&mut self, name: &str, text: &'src str) {
+        self.push_frame(PathBuf::from(name), text, None, None);
+    }
+
+    fn push_frame(
+        &mut self,
+        path: PathBuf,
+        text: &'src str,
+        dir: Option<usize>,
+        node: Option<usize>,
+    ) {
+        self.files.push(SourceFile {
+            path,
+            base: self.next_base,
+            len: text.len(),
+            dir,
+            node,
+            markers: Vec::new(),
+        });
+        // Leaving a gap between files gives the end of each its own offset.
+        self.next_base += text.len() + 1;
+        self.frames.push(Frame {
+            text,
+            scanner: self.scanner(text),
+            file: self.files.len() - 1,
+            lex_errors: 0,
+            previous_end: None,
+            at_line_start: true,
+            conditionals: self.conditionals.len(),
+            guard: Guard::Start,
+            resume: std::mem::take(&mut self.pending),
+        });
+    }
+
+    /// Goes back to the including file once an included one ends.
+    pub(super) fn end_file(&mut self, eof: &PpToken<'src>) {
+        let depth = self.frames.last().map_or(0, |frame| frame.conditionals);
+        self.close_conditionals(eof, depth);
+        let Some(frame) = self.frames.pop() else {
+            return;
+        };
+        if let Guard::Closed(name) = frame.guard {
+            let path = self.files[frame.file].path.clone();
+            self.guards.insert(path, name);
+        }
+        self.pending = frame.resume;
+    }
+
+    pub(super) fn current_file(&self) -> &SourceFile {
+        let file = self.frames.last().map_or(0, |frame| frame.file);
+        &self.files[file]
+    }
+
+    /// Runs an `#include` or, when `is_next`, an `#include_next`.
+    pub(super) fn include(
+        &mut self,
+        directive: &PpToken<'src>,
+        rest: &[PpToken<'src>],
+        is_next: bool,
+    ) {
+        let span = rest.first().unwrap_or(directive).span();
+        let Some((name, is_angled)) = self.header_name(rest) else {
+            self.errors.push(PreprocessError::InvalidInclude { span });
+            return;
+        };
+        let Some((path, dir)) = self.find_header(&name, is_angled, is_next) else {
+            self.errors
+                .push(PreprocessError::IncludeNotFound { name, span });
+            return;
+        };
+
+        let includer = self.current_file().node;
+        let line = directive.span().line();
+        if let Some(reason) = self.skip_reason(&path) {
+            if reason == SkipReason::Cycle {
+                self.errors.push(PreprocessError::IncludeCycle {
+                    path: path.display().to_string(),
+                    span,
+                });
+            }
+            self.include_graph.push(Include {
+                path,
+                includer,
+                line,
+                skipped: Some(reason),
+            });
+            return;
+        }
+
+        let text = self.resolver.file_system.read(&path).unwrap_or_default();
+        let text = self.arena.alloc(text);
+        self.push_file(path, text, dir, includer, line);
+    }
+
+    /// Whether `__has_include` finds `header_name`, spelled with its
+    /// delimiters.
+    pub(super) fn has_include(&self, header_name: &str, is_next: bool) -> bool {
+        let is_angled = header_name.starts_with('<');
+        let name = &header_name[1..header_name.len() - 1];
+        self.find_header(name, is_angled, is_next).is_some()
+    }
+
+    fn find_header(
+        &self,
+        name: &str,
+        is_angled: bool,
+        is_next: bool,
+    ) -> Option<(PathBuf, Option<usize>)> {
+        let includer = self.current_file();
+        let after = includer.dir.filter(|_| is_next);
+        self.resolver.find(name, is_angled, &includer.path, after)
+    }
+
+    /// The name of the header to include, and whether it is `<angled>`.
+    /// Without a header name, the directive's macros are expanded first.
+    fn header_name(&mut self, rest: &[PpToken<'src>]) -> Option<(String, bool)> {
+        if let Some(Token::HeaderName(name)) = rest.first().map(PpToken::token) {
+            return Some((name[1..name.len() - 1].to_string(), name.starts_with('<')));
+        }
+
+        let tokens = self.expand_arg(rest.to_vec());
+        match tokens.first()?.token() {
+            Token::String(_) if tokens[0].spelling().starts_with('"') => {
+                let spelling = tokens[0].spelling();
+                Some((spelling[1..spelling.len() - 1].to_string(), false))
+            }
+            Token::Operator(OperatorType::LessThan) => {
+                let close = tokens.iter().position(|token| {
+                    token.token() == &Token::Operator(OperatorType::GreaterThan)
+                })?;
+                let mut name = String::new();
+                for token in &tokens[1..close] {
+                    if token.leading_space && !name.is_empty() {
+                        name.push(' ');
+                    }
+                    name.push_str(token.spelling());
+                }
+                Some((name, true))
+            }
+            _ => None,
+        }
+    }
+
+    fn skip_reason(&self, path: &Path) -> Option<SkipReason> {
+        if self.once.contains(path) {
+            return Some(SkipReason::PragmaOnce);
+        }
+        let is_defined = |name: &str| self.macros.contains_key(name);
+        if let Some(name) = self.guards.get(path).filter(|name| is_defined(name)) {
+            return Some(SkipReason::IncludeGuard(name.to_string()));
+        }
+        // Reading a file again while it is being read is fine when its guard
+        // makes it empty.
+        let frame = self
+            .frames
+            .iter()
+            .find(|frame| self.files[frame.file].path == path)?;
+        match frame.guard {
+            Guard::Open(name) if is_defined(name) => {
+                Some(SkipReason::IncludeGuard(name.to_string()))
+            }
+            _ => Some(SkipReason::Cycle),
+        }
+    }
+
+    /// Follows the directives of a file for the include guard pattern, before
+    /// running them.
+    pub(super) fn track_guard(&mut self, directive: &PpToken<'src>, rest: &[PpToken<'src>]) {
+        let depth = self.conditionals.len();
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        let is_outermost = depth == frame.conditionals + 1;
+        frame.guard = match (frame.guard, directive.spelling()) {
+            (Guard::Start, "ifndef") => match rest.first().map(PpToken::token) {
+                Some(Token::Id(name)) => Guard::Open(name),
+                _ => Guard::None,
+            },
+            (Guard::Open(name), "endif") if is_outermost => Guard::Closed(name),
+            (Guard::Open(_), "else" | "elif" | "elifdef" | "elifndef") if is_outermost => {
+                Guard::None
+            }
+            (Guard::Open(name), _) => Guard::Open(name),
+            _ => Guard::None,
+        };
+    }
+
+    /// Notes that a file has a token outside of its include guard, if any.
+    pub(super) fn leave_guard(&mut self) {
+        if let Some(frame) = self.frames.last_mut() {
+            if matches!(frame.guard, Guard::Start | Guard::Closed(_)) {
+                frame.guard = Guard::None;
+            }
+        }
+    }
+
+    /// The files read so far.
+    pub fn include_graph(&self) -> &IncludeGraph {
+        &self.include_graph
+    }
+
+    /// The macro guarding the file at `path` against being included twice,
+    /// once the file was read.
+    pub fn include_guard(&self, path: impl AsRef<Path>) -> Option<&'src str> {
+        self.guards.get(&normalize(path.as_ref())).copied()
+    }
+}
@@ -0,0 +1,133 @@
+use std::path::{Path, PathBuf};
+
+use crate::{Encoding, PreprocessError, Span, Token};
+
+use super::include::SourceFile;
+use super::{PpToken, Preprocessor};
+
+/// A `#line` directive or a linemarker, renumbering the lines following it.
+#[derive(Debug)]
+pub(super) struct LineMarker {
+    /// Where the renumbered lines start.
+    offset: usize,
+    /// What to add to a physical line number to get the presumed one.
+    delta: isize,
+    /// The presumed name of the file, when one was given.
+    path: Option<PathBuf>,
+}
+
+impl SourceFile {
+    fn marker(&self, offset: usize) -> Option<&LineMarker> {
+        self.markers
+            .iter()
+            .rev()
+            .find(|marker| marker.offset <= offset)
+    }
+
+    /// The presumed number of the physical `line` read last.
+    pub(super) fn presumed_line(&self, line: usize) -> usize {
+        let delta = self.markers.last().map_or(0, |marker| marker.delta);
+        line.saturating_add_signed(delta)
+    }
+
+    /// The presumed name of the file at `offset`.
+    pub(super) fn presumed_path(&self, offset: usize) -> &Path {
+        self.markers
+            .iter()
+            .rev()
+            .filter(|marker| marker.offset <= offset)
+            .find_map(|marker| marker.path.as_deref())
+            .unwrap_or(&self.path)
+    }
+}
+
+impl<'src> Preprocessor<'src> {
+    /// Runs a `#line` directive or, when `directive` is a number, a
+    /// linemarker like `# 42 "file.c" 2`, given the position following its
+    /// line.
+    pub(super) fn line(&mut self, directive: &PpToken<'src>, rest: &[PpToken<'src>], end: Span) {
+        // Unlike `#line`, linemarkers are the output of a preprocessor, so
+        // they aren't expanded.
+        let is_linemarker = matches!(directive.token(), Token::PpNumber(_));
+        let tokens = if is_linemarker {
+            std::iter::once(directive).chain(rest).cloned().collect()
+        } else {
+            self.expand_arg(rest.to_vec())
+        };
+
+        let Some((line, path)) = parse_line(&tokens, is_linemarker) else {
+            let span = tokens.first().unwrap_or(directive).span();
+            self.errors
+                .push(PreprocessError::InvalidLineDirective { span });
+            return;
+        };
+        let Some(file) = self.frames.last().map(|frame| frame.file) else {
+            return;
+        };
+        let file = &mut self.files[file];
+        let physical = end.line() as isize - file.markers.last().map_or(0, |marker| marker.delta);
+        file.markers.push(LineMarker {
+            offset: end.start(),
+            delta: line as isize - physical,
+            path,
+        });
+    }
+
+    fn source_file(&self, span: Span) -> Option<&SourceFile> {
+        self.files
+            .iter()
+            .find(|file| (file.base..=file.base + file.len).contains(&span.start()))
+    }
+
+    /// The path of the file `span` is in.
+    pub fn file_path(&self, span: Span) -> Option<&Path> {
+        self.source_file(span).map(|file| file.path.as_path())
+    }
+
+    /// The name `#line` directives give to the file `span` is in, or its path
+    /// without them.
+    pub fn presumed_path(&self, span: Span) -> Option<&Path> {
+        self.source_file(span)
+            .map(|file| file.presumed_path(span.start()))
+    }
+
+    /// Maps `span`, whose line is the one `#line` directives give, back to
+    /// the line it is on in its file.
+    pub fn physical_span(&self, span: Span) -> Span {
+        let delta = self
+            .source_file(span)
+            .and_then(|file| file.marker(span.start()))
+            .map_or(0, |marker| marker.delta);
+        let line = span.line().saturating_add_signed(-delta);
+        Span::new(span.start(), span.end(), line, span.column())
+    }
+}
+
+/// Parses the line number and the optional file name of a `#line`, or of a
+/// linemarker which may be followed by flags.
+fn parse_line(tokens: &[PpToken<'_>], is_linemarker: bool) -> Option<(usize, Option<PathBuf>)> {
+    let (number, rest) = tokens.split_first()?;
+    let digits = number.spelling();
+    if !matches!(number.token(), Token::PpNumber(_)) || !digits.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    // Only linemarkers may give the line 0.
+    let line: usize = digits.parse().ok()?;
+    if line > 2_147_483_647 || (line == 0 && !is_linemarker) {
+        return None;
+    }
+
+    let (path, rest) = match rest.split_first() {
+        Some((name, rest)) => match name.token() {
+            Token::String(name) if name.encoding() == Encoding::Plain => {
+                (Some(PathBuf::from(name.value()?.as_ref())), rest)
+            }
+            _ => return None,
+        },
+        None => (None, rest),
+    };
+    let is_flag = |token: &PpToken<'_>| matches!(token.spelling(), "1" | "2" | "3" | "4");
+    let is_valid = (is_linemarker || rest.is_empty()) && rest.iter().all(is_flag);
+    is_valid.then_some((line, path))
+}
@@ -0,0 +1,538 @@
+use std::rc::Rc;
+
+use crate::{PreprocessError, Scanner, Span, SpannedToken, Token};
+
+use super::{Expansion, HideSet, PpToken, Preprocessor};
+
+/// The name C gives to the variable arguments of a variadic macro.
+const VA_ARGS: &str = "__VA_ARGS__";
+const VA_OPT: &str = "__VA_OPT__";
+
+/// How deeply macro arguments may be expanded within each other, as in
+/// `f(f(f(x)))`, which bounds the recursion.
+const MAX_ARG_DEPTH: usize = 256;
+
+/// A macro definition.
+#[derive(Debug)]
+pub(super) struct Macro<'src> {
+    /// The span of the macro's name in its `#define`.
+    pub(super) span: Span,
+    /// The parameters of a function-like macro, ending in `__VA_ARGS__` when
+    /// the macro is variadic.
+    params: Option<Vec<&'src str>>,
+    body: Vec<PpToken<'src>>,
+}
+
+impl<'src> Macro<'src> {
+    /// Parses the rest of a `#define` line following the macro's name.
+    pub(super) fn parse(
+        name: &PpToken<'src>,
+        rest: &[PpToken<'src>],
+    ) -> Result<Self, PreprocessError> {
+        if !matches!(name.token(), Token::Id(name) if *name != "defined") {
+            return Err(PreprocessError::InvalidMacroName { span: name.span() });
+        }
+
+        let (params, body) = match rest.split_first() {
+            Some((paren, rest)) if paren.token() == &Token::OpenParen && !paren.leading_space => {
+                let (params, body) = parse_params(paren, rest)?;
+                (Some(params), body)
+            }
+            _ => (None, rest),
+        };
+        let definition = Self {
+            span: name.span(),
+            params,
+            body: body.to_vec(),
+        };
+        definition.check_body()?;
+        Ok(definition)
+    }
+
+    fn check_body(&self) -> Result<(), PreprocessError> {
+        check_edges(&self.body)?;
+        for (index, token) in self.body.iter().enumerate() {
+            match token.token() {
+                Token::Hash if self.params.is_some() => {
+                    let next = self.body.get(index + 1);
+                    if next.and_then(|next| self.param_index(next)).is_none() {
+                        return Err(PreprocessError::StringifyNonParameter { span: token.span() });
+                    }
+                }
+                Token::Id(VA_ARGS | VA_OPT) if !self.is_variadic() => {
+                    return Err(PreprocessError::VariadicOutsideVariadicMacro {
+                        span: token.span(),
+                    });
+                }
+                Token::Id(VA_OPT) => check_edges(va_opt_content(&self.body, index).0)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn is_variadic(&self) -> bool {
+        self.params
+            .as_ref()
+            .is_some_and(|params| params.last() == Some(&VA_ARGS))
+    }
+
+    fn param_index(&self, token: &PpToken<'src>) -> Option<usize> {
+        let Token::Id(name) = token.token() else {
+            return None;
+        };
+        self.params.as_ref()?.iter().position(|param| param == name)
+    }
+
+    /// Whether the parameter `param` is an operand of `#` or `##`, which take
+    /// its argument as written rather than expanded.
+    fn is_operand(&self, param: usize) -> bool {
+        let is_param = |token: Option<&PpToken<'src>>| {
+            token.and_then(|token| self.param_index(token)) == Some(param)
+        };
+        self.body
+            .iter()
+            .enumerate()
+            .any(|(index, token)| match token.token() {
+                Token::Hash => is_param(self.body.get(index + 1)),
+                Token::HashHash => {
+                    is_param(index.checked_sub(1).and_then(|index| self.body.get(index)))
+                        || is_param(self.body.get(index + 1))
+                }
+                _ => false,
+            })
+    }
+
+    /// Whether a redefinition with `other` is allowed: the parameters and the
+    /// replacement lists must be identical, whitespace included.
+    pub(super) fn is_same_as(&self, other: &Macro<'src>) -> bool {
+        self.params == other.params
+            && self.body.len() == other.body.len()
+            && self
+                .body
+                .iter()
+                .zip(&other.body)
+                .enumerate()
+                .all(|(i, (a, b))| {
+                    a.spelling() == b.spelling() && (i == 0 || a.leading_space == b.leading_space)
+                })
+    }
+}
+
+/// Checks that `##` doesn't start or end `tokens`, as it has no operand there.
+fn check_edges(tokens: &[PpToken<'_>]) -> Result<(), PreprocessError> {
+    for edge in [tokens.first(), tokens.last()].into_iter().flatten() {
+        if edge.token() == &Token::HashHash {
+            return Err(PreprocessError::PasteAtEdge { span: edge.span() });
+        }
+    }
+    Ok(())
+}
+
+/// Parses the parameters following the `(` of a function-like macro,
+/// returning them with the replacement list after the `)`.
+fn parse_params<'a, 'src>(
+    paren: &PpToken<'src>,
+    tokens: &'a [PpToken<'src>],
+) -> Result<(Vec<&'src str>, &'a [PpToken<'src>]), PreprocessError> {
+    let mut params = Vec::new();
+    let mut tokens = tokens.iter();
+    let error = |token: Option<&PpToken<'src>>| PreprocessError::InvalidParameterList {
+        span: token.unwrap_or(paren).span(),
+    };
+
+    let mut token = tokens.next();
+    if token.map(PpToken::token) != Some(&Token::CloseParen) {
+        loop {
+            match token.map(PpToken::token) {
+                Some(Token::Id(name)) if *name != VA_ARGS && !params.contains(name) => {
+                    params.push(*name);
+                }
+                Some(Token::Ellipsis) => {
+                    params.push(VA_ARGS);
+                    token = tokens.next();
+                    break;
+                }
+                _ => return Err(error(token)),
+            }
+            token = tokens.next();
+            match token.map(PpToken::token) {
+                Some(Token::Comma) => token = tokens.next(),
+                _ => break,
+            }
+        }
+        if token.map(PpToken::token) != Some(&Token::CloseParen) {
+            return Err(error(token));
+        }
+    }
+    Ok((params, tokens.as_slice()))
+}
+
+impl<'src> Preprocessor<'src> {
+    /// Expands `token` if it names a macro, leaving the result to be rescanned
+    /// with the rest of the input. Returns the token back when it doesn't.
+    pub(super) fn expand(&mut self, token: PpToken<'src>) -> Option<PpToken<'src>> {
+        let Token::Id(name) = *token.token() else {
+            return Some(token);
+        };
+        if token.hide_set.contains(name) {
+            return Some(token);
+        }
+        if name == "_Pragma" {
+            return self.pragma_operator(token);
+        }
+        let Some(definition) = self.macros.get(name).cloned() else {
+            return Some(self.expand_dynamic(token));
+        };
+
+        let (args, mut hide_set) = if definition.params.is_none() {
+            (Vec::new(), (*token.hide_set).clone())
+        } else if self.skip_open_paren() {
+            let (args, close) = self.collect_args(&token, &definition)?;
+            let hide_set = token.hide_set.intersection(&close.hide_set).copied();
+            (args, hide_set.collect())
+        } else {
+            return Some(token);
+        };
+        hide_set.insert(name);
+        let hide_set = HideSet::new(hide_set);
+
+        let expansion = Rc::new(Expansion {
+            name,
+            definition: definition.span,
+            invocation: token.span(),
+            parent: token.expansion.clone(),
+        });
+        let mut args = Arguments::new(args);
+        let mut tokens = self.substitute(&definition, &definition.body, &mut args, &expansion);
+        for expanded in &mut tokens {
+            expanded.hide_set = if expanded.hide_set.is_empty() {
+                hide_set.clone()
+            } else {
+                HideSet::new(expanded.hide_set.union(&hide_set).copied().collect())
+            };
+            expanded.starts_line = false;
+        }
+        if let Some(first) = tokens.first_mut() {
+            first.leading_space = token.leading_space;
+            first.starts_line = token.starts_line;
+        }
+        for expanded in tokens.into_iter().rev() {
+            self.pending.push_front(expanded);
+        }
+        None
+    }
+
+    /// Consumes the `(` following the name of a function-like macro, if
+    /// there is one. Without it the name isn't a call.
+    pub(super) fn skip_open_paren(&mut self) -> bool {
+        while let Some(token) = self.next_raw() {
+            match token.token() {
+                Token::Newline => {}
+                Token::OpenParen => return true,
+                _ => {
+                    self.pending.push_front(token);
+                    return false;
+                }
+            }
+        }
+        false
+    }
+
+    /// Collects the arguments of a call up to its `)`, which is returned
+    /// along with them.
+    fn collect_args(
+        &mut self,
+        name: &PpToken<'src>,
+        definition: &Macro<'src>,
+    ) -> Option<(Vec<Vec<PpToken<'src>>>, PpToken<'src>)> {
+        let params = definition.params.as_deref().unwrap_or_default();
+        let mut args = vec![Vec::new()];
+        let mut depth = 0;
+        let mut newline = false;
+        let close = loop {
+            let Some(mut token) = self.next_raw() else {
+                break None;
+            };
+            match token.token() {
+                Token::Eof => {
+                    self.pending.push_front(token);
+                    break None;
+                }
+                Token::Newline => {
+                    newline = true;
+                    continue;
+                }
+                Token::CloseParen if depth == 0 => break Some(token),
+                Token::Comma
+                    if depth == 0 && (args.len() < params.len() || !definition.is_variadic()) =>
+                {
+                    args.push(Vec::new());
+                    continue;
+                }
+                Token::OpenParen => depth += 1,
+                Token::CloseParen => depth -= 1,
+                _ => {}
+            }
+            token.leading_space |= std::mem::take(&mut newline);
+            token.starts_line = false;
+            args.last_mut()?.push(token);
+        };
+
+        let Some(close) = close else {
+            self.errors.push(PreprocessError::UnterminatedMacroCall {
+                name: name.spelling().to_string(),
+                span: name.span(),
+            });
+            return None;
+        };
+
+        if params.is_empty() && args.len() == 1 && args[0].is_empty() {
+            args.clear();
+        }
+        if definition.is_variadic() && args.len() + 1 == params.len() {
+            args.push(Vec::new());
+        }
+        if args.len() != params.len() {
+            let variadic = usize::from(definition.is_variadic());
+            self.errors.push(PreprocessError::WrongArgumentCount {
+                name: name.spelling().to_string(),
+                expected: params.len() - variadic,
+                found: args.len(),
+                span: name.span(),
+            });
+            return None;
+        }
+        Some((args, close))
+    }
+
+    /// Replaces the parameters in `body` by their arguments, applying `#`,
+    /// `##` and `__VA_OPT__`.
+    fn substitute(
+        &mut self,
+        definition: &Macro<'src>,
+        body: &[PpToken<'src>],
+        args: &mut Arguments<'src>,
+        expansion: &Rc<Expansion<'src>>,
+    ) -> Vec<PpToken<'src>> {
+        let from_body = |token: &PpToken<'src>| PpToken {
+            expansion: Some(expansion.clone()),
+            ..token.clone()
+        };
+        let is_paste = |index: usize| body.get(index).map(PpToken::token) == Some(&Token::HashHash);
+
+        let mut tokens = Vec::new();
+        // The leading space of the placemarker left by an empty operand of
+        // `##`, which the next `##` pastes to instead of the last token.
+        let mut placemarker = None;
+        let mut index = 0;
+        while let Some(token) = body.get(index) {
+            let param = definition.param_index(token);
+            let next_param = body
+                .get(index + 1)
+                .and_then(|next| definition.param_index(next));
+
+            if token.token() == &Token::Hash && definition.params.is_some() {
+                let arg = &args.raw[next_param.unwrap_or_default()];
+                tokens.push(self.stringify(arg, &from_body(token)));
+                placemarker = None;
+                index += 2;
+            } else if token.token() == &Token::HashHash {
+                let rhs = match next_param {
+                    Some(param) => args.raw[param].clone(),
+                    None => vec![from_body(&body[index + 1])],
+                };
+                if let Some(leading_space) = placemarker {
+                    // Pasting to a placemarker leaves the other operand.
+                    if !rhs.is_empty() {
+                        push_arg(&mut tokens, rhs, leading_space);
+                        placemarker = None;
+                    }
+                } else if let Some((first, rest)) = rhs.split_first() {
+                    self.paste(&mut tokens, first.clone());
+                    tokens.extend(rest.iter().cloned());
+                }
+                index += 2;
+            } else if let Some(param) = param.filter(|_| is_paste(index + 1)) {
+                let arg = &args.raw[param];
+                if arg.is_empty() {
+                    placemarker = Some(token.leading_space);
+                } else {
+                    push_arg(&mut tokens, arg.clone(), token.leading_space);
+                    placemarker = None;
+                }
+                index += 1;
+            } else if let Some(param) = param {
+                let arg = self.expanded_arg(definition, args, param).to_vec();
+                push_arg(&mut tokens, arg, token.leading_space);
+                placemarker = None;
+                index += 1;
+            } else if token.token() == &Token::Id(VA_OPT) {
+                let (content, end) = va_opt_content(body, index);
+                let last = args.raw.len() - 1;
+                if !self.expanded_arg(definition, args, last).is_empty() {
+                    let content = self.substitute(definition, content, args, expansion);
+                    push_arg(&mut tokens, content, token.leading_space);
+                }
+                placemarker = None;
+                index = end;
+            } else {
+                tokens.push(from_body(token));
+                placemarker = None;
+                index += 1;
+            }
+        }
+        tokens
+    }
+
+    /// The argument of `param` once expanded. It is only expanded the first
+    /// time, and its tokens as written are given up unless `#` or `##` need
+    /// them, so that nested calls don't keep a copy of them at every level.
+    fn expanded_arg<'a>(
+        &mut self,
+        definition: &Macro<'src>,
+        args: &'a mut Arguments<'src>,
+        param: usize,
+    ) -> &'a [PpToken<'src>] {
+        if args.expanded[param].is_none() {
+            let arg = if definition.is_operand(param) {
+                args.raw[param].clone()
+            } else {
+                std::mem::take(&mut args.raw[param])
+            };
+            args.expanded[param] = Some(self.expand_arg(arg));
+        }
+        args.expanded[param].as_deref().unwrap_or_default()
+    }
+
+    /// Fully expands a macro argument on its own, as if it were the rest of
+    /// the input.
+    pub(super) fn expand_arg(&mut self, arg: Vec<PpToken<'src>>) -> Vec<PpToken<'src>> {
+        if self.arg_depth == MAX_ARG_DEPTH {
+            if let Some(first) = arg.first() {
+                let span = first.span();
+                self.errors
+                    .push(PreprocessError::MacroNestingTooDeep { span });
+            }
+            return arg;
+        }
+        let mut pending = std::mem::replace(&mut self.pending, arg.into());
+        // Most of an enclosing argument was read as the arguments of this
+        // call, so its room isn't kept while the call is expanded.
+        pending.shrink_to_fit();
+        self.arg_depth += 1;
+        let mut expanded = Vec::new();
+        while let Some(token) = self.next_raw() {
+            expanded.extend(self.expand(token));
+        }
+        self.pending = pending;
+        self.arg_depth -= 1;
+        expanded
+    }
+
+    /// Turns an argument into a string literal spelling it, for `#`.
+    fn stringify(&mut self, arg: &[PpToken<'src>], hash: &PpToken<'src>) -> PpToken<'src> {
+        let mut text = String::from("\"");
+        for (index, token) in arg.iter().enumerate() {
+            if index > 0 && token.leading_space {
+                text.push(' ');
+            }
+            if let Token::String(_) | Token::Char(_) = token.token() {
+                for char in token.spelling().chars() {
+                    if char == '"' || char == '\\' {
+                        text.push('\\');
+                    }
+                    text.push(char);
+                }
+            } else {
+                text.push_str(token.spelling());
+            }
+        }
+        text.push('"');
+
+        let text = self.arena.alloc(text);
+        let token = self
+            .relex(text, hash.span())
+            .unwrap_or_else(|| SpannedToken::new(Token::Invalid(text), hash.span(), text));
+        PpToken {
+            token,
+            ..hash.clone()
+        }
+    }
+
+    /// Pastes `rhs` to the last token of `tokens`, for `##`.
+    fn paste(&mut self, tokens: &mut Vec<PpToken<'src>>, rhs: PpToken<'src>) {
+        let Some(lhs) = tokens.pop() else {
+            tokens.push(rhs);
+            return;
+        };
+
+        let text = self
+            .arena
+            .alloc(format!("{}{}", lhs.spelling(), rhs.spelling()));
+        match self.relex(text, lhs.span()) {
+            Some(token) => tokens.push(PpToken { token, ..lhs }),
+            None => {
+                self.errors.push(PreprocessError::InvalidPaste {
+                    text: text.to_string(),
+                    span: lhs.span(),
+                });
+                tokens.extend([lhs, rhs]);
+            }
+        }
+    }
+
+    /// Scans `text` as a single token, placed at `span`.
+    pub(super) fn relex(&self, text: &'src str, span: Span) -> Option<SpannedToken<'src>> {
+        let mut scanner = Scanner::new(text)
+            .with_standard(self.standard)
+            .with_preprocessing(true);
+        let token = scanner.next()?;
+        let is_single = token.spelling() == text && scanner.errors().is_empty();
+        (is_single && !text.is_empty()).then(|| SpannedToken::new(token.into_token(), span, text))
+    }
+}
+
+/// The arguments of a macro call, as written and once expanded.
+struct Arguments<'src> {
+    raw: Vec<Vec<PpToken<'src>>>,
+    expanded: Vec<Option<Vec<PpToken<'src>>>>,
+}
+
+impl<'src> Arguments<'src> {
+    fn new(raw: Vec<Vec<PpToken<'src>>>) -> Self {
+        let expanded = raw.iter().map(|_| None).collect();
+        Self { raw, expanded }
+    }
+}
+
+/// Appends an argument, giving its first token the leading space of the
+/// parameter it replaces.
+fn push_arg<'src>(tokens: &mut Vec<PpToken<'src>>, arg: Vec<PpToken<'src>>, leading_space: bool) {
+    let start = tokens.len();
+    tokens.extend(arg);
+    if let Some(first) = tokens.get_mut(start) {
+        first.leading_space = leading_space;
+    }
+}
+
+/// Finds the tokens between the parentheses of the `__VA_OPT__` at `index`,
+/// and the index following them.
+fn va_opt_content<'a, 'src>(
+    body: &'a [PpToken<'src>],
+    index: usize,
+) -> (&'a [PpToken<'src>], usize) {
+    if body.get(index + 1).map(PpToken::token) != Some(&Token::OpenParen) {
+        return (&[], index + 1);
+    }
+    let mut depth = 0;
+    for (end, token) in body.iter().enumerate().skip(index + 1) {
+        match token.token() {
+            Token::OpenParen => depth += 1,
+            Token::CloseParen if depth == 1 => return (&body[index + 2..end], end + 1),
+            Token::CloseParen => depth -= 1,
+            _ => {}
+        }
+    }
+    (&body[index + 2..], body.len())
+}
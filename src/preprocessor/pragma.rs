@@ -0,0 +1,272 @@
+use crate::{Encoding, PreprocessError, Scanner, Span, SpannedToken, Token};
+
+use super::{PpToken, Preprocessor};
+
+/// A `#pragma` directive or `_Pragma` operator, which the preprocessor
+/// passes on as a `Token::Pragma`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pragma<'src> {
+    /// `#pragma once`, which the preprocessor also honors itself.
+    Once,
+    /// `#pragma pack(...)`.
+    Pack(PackPragma<'src>),
+    /// `#pragma GCC diagnostic ...`, also spelled `clang diagnostic`.
+    Diagnostic(DiagnosticPragma<'src>),
+    /// `#pragma STDC FP_CONTRACT ON`, `OFF` or `DEFAULT`.
+    FpContract(PragmaSwitch),
+    /// Any other pragma, holding the text following `#pragma`.
+    Unknown(&'src str),
+}
+
+/// The forms of `#pragma pack`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PackPragma<'src> {
+    /// `pack(n)`, or `pack()` going back to the default alignment.
+    Set(Option<u32>),
+    /// `pack(push)`, optionally naming the pushed entry and setting a new
+    /// alignment, as in `pack(push, name, 4)`.
+    Push {
+        name: Option<&'src str>,
+        alignment: Option<u32>,
+    },
+    /// `pack(pop)`, or `pack(pop, name)` popping up to the named entry.
+    Pop { name: Option<&'src str> },
+    /// `pack(show)`.
+    Show,
+}
+
+/// The forms of `#pragma GCC diagnostic`. Options are spelled like
+/// `-Wunused-variable`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticPragma<'src> {
+    Push,
+    Pop,
+    Ignored(&'src str),
+    Warning(&'src str),
+    Error(&'src str),
+}
+
+/// The state a standard pragma like `FP_CONTRACT` sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PragmaSwitch {
+    On,
+    Off,
+    Default,
+}
+
+impl<'src> Pragma<'src> {
+    /// Parses the tokens following `#pragma`, where `text` is their spelling.
+    /// Returns `None` when a known pragma is malformed.
+    fn parse(tokens: &[PpToken<'src>], text: &'src str) -> Option<Self> {
+        let spellings: Vec<&str> = tokens.iter().map(PpToken::spelling).collect();
+        let pragma = match spellings[..] {
+            ["once"] => Pragma::Once,
+            ["once", ..] => return None,
+            ["pack", ..] => Pragma::Pack(PackPragma::parse(&tokens[1..])?),
+            ["GCC" | "clang", "diagnostic", ..] => {
+                Pragma::Diagnostic(DiagnosticPragma::parse(&tokens[2..])?)
+            }
+            ["STDC", "FP_CONTRACT", switch] => Pragma::FpContract(match switch {
+                "ON" => PragmaSwitch::On,
+                "OFF" => PragmaSwitch::Off,
+                "DEFAULT" => PragmaSwitch::Default,
+                _ => return None,
+            }),
+            ["STDC", "FP_CONTRACT", ..] => return None,
+            _ => Pragma::Unknown(text),
+        };
+        Some(pragma)
+    }
+}
+
+impl<'src> PackPragma<'src> {
+    fn parse(tokens: &[PpToken<'src>]) -> Option<Self> {
+        let [open, args @ .., close] = tokens else {
+            return None;
+        };
+        if open.token() != &Token::OpenParen || close.token() != &Token::CloseParen {
+            return None;
+        }
+        let pack = match args {
+            [] => PackPragma::Set(None),
+            [alignment] if alignment.spelling() == "show" => PackPragma::Show,
+            [alignment] => PackPragma::Set(Some(parse_alignment(alignment)?)),
+            [action, rest @ ..] => {
+                let (name, alignment) = parse_pack_args(rest)?;
+                match action.spelling() {
+                    "push" => PackPragma::Push { name, alignment },
+                    "pop" if alignment.is_none() => PackPragma::Pop { name },
+                    _ => return None,
+                }
+            }
+        };
+        Some(pack)
+    }
+}
+
+/// Parses the `, name, n` following the `push` or `pop` of `#pragma pack`,
+/// both of which are optional.
+fn parse_pack_args<'src>(tokens: &[PpToken<'src>]) -> Option<(Option<&'src str>, Option<u32>)> {
+    let mut name = None;
+    let mut alignment = None;
+    for pair in tokens.chunks(2) {
+        let [comma, arg] = pair else {
+            return None;
+        };
+        if comma.token() != &Token::Comma || alignment.is_some() {
+            return None;
+        }
+        match arg.token() {
+            Token::Id(id) if name.is_none() => name = Some(*id),
+            _ => alignment = Some(parse_alignment(arg)?),
+        }
+    }
+    Some((name, alignment))
+}
+
+/// Parses the alignment of `#pragma pack`, a small power of two.
+fn parse_alignment(token: &PpToken<'_>) -> Option<u32> {
+    let Token::PpNumber(_) = token.token() else {
+        return None;
+    };
+    let alignment: u32 = token.spelling().parse().ok()?;
+    (alignment.is_power_of_two() && alignment <= 16).then_some(alignment)
+}
+
+impl<'src> DiagnosticPragma<'src> {
+    fn parse(tokens: &[PpToken<'src>]) -> Option<Self> {
+        let (action, option) = match tokens {
+            [action] => (action.spelling(), None),
+            [action, option] => match option.token() {
+                Token::String(literal) if literal.encoding() == Encoding::Plain => {
+                    let raw = literal.raw();
+                    (action.spelling(), Some(&raw[1..raw.len() - 1]))
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let diagnostic = match (action, option) {
+            ("push", None) => DiagnosticPragma::Push,
+            ("pop", None) => DiagnosticPragma::Pop,
+            ("ignored", Some(option)) => DiagnosticPragma::Ignored(option),
+            ("warning", Some(option)) => DiagnosticPragma::Warning(option),
+            ("error", Some(option)) => DiagnosticPragma::Error(option),
+            _ => return None,
+        };
+        Some(diagnostic)
+    }
+}
+
+impl<'src> Preprocessor<'src> {
+    /// Runs a `#pragma` directive, leaving its `Token::Pragma` to be read
+    /// next.
+    pub(super) fn pragma(
+        &mut self,
+        hash: &PpToken<'src>,
+        directive: &PpToken<'src>,
+        rest: &[PpToken<'src>],
+    ) {
+        let start = hash.span();
+        let end = rest.last().unwrap_or(directive).span().end();
+        let span = Span::new(start.start(), end, start.line(), start.column());
+        if let Some(pragma) = self.pragma_token(hash, rest, span) {
+            self.pending.push_front(pragma);
+        }
+    }
+
+    /// Runs the `_Pragma("...")` operator, whose string is read as the rest
+    /// of a `#pragma` line.
+    pub(super) fn pragma_operator(&mut self, operator: PpToken<'src>) -> Option<PpToken<'src>> {
+        let mut operand = Vec::new();
+        if self.skip_open_paren() {
+            while let Some(token) = self.next_raw() {
+                match token.token() {
+                    Token::Newline => continue,
+                    Token::Eof => {
+                        self.pending.push_front(token);
+                        break;
+                    }
+                    Token::CloseParen => {
+                        operand.push(token);
+                        break;
+                    }
+                    _ => operand.push(token),
+                }
+            }
+        }
+        let start = operator.span();
+        let end = operand.last().unwrap_or(&operator).span().end();
+        let span = Span::new(start.start(), end, start.line(), start.column());
+
+        let [string, close] = &operand[..] else {
+            self.errors.push(PreprocessError::InvalidPragma { span });
+            return None;
+        };
+        let literal = match string.token() {
+            Token::String(literal)
+                if close.token() == &Token::CloseParen
+                    && matches!(literal.encoding(), Encoding::Plain | Encoding::Wide) =>
+            {
+                literal
+            }
+            _ => {
+                self.errors.push(PreprocessError::InvalidPragma { span });
+                return None;
+            }
+        };
+
+        // Destringizing only undoes the escapes `#` would add.
+        let raw = literal.raw().trim_start_matches('L');
+        let text = raw[1..raw.len() - 1]
+            .replace("\\\\", "\\")
+            .replace("\\\"", "\"");
+        let text = self.arena.alloc(text);
+        let mut previous_end = None;
+        let tokens: Vec<PpToken<'src>> = Scanner::new(text)
+            .with_standard(self.standard)
+            .with_preprocessing(true)
+            .take_while(|token| token.token() != &Token::Eof)
+            .map(|token| {
+                let leading_space = previous_end.is_some_and(|end| end < token.span().start());
+                previous_end = Some(token.span().end());
+                let spelling = token.spelling();
+                let token = SpannedToken::new(token.into_token(), span, spelling);
+                PpToken::new(token, leading_space, false)
+            })
+            .collect();
+        self.pragma_token(&operator, &tokens, span)
+    }
+
+    /// Makes the `Token::Pragma` of the tokens following `#pragma`, running
+    /// it if the preprocessor handles it itself.
+    fn pragma_token(
+        &mut self,
+        template: &PpToken<'src>,
+        tokens: &[PpToken<'src>],
+        span: Span,
+    ) -> Option<PpToken<'src>> {
+        let mut spelling = String::from("#pragma");
+        for (index, token) in tokens.iter().enumerate() {
+            if index == 0 || token.leading_space {
+                spelling.push(' ');
+            }
+            spelling.push_str(token.spelling());
+        }
+        let spelling = self.arena.alloc(spelling);
+        let text = spelling["#pragma".len()..].trim_start();
+
+        let Some(pragma) = Pragma::parse(tokens, text) else {
+            self.errors.push(PreprocessError::InvalidPragma { span });
+            return None;
+        };
+        if pragma == Pragma::Once {
+            let path = self.current_file().path.clone();
+            self.once.insert(path);
+        }
+        Some(PpToken {
+            token: SpannedToken::new(Token::Pragma(pragma), span, spelling),
+            ..template.clone()
+        })
+    }
+}
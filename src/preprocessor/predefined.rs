@@ -0,0 +1,199 @@
+use std::fmt::Write;
+
+use crate::Standard;
+
+use super::{PpToken, Preprocessor};
+
+/// The machine the source is preprocessed for, which decides the target
+/// macros, like `__x86_64__` and `__SIZEOF_LONG__`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Target {
+    #[default]
+    X86_64Linux,
+    I686Linux,
+    Aarch64Linux,
+}
+
+impl Target {
+    fn macros(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Target::X86_64Linux => &[
+                ("__x86_64__", "1"),
+                ("__x86_64", "1"),
+                ("__amd64__", "1"),
+                ("__amd64", "1"),
+                ("__LP64__", "1"),
+                ("_LP64", "1"),
+                ("__SIZEOF_LONG__", "8"),
+                ("__SIZEOF_POINTER__", "8"),
+                ("__SIZEOF_SIZE_T__", "8"),
+                ("__SIZEOF_LONG_DOUBLE__", "16"),
+                ("__LONG_MAX__", "0x7fffffffffffffffL"),
+            ],
+            Target::I686Linux => &[
+                ("__i386__", "1"),
+                ("__i386", "1"),
+                ("__i686__", "1"),
+                ("__ILP32__", "1"),
+                ("__SIZEOF_LONG__", "4"),
+                ("__SIZEOF_POINTER__", "4"),
+                ("__SIZEOF_SIZE_T__", "4"),
+                ("__SIZEOF_LONG_DOUBLE__", "12"),
+                ("__LONG_MAX__", "0x7fffffffL"),
+            ],
+            Target::Aarch64Linux => &[
+                ("__aarch64__", "1"),
+                ("__LP64__", "1"),
+                ("_LP64", "1"),
+                ("__CHAR_UNSIGNED__", "1"),
+                ("__SIZEOF_LONG__", "8"),
+                ("__SIZEOF_POINTER__", "8"),
+                ("__SIZEOF_SIZE_T__", "8"),
+                ("__SIZEOF_LONG_DOUBLE__", "16"),
+                ("__LONG_MAX__", "0x7fffffffffffffffL"),
+            ],
+        }
+    }
+}
+
+/// The target macros every supported target shares: Linux on a little
+/// endian machine.
+const COMMON_TARGET_MACROS: &[(&str, &str)] = &[
+    ("__linux__", "1"),
+    ("__linux", "1"),
+    ("__gnu_linux__", "1"),
+    ("__unix__", "1"),
+    ("__unix", "1"),
+    ("__ELF__", "1"),
+    ("__CHAR_BIT__", "8"),
+    ("__SIZEOF_SHORT__", "2"),
+    ("__SIZEOF_INT__", "4"),
+    ("__SIZEOF_LONG_LONG__", "8"),
+    ("__SIZEOF_FLOAT__", "4"),
+    ("__SIZEOF_DOUBLE__", "8"),
+    ("__SIZEOF_WCHAR_T__", "4"),
+    ("__SCHAR_MAX__", "0x7f"),
+    ("__SHRT_MAX__", "0x7fff"),
+    ("__INT_MAX__", "0x7fffffff"),
+    ("__LONG_LONG_MAX__", "0x7fffffffffffffffLL"),
+    ("__ORDER_LITTLE_ENDIAN__", "1234"),
+    ("__ORDER_BIG_ENDIAN__", "4321"),
+    ("__BYTE_ORDER__", "__ORDER_LITTLE_ENDIAN__"),
+];
+
+/// The predefined macros whose value depends on where they are used.
+const DYNAMIC_MACROS: &[&str] = &[
+    "__FILE__",
+    "__LINE__",
+    "__COUNTER__",
+    "__DATE__",
+    "__TIME__",
+];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+impl<'src> Preprocessor<'src> {
+    /// Defines the predefined macros, then runs the definitions of the
+    /// command line, before the source is read.
+    pub(super) fn predefine(&mut self) {
+        self.predefined = true;
+
+        let mut text = String::new();
+        let version = match self.standard {
+            Standard::C89 => None,
+            Standard::C99 => Some("199901L"),
+            Standard::C11 => Some("201112L"),
+            Standard::C17 => Some("201710L"),
+            Standard::C23 => Some("202311L"),
+        };
+        let standard = [("__STDC__", "1"), ("__STDC_HOSTED__", "1")]
+            .into_iter()
+            .chain(version.map(|version| ("__STDC_VERSION__", version)));
+        let target = COMMON_TARGET_MACROS.iter().chain(self.target.macros());
+        for (name, value) in standard.chain(target.copied()) {
+            let _ = writeln!(text, "#define {} {}", name, value);
+        }
+
+        // The last buffer pushed is read first.
+        let command_line = self.arena.alloc(std::mem::take(&mut self.command_line));
+        self.push_buffer("<command-line>", command_line);
+        let built_in = self.arena.alloc(text);
+        self.push_buffer("<built-in>", built_in);
+    }
+
+    /// Whether `name` is a macro, predefined ones included.
+    pub(super) fn is_macro(&self, name: &str) -> bool {
+        self.macros.contains_key(name) || DYNAMIC_MACROS.contains(&name)
+    }
+
+    /// Expands `token` if it is a predefined macro like `__LINE__`, which
+    /// only gets its value where it is used. Returns the token back when it
+    /// isn't one.
+    pub(super) fn expand_dynamic(&mut self, token: PpToken<'src>) -> PpToken<'src> {
+        // Inside a macro, the file and line are the ones of the outermost
+        // invocation.
+        let mut invocation = token.span();
+        let mut expansion = token.expansion();
+        while let Some(outer) = expansion {
+            invocation = outer.invocation();
+            expansion = outer.parent();
+        }
+        let text = match token.spelling() {
+            "__FILE__" => {
+                let file = self.current_file();
+                let path = file.presumed_path(invocation.start()).display().to_string();
+                format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            "__LINE__" => invocation.line().to_string(),
+            "__COUNTER__" => {
+                self.counter += 1;
+                (self.counter - 1).to_string()
+            }
+            "__DATE__" => {
+                let (year, month, day) = civil_date(self.timestamp / 86_400);
+                format!("\"{} {:2} {}\"", MONTHS[month - 1], day, year)
+            }
+            "__TIME__" => {
+                let seconds = self.timestamp % 86_400;
+                format!(
+                    "\"{:02}:{:02}:{:02}\"",
+                    seconds / 3600,
+                    seconds / 60 % 60,
+                    seconds % 60
+                )
+            }
+            _ => return token,
+        };
+        let text = self.arena.alloc(text);
+        match self.relex(text, token.span()) {
+            Some(expanded) => PpToken {
+                token: expanded,
+                ..token
+            },
+            None => token,
+        }
+    }
+}
+
+/// The year, month and day of the date `days` after 1970-01-01.
+fn civil_date(days: u64) -> (u64, usize, u64) {
+    // From Howard Hinnant's `civil_from_days`, with eras of 400 years
+    // starting on March 1st.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (year, month as usize, day)
+}
@@ -0,0 +1,39 @@
+use crate::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TriviaKind {
+    /// A run of spaces, tabs and other blanks, newlines excluded.
+    Whitespace,
+    Newline,
+    /// A backslash followed by a newline, which joins two physical lines.
+    LineContinuation,
+    LineComment,
+    BlockComment,
+}
+
+/// Source text between tokens that doesn't affect the program, kept by the
+/// scanner's lossless mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia<'src> {
+    kind: TriviaKind,
+    span: Span,
+    text: &'src str,
+}
+
+impl<'src> Trivia<'src> {
+    pub fn new(kind: TriviaKind, span: Span, text: &'src str) -> Self {
+        Self { kind, span, text }
+    }
+
+    pub fn kind(&self) -> TriviaKind {
+        self.kind
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn text(&self) -> &'src str {
+        self.text
+    }
+}
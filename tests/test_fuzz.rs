@@ -0,0 +1,172 @@
+use scanner::{Arena, Parser, Preprocessor, Scanner, SourceText, SpannedToken, Token};
+
+/// A small xorshift generator so the inputs are reproducible without any
+/// extra dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn assert_scans(src: &str) {
+    let tokens: Vec<SpannedToken> = Scanner::new(src).collect();
+    assert_eq!(
+        tokens.last().map(|t| t.token()),
+        Some(&Token::Eof),
+        "{:?}",
+        src
+    );
+    for token in &tokens {
+        let span = token.span();
+        assert!(
+            span.start() <= span.end() && span.end() <= src.len(),
+            "{:?}",
+            src
+        );
+        assert!(src.is_char_boundary(span.start()) && src.is_char_boundary(span.end()));
+    }
+
+    for preprocessing in [false, true] {
+        let rebuilt: String = Scanner::new(src)
+            .with_trivia(true)
+            .with_preprocessing(preprocessing)
+            .map(|token| token.to_string())
+            .collect();
+        assert_eq!(rebuilt, src);
+    }
+
+    let source = SourceText::new(src).with_trigraphs(true);
+    let mut rebuilt = String::new();
+    for token in Scanner::from_source(&source).with_trivia(true) {
+        let span = token.span();
+        assert!(
+            span.start() <= span.end() && span.end() <= src.len(),
+            "{:?}",
+            src
+        );
+        assert!(
+            src.is_char_boundary(span.start()) && src.is_char_boundary(span.end()),
+            "{:?} {:?}",
+            src,
+            token
+        );
+        rebuilt += &token.to_string();
+    }
+    assert_eq!(rebuilt, source.text());
+    for token in Scanner::from_source(&source).concat_strings() {
+        let span = token.span();
+        assert!(
+            span.start() <= span.end() && span.end() <= src.len(),
+            "{:?}",
+            src
+        );
+    }
+
+    if let Err(error) = Parser::new(src).parse() {
+        let span = error.span();
+        assert!(span.start() <= span.end() && span.end() <= src.len());
+    }
+}
+
+#[test]
+fn fuzz_random_bytes() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for _ in 0..5_000 {
+        let len = rng.below(64);
+        let bytes: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+        assert_scans(&String::from_utf8_lossy(&bytes));
+    }
+}
+
+#[test]
+fn fuzz_c_like_fragments() {
+    const FRAGMENTS: &[&str] = &[
+        "int", "x", "_y1", "0", "42", "1.5", ".", "f", "\"", "'", "/", "*", "//", "/*", "*/", "\\",
+        "\n", " ", "\t", "+", "-", "=", "<", ">", "!", "&", "|", "(", ")", "{", "}", "[", "]", ";",
+        "#", "?", "é", "\0", "include", "<", "\\\n", "\"s\"",
+    ];
+
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..5_000 {
+        let len = rng.below(24);
+        let src: String = (0..len)
+            .map(|_| FRAGMENTS[rng.below(FRAGMENTS.len())])
+            .collect();
+        assert_scans(&src);
+    }
+}
+
+#[test]
+fn fuzz_preprocessor() {
+    const FRAGMENTS: &[&str] = &[
+        "#define ",
+        "#undef ",
+        "#if ",
+        "#elif ",
+        "#else",
+        "#endif",
+        "#ifdef ",
+        "#",
+        "##",
+        "defined",
+        "f",
+        "g",
+        "x",
+        "(",
+        ")",
+        ",",
+        "...",
+        "__VA_ARGS__",
+        "__VA_OPT__",
+        "1",
+        "0",
+        "+",
+        "/",
+        "?",
+        ":",
+        " ",
+        "\n",
+        "\"",
+        "'",
+        "\\\n",
+    ];
+
+    let mut rng = Rng(0xd1b5_4a32_d192_ed03);
+    for _ in 0..5_000 {
+        let len = rng.below(32);
+        let src: String = (0..len)
+            .map(|_| FRAGMENTS[rng.below(FRAGMENTS.len())])
+            .collect();
+        let arena = Arena::new();
+        let tokens: Vec<_> = Preprocessor::new(&src, &arena).collect();
+        assert_eq!(
+            tokens.last().map(|t| t.token()),
+            Some(&Token::Eof),
+            "{:?}",
+            src
+        );
+    }
+}
+
+#[test]
+fn truncated_prefixes_of_program() {
+    let src = "
+int main() {
+    char* s = \"a string\"; /* block */ char c = 'c';
+    // line comment
+    for (int i = 0; i <= 10.5f; i++) { s += 1; }
+    return 0;
+}";
+    for end in (0..=src.len()).filter(|&i| src.is_char_boundary(i)) {
+        assert_scans(&src[..end]);
+    }
+}
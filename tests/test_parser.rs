@@ -0,0 +1,1076 @@
+use scanner::{
+    AlignmentSpecifier, Arena, BinaryOp, BlockItem, DeclarationSpecifiers, Declarator,
+    DerivedDeclarator, Designator, Expr, ExprKind, ExternalDeclaration, ForInit, Initializer,
+    LexError, Member, MemberDeclaration, ParseError, Parser, Preprocessor, Scanner, Span,
+    SpannedToken, Standard, StaticAssert, Stmt, StmtKind, StorageClass, StructKind, StructType,
+    TranslationUnit, TypeName, TypeQualifier, TypeSpecifier,
+};
+
+/// Spells `expr` as an S-expression, like `(Add a (Multiply b c))`.
+fn sexp(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::Id(name) => name.to_string(),
+        ExprKind::Number(number) => number.raw().to_string(),
+        ExprKind::Char(char) => char.raw().to_string(),
+        ExprKind::String(string) => string.raw().to_string(),
+        ExprKind::Bool(value) => value.to_string(),
+        ExprKind::Nullptr => "nullptr".to_string(),
+        ExprKind::Unary { op, operand } => format!("({:?} {})", op, sexp(operand)),
+        ExprKind::Binary { op, left, right } => {
+            format!("({:?} {} {})", op, sexp(left), sexp(right))
+        }
+        ExprKind::Assign { op, target, value } => {
+            let op = op.map_or(String::new(), |op| format!("{:?}", op));
+            format!("({}= {} {})", op, sexp(target), sexp(value))
+        }
+        ExprKind::Call { callee, arguments } => {
+            let mut text = format!("(call {}", sexp(callee));
+            for argument in arguments {
+                text += " ";
+                text += &sexp(argument);
+            }
+            text + ")"
+        }
+        ExprKind::Conditional {
+            condition,
+            then,
+            otherwise,
+        } => format!("(? {} {} {})", sexp(condition), sexp(then), sexp(otherwise)),
+        ExprKind::Comma(exprs) => {
+            let exprs: Vec<String> = exprs.iter().map(sexp).collect();
+            format!("(, {})", exprs.join(" "))
+        }
+        ExprKind::Cast { type_name, operand } => {
+            format!("(cast {} {})", spell_type(type_name), sexp(operand))
+        }
+        ExprKind::CompoundLiteral {
+            type_name,
+            initializers,
+        } => format!("({}){{{}}}", spell_type(type_name), initializers.len()),
+        ExprKind::SizeofExpr(operand) => format!("(sizeof {})", sexp(operand)),
+        ExprKind::SizeofType(type_name) => format!("(sizeof {})", spell_type(type_name)),
+        ExprKind::Alignof(type_name) => format!("(alignof {})", spell_type(type_name)),
+        ExprKind::Index { array, index } => format!("([] {} {})", sexp(array), sexp(index)),
+        ExprKind::Member {
+            base,
+            member,
+            is_arrow,
+        } => {
+            let operator = if *is_arrow { "->" } else { "." };
+            format!("({} {} {})", operator, sexp(base), member)
+        }
+    }
+}
+
+/// Spells `type_name` like `Unsigned Int*`.
+fn spell_type(type_name: &TypeName) -> String {
+    let specifiers: Vec<String> = type_name
+        .specifiers
+        .type_specifiers
+        .iter()
+        .map(|specifier| format!("{:?}", specifier))
+        .collect();
+    let mut text = specifiers.join(" ");
+    for derived in &type_name.declarator.derived {
+        text += match derived {
+            DerivedDeclarator::Pointer(_) => "*",
+            DerivedDeclarator::Array { .. } => "[]",
+            DerivedDeclarator::Function { .. } => "()",
+        };
+    }
+    text
+}
+
+fn expr(src: &str) -> String {
+    sexp(&Parser::new(src).parse_expression().unwrap())
+}
+
+fn parse(src: &str) -> TranslationUnit<'_> {
+    Parser::new(src).parse().unwrap()
+}
+
+fn specifiers<'a>(type_specifiers: &[TypeSpecifier<'a>]) -> DeclarationSpecifiers<'a> {
+    DeclarationSpecifiers {
+        type_specifiers: type_specifiers.to_vec(),
+        ..DeclarationSpecifiers::default()
+    }
+}
+
+#[test]
+fn test_parse_main_program() {
+    let src = "
+int main() {
+    int a = 10;
+    int c;
+    for (int i = 0; i <= 10; i++) {
+        if (i + a <= 15) {
+            c = i;
+        }
+    }
+    return 0;
+}
+";
+    let unit = parse(src);
+    let [ExternalDeclaration::Function(main)] = &unit.items[..] else {
+        panic!("{:?}", unit);
+    };
+    assert_eq!(main.specifiers, specifiers(&[TypeSpecifier::Int]));
+    assert_eq!(main.declarator.name, Some("main"));
+    assert_eq!(
+        main.declarator.derived,
+        [DerivedDeclarator::Function {
+            parameters: Vec::new(),
+            is_variadic: false
+        }]
+    );
+    assert_eq!(main.span, Span::new(1, src.len() - 1, 2, 1));
+
+    let [BlockItem::Declaration(a), BlockItem::Declaration(c), BlockItem::Stmt(for_loop), BlockItem::Stmt(ret)] =
+        &main.body.items[..]
+    else {
+        panic!("{:?}", main.body);
+    };
+    assert_eq!(a.declarators[0].declarator.name, Some("a"));
+    let Some(Initializer::Expr(ten)) = &a.declarators[0].initializer else {
+        panic!("{:?}", a);
+    };
+    assert_eq!(sexp(ten), "10");
+    assert_eq!(c.declarators[0].initializer, None);
+
+    let StmtKind::For {
+        init,
+        condition,
+        step,
+        body,
+    } = &for_loop.kind
+    else {
+        panic!("{:?}", for_loop);
+    };
+    let ForInit::Declaration(i) = &**init else {
+        panic!("{:?}", init);
+    };
+    assert_eq!(i.declarators[0].declarator.name, Some("i"));
+    assert_eq!(sexp(condition.as_ref().unwrap()), "(LessThanOrEqual i 10)");
+    assert_eq!(sexp(step.as_ref().unwrap()), "(PostIncrement i)");
+
+    let StmtKind::Compound(block) = &body.kind else {
+        panic!("{:?}", body);
+    };
+    let [BlockItem::Stmt(Stmt {
+        kind:
+            StmtKind::If {
+                condition,
+                then,
+                otherwise: None,
+            },
+        span,
+    })] = &block.items[..]
+    else {
+        panic!("{:?}", block);
+    };
+    assert_eq!(sexp(condition), "(LessThanOrEqual (Add i a) 15)");
+    assert_eq!(span.line(), 6);
+    let StmtKind::Compound(then) = &then.kind else {
+        panic!("{:?}", then);
+    };
+    let [BlockItem::Stmt(Stmt {
+        kind: StmtKind::Expr(Some(assignment)),
+        ..
+    })] = &then.items[..]
+    else {
+        panic!("{:?}", then);
+    };
+    assert_eq!(sexp(assignment), "(= c i)");
+
+    let StmtKind::Return(Some(zero)) = &ret.kind else {
+        panic!("{:?}", ret);
+    };
+    assert_eq!(sexp(zero), "0");
+}
+
+#[test]
+fn test_parse_expressions() {
+    assert_eq!(expr("a + b * c - d"), "(Subtract (Add a (Multiply b c)) d)");
+    assert_eq!(expr("(a + b) * c"), "(Multiply (Add a b) c)");
+    assert_eq!(
+        expr("a < b == c >= d"),
+        "(Equals (LessThan a b) (GreaterThanOrEqual c d))"
+    );
+    assert_eq!(expr("a = b += c % 2"), "(= a (Add= b (Modulo c 2)))");
+    assert_eq!(expr("-!x++"), "(Minus (Not (PostIncrement x)))");
+    assert_eq!(expr("--x / +y"), "(Divide (PreDecrement x) (Plus y))");
+    assert_eq!(
+        expr("f()(1, g(x), 'c')"),
+        "(call (call f) 1 (call g x) 'c')"
+    );
+    assert_eq!(expr(r#"puts("a" "b")"#), r#"(call puts "a" "b")"#);
+
+    let sum = Parser::new("  a + b").parse_expression().unwrap();
+    assert_eq!(sum.span, Span::new(2, 7, 1, 3));
+    let ExprKind::Binary {
+        op: BinaryOp::Add,
+        left,
+        ..
+    } = &sum.kind
+    else {
+        panic!("{:?}", sum);
+    };
+    assert_eq!(left.span, Span::new(2, 3, 1, 3));
+}
+
+#[test]
+fn test_operator_precedence() {
+    let cases = [
+        ("a || b && c | d ^ e & f", "(Or a (And b (BitOr c (BitXor d (BitAnd e f)))))"),
+        ("a == b < c << d + e * f", "(Equals a (LessThan b (ShiftLeft c (Add d (Multiply e f)))))"),
+        ("a * b + c << d > e != f & g ^ h | i && j || k",
+         "(Or (And (BitOr (BitXor (BitAnd (NotEqual (GreaterThan (ShiftLeft (Add (Multiply a b) c) d) e) f) g) h) i) j) k)"),
+        ("a - b - c", "(Subtract (Subtract a b) c)"),
+        ("a / b % c * d", "(Multiply (Modulo (Divide a b) c) d)"),
+        ("a >> b >> c", "(ShiftRight (ShiftRight a b) c)"),
+        ("a = b = c", "(= a (= b c))"),
+        ("a <<= b |= c ^= d &= e >>= f", "(ShiftLeft= a (BitOr= b (BitXor= c (BitAnd= d (ShiftRight= e f)))))"),
+        ("a ? b : c ? d : e", "(? a b (? c d e))"),
+        ("a ? b, c : d", "(? a (, b c) d)"),
+        ("x = a || b ? c : d", "(= x (? (Or a b) c d))"),
+        ("a, b = c, d", "(, a (= b c) d)"),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(expr(src), expected, "{}", src);
+    }
+}
+
+#[test]
+fn test_unary_and_postfix_operators() {
+    let cases = [
+        // `*` and `&` in front of an operand are dereference and address-of.
+        ("a * *b", "(Multiply a (Deref b))"),
+        ("a & &b", "(BitAnd a (AddressOf b))"),
+        ("*p * q", "(Multiply (Deref p) q)"),
+        ("a - -b", "(Subtract a (Minus b))"),
+        ("*p++", "(Deref (PostIncrement p))"),
+        ("&a[1]", "(AddressOf ([] a 1))"),
+        ("~!x", "(BitNot (Not x))"),
+        ("++*p", "(PreIncrement (Deref p))"),
+        ("p->next->value.x", "(. (-> (-> p next) value) x)"),
+        ("f(a)[i]->g(b, c)", "(call (-> ([] (call f a) i) g) b c)"),
+        ("a[b, c]", "([] a (, b c))"),
+        ("f((a, b), c)", "(call f (, a b) c)"),
+        ("sizeof x + 1", "(Add (sizeof x) 1)"),
+        ("sizeof *p", "(sizeof (Deref p))"),
+        ("sizeof(int) * 2", "(Multiply (sizeof Int) 2)"),
+        ("sizeof(x) * 2", "(Multiply (sizeof x) 2)"),
+        (
+            "sizeof (unsigned char *[]) - 1",
+            "(Subtract (sizeof Unsigned Char[]*) 1)",
+        ),
+        ("_Alignof(long double)", "(alignof Long Double)"),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(expr(src), expected, "{}", src);
+    }
+}
+
+#[test]
+fn test_casts() {
+    let cases = [
+        ("(int)x + 1", "(Add (cast Int x) 1)"),
+        ("(a) + 1", "(Add a 1)"),
+        ("(char *)p++", "(cast Char* (PostIncrement p))"),
+        ("(float)(int)x", "(cast Float (cast Int x))"),
+        ("-(long)x", "(Minus (cast Long x))"),
+        ("(const void *)&x", "(cast Void* (AddressOf x))"),
+        ("(unsigned long)x", "(cast Unsigned Long x)"),
+        ("(int (*)(void))f", "(cast Int*() f)"),
+        ("(char (*)[4])p", "(cast Char*[] p)"),
+        ("sizeof (int){ 1 }", "(sizeof (Int){1})"),
+        ("(int[]){ 1, 2 }[1]", "([] (Int[]){2} 1)"),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(expr(src), expected, "{}", src);
+    }
+
+    let error = Parser::new("(int x)y").parse_expression().unwrap_err();
+    assert_eq!(error.to_string(), "expected `)`, found \"x\"");
+    let error = Parser::new("(static int)y").parse_expression().unwrap_err();
+    assert_eq!(error.to_string(), "expected expression, found \"static\"");
+}
+
+#[test]
+fn test_c23_constants() {
+    let c23 = |src| {
+        let parser = Parser::from_scanner(Scanner::new(src).with_standard(Standard::C23));
+        sexp(&parser.parse_expression().unwrap())
+    };
+    assert_eq!(c23("true && !false"), "(And true (Not false))");
+    assert_eq!(c23("p == nullptr"), "(Equals p nullptr)");
+    // Before C23 they are ordinary identifiers.
+    assert_eq!(expr("p == nullptr"), "(Equals p nullptr)");
+}
+
+#[test]
+fn test_parse_statements() {
+    let stmt = |src| Parser::new(src).parse_statement().unwrap();
+
+    // The `else` belongs to the inner `if`.
+    let StmtKind::If {
+        then, otherwise, ..
+    } = stmt("if (a) if (b) x; else y;").kind
+    else {
+        panic!();
+    };
+    assert_eq!(otherwise, None);
+    assert!(matches!(
+        then.kind,
+        StmtKind::If {
+            otherwise: Some(_),
+            ..
+        }
+    ));
+
+    let StmtKind::DoWhile { body, condition } = stmt("do x--; while (x);").kind else {
+        panic!();
+    };
+    assert_eq!(sexp(&condition), "x");
+    assert!(matches!(body.kind, StmtKind::Expr(Some(_))));
+
+    let StmtKind::For {
+        init,
+        condition: None,
+        step: None,
+        ..
+    } = stmt("for (;;) ;").kind
+    else {
+        panic!();
+    };
+    assert_eq!(*init, ForInit::Expr(None));
+
+    let StmtKind::Switch { body, .. } = stmt("switch (c) { case 1: break; default: return; }").kind
+    else {
+        panic!();
+    };
+    let StmtKind::Compound(block) = body.kind else {
+        panic!();
+    };
+    let kinds: Vec<_> = block
+        .items
+        .iter()
+        .map(|item| match item {
+            BlockItem::Stmt(stmt) => &stmt.kind,
+            item => panic!("{:?}", item),
+        })
+        .collect();
+    assert!(matches!(
+        kinds[..],
+        [StmtKind::Case { .. }, StmtKind::Default(_)]
+    ));
+
+    let StmtKind::Labeled { label, body } = stmt("again: goto again;").kind else {
+        panic!();
+    };
+    assert_eq!(label, "again");
+    assert_eq!(body.kind, StmtKind::Goto("again"));
+    assert_eq!(stmt("while (1) continue;").span, Span::new(0, 19, 1, 1));
+}
+
+#[test]
+fn test_parse_declarations() {
+    let unit = parse(
+        "static const unsigned long x = 1, *const p, a[10];\n\
+         int printf(const char *format, ...);\n\
+         int main(int argc, char *argv[]);\n\
+         void f(void), g();\n\
+         int v[] = { 1, [2] = 3, .x = { 4 }, };",
+    );
+    let declarations: Vec<_> = unit
+        .items
+        .iter()
+        .map(|item| match item {
+            ExternalDeclaration::Declaration(declaration) => declaration,
+            item => panic!("{:?}", item),
+        })
+        .collect();
+
+    let statics = declarations[0];
+    assert_eq!(statics.specifiers.storage_classes, [StorageClass::Static]);
+    assert_eq!(statics.specifiers.qualifiers, [TypeQualifier::Const]);
+    assert_eq!(
+        statics.specifiers.type_specifiers,
+        [TypeSpecifier::Unsigned, TypeSpecifier::Long]
+    );
+    let names: Vec<_> = statics
+        .declarators
+        .iter()
+        .map(|d| d.declarator.name.unwrap())
+        .collect();
+    assert_eq!(names, ["x", "p", "a"]);
+    assert_eq!(
+        statics.declarators[1].declarator.derived,
+        [DerivedDeclarator::Pointer(vec![TypeQualifier::Const])]
+    );
+    assert!(matches!(
+        &statics.declarators[2].declarator.derived[..],
+        [DerivedDeclarator::Array { size: Some(size), is_static: false, .. }] if sexp(size) == "10"
+    ));
+
+    let DerivedDeclarator::Function {
+        parameters,
+        is_variadic: true,
+    } = &declarations[1].declarators[0].declarator.derived[0]
+    else {
+        panic!("{:?}", declarations[1]);
+    };
+    assert_eq!(parameters.len(), 1);
+    assert_eq!(parameters[0].specifiers.qualifiers, [TypeQualifier::Const]);
+    assert_eq!(parameters[0].declarator.name, Some("format"));
+
+    // `argv` is an array of pointers.
+    let DerivedDeclarator::Function { parameters, .. } =
+        &declarations[2].declarators[0].declarator.derived[0]
+    else {
+        panic!("{:?}", declarations[2]);
+    };
+    assert!(matches!(
+        parameters[1].declarator.derived[..],
+        [
+            DerivedDeclarator::Array { size: None, .. },
+            DerivedDeclarator::Pointer(_)
+        ]
+    ));
+
+    let void = &declarations[3].declarators;
+    let DerivedDeclarator::Function { parameters, .. } = &void[0].declarator.derived[0] else {
+        panic!("{:?}", void);
+    };
+    assert_eq!(parameters[0].specifiers, specifiers(&[TypeSpecifier::Void]));
+    assert_eq!(
+        parameters[0].declarator,
+        Declarator {
+            name: None,
+            derived: Vec::new(),
+            span: Span::new(133, 133, 4, 12),
+        }
+    );
+    assert_eq!(void[1].declarator.name, Some("g"));
+
+    let Some(Initializer::List(items)) = &declarations[4].declarators[0].initializer else {
+        panic!("{:?}", declarations[4]);
+    };
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0].designators, []);
+    assert!(matches!(&items[1].designators[..], [Designator::Index(index)] if sexp(index) == "2"));
+    assert_eq!(items[2].designators, [Designator::Member("x")]);
+    assert!(matches!(&items[2].initializer, Initializer::List(list) if list.len() == 1));
+}
+
+#[test]
+fn test_static_assert_and_alignas() {
+    let unit = parse(
+        "_Static_assert(sizeof(int) == 4, \"int\");\n\
+         _Alignas(16) char buffer[64];\n\
+         struct S { _Alignas(double) char c; _Static_assert(1, \"c\"); };\n\
+         void f(void) { _Static_assert(1, \"x\"); _Alignas(8) int x; }",
+    );
+    let ExternalDeclaration::StaticAssert(assertion) = &unit.items[0] else {
+        panic!("{:?}", unit.items[0]);
+    };
+    assert_eq!(sexp(&assertion.condition), "(Equals (sizeof Int) 4)");
+    assert_eq!(assertion.message.as_ref().map(|m| m.raw()), Some("\"int\""));
+    assert_eq!(assertion.span, Span::new(0, 40, 1, 1));
+
+    let ExternalDeclaration::Declaration(buffer) = &unit.items[1] else {
+        panic!("{:?}", unit.items[1]);
+    };
+    assert!(matches!(
+        &buffer.specifiers.alignment_specifiers[..],
+        [AlignmentSpecifier::Expr(size)] if sexp(size) == "16"
+    ));
+    assert_eq!(buffer.specifiers.type_specifiers, [TypeSpecifier::Char]);
+
+    let ExternalDeclaration::Declaration(s) = &unit.items[2] else {
+        panic!("{:?}", unit.items[2]);
+    };
+    let [TypeSpecifier::Struct(s)] = &s.specifiers.type_specifiers[..] else {
+        panic!("{:?}", s);
+    };
+    let Some([Member::Declaration(member), Member::StaticAssert(_)]) = s.members.as_deref() else {
+        panic!("{:?}", s);
+    };
+    assert!(matches!(
+        &member.specifiers.alignment_specifiers[..],
+        [AlignmentSpecifier::Type(type_name)] if spell_type(type_name) == "Double"
+    ));
+
+    let ExternalDeclaration::Function(f) = &unit.items[3] else {
+        panic!("{:?}", unit.items[3]);
+    };
+    assert!(matches!(
+        &f.body.items[..],
+        [BlockItem::StaticAssert(_), BlockItem::Declaration(x)]
+            if x.specifiers.alignment_specifiers.len() == 1
+    ));
+
+    // C23 spells them in lowercase and makes the message optional.
+    let src = "static_assert(true); alignas(4) int y;";
+    let unit = Parser::from_scanner(Scanner::new(src).with_standard(Standard::C23))
+        .parse()
+        .unwrap();
+    assert!(matches!(
+        &unit.items[..],
+        [
+            ExternalDeclaration::StaticAssert(StaticAssert { message: None, .. }),
+            ExternalDeclaration::Declaration(_)
+        ]
+    ));
+
+    let error = Parser::new("_Static_assert(1, 2);").parse().unwrap_err();
+    assert_eq!(error.to_string(), "expected string literal, found \"2\"");
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = Parser::new("int main() { int x = ; }").parse().unwrap_err();
+    assert_eq!(
+        error,
+        ParseError::UnexpectedToken {
+            expected: "expression".to_string(),
+            found: ";".to_string(),
+            span: Span::new(21, 22, 1, 22),
+        }
+    );
+    assert_eq!(error.to_string(), "expected expression, found \";\"");
+
+    let error = Parser::new("int main() { return 0;").parse().unwrap_err();
+    assert_eq!(error.to_string(), "expected `}` at end of input");
+    assert_eq!(error.span(), Span::new(22, 22, 1, 22));
+
+    let error = Parser::new("int x").parse().unwrap_err();
+    assert_eq!(error.to_string(), "expected `;` at end of input");
+    let error = Parser::new("x = 1;").parse().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "expected declaration specifiers, found \"x\""
+    );
+    let error = Parser::new("a + ").parse_expression().unwrap_err();
+    assert_eq!(error.to_string(), "expected expression at end of input");
+
+    let error = Parser::new("char *s = \"abc;").parse().unwrap_err();
+    assert!(matches!(error, ParseError::Lex(_)), "{:?}", error);
+}
+
+#[test]
+fn test_nesting_limit() {
+    let nested = |open: &str, inner: &str, close: &str, depth| {
+        format!("{}{}{}", open.repeat(depth), inner, close.repeat(depth))
+    };
+    assert_eq!(expr(&nested("(", "x", ")", 64)), "x");
+    let loops = nested("for (;;) ", ";", "", 64);
+    assert!(Parser::new(&loops).parse_statement().is_ok());
+    let blocks = nested("{", "", "}", 64);
+    assert!(Parser::new(&blocks).parse_statement().is_ok());
+
+    let parens = nested("(", "x", ")", 100_000);
+    let error = Parser::new(&parens).parse_expression().unwrap_err();
+    assert_eq!(
+        error,
+        ParseError::NestingTooDeep {
+            span: Span::new(65, 66, 1, 66)
+        }
+    );
+    assert_eq!(error.to_string(), "nesting too deep");
+
+    let errors = [
+        Parser::new(&nested("-", "x", "", 100_000)).parse_expression(),
+        Parser::new(&nested("", "x", " = x", 100_000)).parse_expression(),
+        Parser::new(&nested("f(", "x", ")", 100_000)).parse_expression(),
+        Parser::new(&nested("sizeof(int[", "1", "])", 100_000)).parse_expression(),
+    ]
+    .map(Result::unwrap_err);
+    let loops = nested("for (;;) ", ";", "", 100_000);
+    let blocks = nested("{", "", "}", 100_000);
+    let declarator = format!("int {};", nested("(", "x", ")", 100_000));
+    let errors = errors.into_iter().chain([
+        Parser::new(&loops).parse_statement().unwrap_err(),
+        Parser::new(&blocks).parse_statement().unwrap_err(),
+        Parser::new(&declarator).parse().unwrap_err(),
+    ]);
+    for error in errors {
+        assert!(
+            matches!(error, ParseError::NestingTooDeep { .. }),
+            "{:?}",
+            error
+        );
+    }
+}
+
+#[test]
+fn test_parse_preprocessed_tokens() {
+    let src = "#define N 10\n#define MAX(a, b) ((a) > (b) ? (a) : (b))\nint a[N];\n";
+    let arena = Arena::new();
+    let tokens = Preprocessor::new(src, &arena)
+        .map(|token| SpannedToken::new(token.token().clone(), token.span(), token.spelling()));
+    let unit = Parser::from_tokens(tokens, &arena).parse().unwrap();
+    let [ExternalDeclaration::Declaration(declaration)] = &unit.items[..] else {
+        panic!("{:?}", unit);
+    };
+    assert!(matches!(
+        &declaration.declarators[0].declarator.derived[..],
+        [DerivedDeclarator::Array { size: Some(size), .. }] if sexp(size) == "10"
+    ));
+    assert_eq!(declaration.span, Span::new(55, 64, 3, 1));
+
+    // Adjacent strings are merged after expansion.
+    let src = "#define FMT \"%d\"\nconst char *s = \"x=\" FMT \"\\n\";";
+    let tokens = Preprocessor::new(src, &arena)
+        .map(|token| SpannedToken::new(token.token().clone(), token.span(), token.spelling()));
+    let unit = Parser::from_tokens(tokens, &arena).parse().unwrap();
+    let [ExternalDeclaration::Declaration(declaration)] = &unit.items[..] else {
+        panic!("{:?}", unit);
+    };
+    let Some(Initializer::Expr(string)) = &declaration.declarators[0].initializer else {
+        panic!("{:?}", declaration);
+    };
+    assert_eq!(sexp(string), "\"x=\" \"%d\" \"\\n\"");
+
+    let tokens = Preprocessor::new("char *s = u\"a\" U\"b\";", &arena)
+        .map(|token| SpannedToken::new(token.token().clone(), token.span(), token.spelling()));
+    let error = Parser::from_tokens(tokens, &arena).parse().unwrap_err();
+    assert!(matches!(
+        error,
+        ParseError::Lex(LexError::IncompatibleStringEncodings { .. })
+    ));
+}
+
+/// The derived declarators of the first declarator of each declaration in
+/// `src`, spelled like `[] * ()`.
+fn derived(src: &str) -> Vec<(String, String)> {
+    let unit = parse(src);
+    let mut declarators = Vec::new();
+    for item in &unit.items {
+        let declarator = match item {
+            ExternalDeclaration::Declaration(declaration) => &declaration.declarators[0].declarator,
+            ExternalDeclaration::Function(function) => &function.declarator,
+            ExternalDeclaration::StaticAssert(_) => continue,
+        };
+        let spelling: Vec<&str> = declarator
+            .derived
+            .iter()
+            .map(|derived| match derived {
+                DerivedDeclarator::Pointer(_) => "*",
+                DerivedDeclarator::Array { .. } => "[]",
+                DerivedDeclarator::Function { .. } => "()",
+            })
+            .collect();
+        declarators.push((declarator.name.unwrap().to_string(), spelling.join(" ")));
+    }
+    declarators
+}
+
+/// The first block item of the body of the last function in `unit`.
+fn first_item<'a, 'src>(unit: &'a TranslationUnit<'src>) -> &'a BlockItem<'src> {
+    match unit.items.last() {
+        Some(ExternalDeclaration::Function(function)) => &function.body.items[0],
+        item => panic!("{:?}", item),
+    }
+}
+
+/// The member declarations of the definition `s`.
+fn member_declarations<'a, 'src>(s: &'a StructType<'src>) -> Vec<&'a MemberDeclaration<'src>> {
+    let members = s.members.iter().flatten();
+    members
+        .map(|member| match member {
+            Member::Declaration(declaration) => declaration,
+            member => panic!("{:?}", member),
+        })
+        .collect()
+}
+
+#[test]
+fn test_declarators() {
+    let cases = [
+        ("int (*fp)(int, char *);", "fp", "* ()"),
+        ("unsigned long const *volatile p[10];", "p", "[] *"),
+        ("char (*(*x[3])())[5];", "x", "[] * () * []"),
+        (
+            "void (*signal(int, void (*)(int)))(int);",
+            "signal",
+            "() * ()",
+        ),
+        ("int **const *ppp;", "ppp", "* * *"),
+        ("int (a)[2], ((b));", "a", "[]"),
+    ];
+    for (src, name, expected) in cases {
+        assert_eq!(
+            derived(src),
+            [(name.to_string(), expected.to_string())],
+            "{}",
+            src
+        );
+    }
+
+    let unit = parse("unsigned long const *volatile p[10];");
+    let ExternalDeclaration::Declaration(declaration) = &unit.items[0] else {
+        panic!("{:?}", unit);
+    };
+    assert_eq!(declaration.specifiers.qualifiers, [TypeQualifier::Const]);
+    assert_eq!(
+        declaration.declarators[0].declarator.derived[1],
+        DerivedDeclarator::Pointer(vec![TypeQualifier::Volatile])
+    );
+    assert_eq!(
+        declaration.declarators[0].declarator.span,
+        Span::new(20, 35, 1, 21)
+    );
+
+    // An unnamed parameter of pointer to function type.
+    let unit = parse("void qsort(void *, int (*)(const void *, const void *));");
+    let ExternalDeclaration::Declaration(declaration) = &unit.items[0] else {
+        panic!("{:?}", unit);
+    };
+    let DerivedDeclarator::Function { parameters, .. } =
+        &declaration.declarators[0].declarator.derived[0]
+    else {
+        panic!("{:?}", declaration);
+    };
+    assert_eq!(parameters[1].declarator.name, None);
+    assert!(matches!(
+        &parameters[1].declarator.derived[..],
+        [DerivedDeclarator::Pointer(_), DerivedDeclarator::Function { parameters, .. }]
+            if parameters.len() == 2
+    ));
+}
+
+#[test]
+fn test_typedef_names() {
+    // `T * x;` declares `x` when `T` is a typedef name.
+    let unit = parse("typedef int T;\nvoid f(void) { T * x; }");
+    let BlockItem::Declaration(declaration) = first_item(&unit) else {
+        panic!("{:?}", unit);
+    };
+    assert_eq!(
+        declaration.specifiers.type_specifiers,
+        [TypeSpecifier::TypedefName("T")]
+    );
+    assert_eq!(declaration.declarators[0].declarator.name, Some("x"));
+
+    // And multiplies otherwise.
+    let unit = parse("int T, x;\nvoid f(void) { T * x; }");
+    let BlockItem::Stmt(Stmt {
+        kind: StmtKind::Expr(Some(product)),
+        ..
+    }) = first_item(&unit)
+    else {
+        panic!("{:?}", unit);
+    };
+    assert_eq!(sexp(product), "(Multiply T x)");
+
+    let unit = parse("typedef int T, *PT;\nPT p;\nconst T t;\nvoid f(T, PT);");
+    let specifiers: Vec<_> = unit
+        .items
+        .iter()
+        .map(|item| match item {
+            ExternalDeclaration::Declaration(declaration) => {
+                declaration.specifiers.type_specifiers.clone()
+            }
+            item => panic!("{:?}", item),
+        })
+        .collect();
+    assert_eq!(specifiers[1], [TypeSpecifier::TypedefName("PT")]);
+    assert_eq!(specifiers[2], [TypeSpecifier::TypedefName("T")]);
+    let ExternalDeclaration::Declaration(f) = &unit.items[3] else {
+        panic!("{:?}", unit);
+    };
+    let DerivedDeclarator::Function { parameters, .. } = &f.declarators[0].declarator.derived[0]
+    else {
+        panic!("{:?}", f);
+    };
+    assert_eq!(
+        parameters[1].specifiers.type_specifiers,
+        [TypeSpecifier::TypedefName("PT")]
+    );
+    assert_eq!(parameters[1].declarator.name, None);
+
+    // After another type specifier, a typedef name is declared again.
+    let unit = parse("typedef int T;\nvoid f(void) { unsigned T; T * x; }");
+    let ExternalDeclaration::Function(f) = &unit.items[1] else {
+        panic!("{:?}", unit);
+    };
+    let BlockItem::Declaration(shadow) = &f.body.items[0] else {
+        panic!("{:?}", f);
+    };
+    assert_eq!(shadow.declarators[0].declarator.name, Some("T"));
+    assert!(matches!(
+        &f.body.items[1],
+        BlockItem::Stmt(Stmt {
+            kind: StmtKind::Expr(Some(_)),
+            ..
+        })
+    ));
+
+    let unit = Parser::new("size_t n;")
+        .with_typedef("size_t")
+        .parse()
+        .unwrap();
+    assert!(matches!(
+        &unit.items[0],
+        ExternalDeclaration::Declaration(declaration) if declaration.declarators.len() == 1
+    ));
+}
+
+#[test]
+fn test_typedef_scopes() {
+    let is_declaration = |src| matches!(first_item(&parse(src)), BlockItem::Declaration(_));
+
+    assert!(is_declaration("typedef int T;\nvoid f(void) { T * x; }"));
+    // A parameter or a local variable hides the typedef.
+    assert!(!is_declaration("typedef int T;\nvoid f(int T) { T * x; }"));
+    let unit = parse("typedef int T;\nvoid f(void) { int T = 2; { T * x; } }");
+    let ExternalDeclaration::Function(f) = &unit.items[1] else {
+        panic!("{:?}", unit);
+    };
+    let BlockItem::Stmt(Stmt {
+        kind: StmtKind::Compound(block),
+        ..
+    }) = &f.body.items[1]
+    else {
+        panic!("{:?}", f);
+    };
+    assert!(matches!(block.items[0], BlockItem::Stmt(_)));
+    // Typedefs declared in a block or a `for` end with it.
+    assert!(!is_declaration(
+        "void f(void) { typedef int U; }\nvoid g(void) { U * x; }"
+    ));
+    assert!(!is_declaration(
+        "void f(void) { for (typedef int U;;) ; }\nvoid g(void) { U * x; }"
+    ));
+    // A prototype's parameter names only hide typedefs within it.
+    assert!(is_declaration(
+        "typedef int T;\nvoid g(int T);\nvoid f(void) { T * x; }"
+    ));
+    // A typedef name followed by `:` is a label.
+    let unit = parse("typedef int T;\nvoid f(void) { T: return; }");
+    assert!(matches!(
+        first_item(&unit),
+        BlockItem::Stmt(Stmt {
+            kind: StmtKind::Labeled { label: "T", .. },
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_typedef_casts() {
+    let src = |expr: &str| format!("typedef long T;\nint x = {};", expr);
+    let initializer = |src: &str| {
+        let unit = Parser::new(src).parse().unwrap();
+        let ExternalDeclaration::Declaration(declaration) = &unit.items[1] else {
+            panic!("{:?}", unit);
+        };
+        let Some(Initializer::Expr(expr)) = &declaration.declarators[0].initializer else {
+            panic!("{:?}", declaration);
+        };
+        sexp(expr)
+    };
+
+    assert_eq!(
+        initializer(&src("(T) - 1")),
+        "(cast TypedefName(\"T\") (Minus 1))"
+    );
+    assert_eq!(initializer(&src("(x) - 1")), "(Subtract x 1)");
+    assert_eq!(
+        initializer(&src("sizeof(T)")),
+        "(sizeof TypedefName(\"T\"))"
+    );
+    assert_eq!(
+        initializer(&src("sizeof(T *) * 2")),
+        "(Multiply (sizeof TypedefName(\"T\")*) 2)"
+    );
+    assert_eq!(initializer(&src("(T *)0")), "(cast TypedefName(\"T\")* 0)");
+}
+
+/// The type specifiers of the file-scope declaration `items[index]`.
+fn declared_type<'a, 'src>(
+    unit: &'a TranslationUnit<'src>,
+    index: usize,
+) -> &'a TypeSpecifier<'src> {
+    match &unit.items[index] {
+        ExternalDeclaration::Declaration(declaration) => &declaration.specifiers.type_specifiers[0],
+        item => panic!("{:?}", item),
+    }
+}
+
+#[test]
+fn test_struct_definitions() {
+    let src = "
+struct node {
+    unsigned flags : 3, kind : 2;
+    int : 0;
+    union {
+        int i;
+        float f;
+    };
+    struct node *next;
+    int data[];
+};
+";
+    let unit = parse(src);
+    let TypeSpecifier::Struct(node) = declared_type(&unit, 0) else {
+        panic!("{:?}", unit);
+    };
+    assert_eq!(node.kind, StructKind::Struct);
+    assert_eq!(node.tag, Some("node"));
+    assert_eq!(node.span, Span::new(1, src.len() - 2, 2, 1));
+    let [flags, unnamed, anonymous, next, data] = &member_declarations(node)[..] else {
+        panic!("{:?}", node);
+    };
+
+    let widths: Vec<_> = flags
+        .declarators
+        .iter()
+        .map(|member| (member.declarator.name, member.bit_width.as_ref().map(sexp)))
+        .collect();
+    assert_eq!(
+        widths,
+        [
+            (Some("flags"), Some("3".to_string())),
+            (Some("kind"), Some("2".to_string()))
+        ]
+    );
+    assert_eq!(unnamed.declarators[0].declarator.name, None);
+    assert_eq!(
+        unnamed.declarators[0].bit_width.as_ref().map(sexp),
+        Some("0".to_string())
+    );
+
+    assert!(anonymous.declarators.is_empty());
+    let [TypeSpecifier::Struct(union)] = &anonymous.specifiers.type_specifiers[..] else {
+        panic!("{:?}", anonymous);
+    };
+    assert_eq!(union.kind, StructKind::Union);
+    assert_eq!(union.tag, None);
+    assert_eq!(union.members.as_ref().map(Vec::len), Some(2));
+
+    let [TypeSpecifier::Struct(self_type)] = &next.specifiers.type_specifiers[..] else {
+        panic!("{:?}", next);
+    };
+    assert_eq!(self_type.tag, Some("node"));
+    assert_eq!(self_type.members, None);
+    assert_eq!(
+        next.declarators[0].declarator.derived,
+        [DerivedDeclarator::Pointer(Vec::new())]
+    );
+    assert_eq!(
+        data.declarators[0].declarator.derived,
+        [DerivedDeclarator::Array {
+            qualifiers: Vec::new(),
+            is_static: false,
+            size: None
+        }]
+    );
+}
+
+#[test]
+fn test_tag_namespaces() {
+    // Forward declarations.
+    let unit = parse("struct S;\nunion U *p;");
+    let TypeSpecifier::Struct(s) = declared_type(&unit, 0) else {
+        panic!("{:?}", unit);
+    };
+    assert_eq!(
+        (s.kind, s.tag, &s.members),
+        (StructKind::Struct, Some("S"), &None)
+    );
+    let TypeSpecifier::Struct(u) = declared_type(&unit, 1) else {
+        panic!("{:?}", unit);
+    };
+    assert_eq!((u.kind, u.tag), (StructKind::Union, Some("U")));
+
+    // A tag and a typedef name may be spelled alike.
+    let unit = parse("typedef struct node node;\nnode *n;\nstruct node *m;");
+    assert_eq!(declared_type(&unit, 1), &TypeSpecifier::TypedefName("node"));
+    let TypeSpecifier::Struct(m) = declared_type(&unit, 2) else {
+        panic!("{:?}", unit);
+    };
+    assert_eq!(m.tag, Some("node"));
+    let unit = parse("typedef int T;\nstruct T { T T; } t;");
+    let TypeSpecifier::Struct(t) = declared_type(&unit, 1) else {
+        panic!("{:?}", unit);
+    };
+    assert_eq!(t.tag, Some("T"));
+    let member = member_declarations(t)[0];
+    assert_eq!(
+        member.specifiers.type_specifiers,
+        [TypeSpecifier::TypedefName("T")]
+    );
+    assert_eq!(member.declarators[0].declarator.name, Some("T"));
+
+    // Members do not hide typedef names.
+    let unit = parse("typedef int T;\nstruct S { int T; };\nvoid f(void) { T * x; }");
+    assert!(matches!(first_item(&unit), BlockItem::Declaration(_)));
+}
+
+#[test]
+fn test_enum_definitions() {
+    let unit = parse("enum color { RED, GREEN = 4, BLUE, };\nenum color c;");
+    let TypeSpecifier::Enum(color) = declared_type(&unit, 0) else {
+        panic!("{:?}", unit);
+    };
+    assert_eq!(color.tag, Some("color"));
+    let values: Vec<_> = color
+        .enumerators
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|enumerator| (enumerator.name, enumerator.value.as_ref().map(sexp)))
+        .collect();
+    assert_eq!(
+        values,
+        [
+            ("RED", None),
+            ("GREEN", Some("4".to_string())),
+            ("BLUE", None)
+        ]
+    );
+    let TypeSpecifier::Enum(c) = declared_type(&unit, 1) else {
+        panic!("{:?}", unit);
+    };
+    assert_eq!((c.tag, &c.enumerators), (Some("color"), &None));
+
+    // Later constants may refer to earlier ones.
+    let unit = parse("enum { A = 1, B = A << 1 };");
+    let TypeSpecifier::Enum(flags) = declared_type(&unit, 0) else {
+        panic!("{:?}", unit);
+    };
+    let b = &flags.enumerators.as_ref().unwrap()[1];
+    assert_eq!(
+        b.value.as_ref().map(sexp),
+        Some("(ShiftLeft A 1)".to_string())
+    );
+
+    // An enumeration constant hides a typedef name.
+    let unit = parse("typedef int T;\nvoid f(void) { enum { T }; T * x; }");
+    let ExternalDeclaration::Function(f) = &unit.items[1] else {
+        panic!("{:?}", unit);
+    };
+    assert!(matches!(
+        &f.body.items[1],
+        BlockItem::Stmt(Stmt {
+            kind: StmtKind::Expr(Some(_)),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_tag_errors() {
+    let error = |src| Parser::new(src).parse().unwrap_err().to_string();
+
+    assert_eq!(error("struct { int x } s;"), "expected `;`, found \"}\"");
+    assert_eq!(error("struct;"), "expected tag or `{`, found \";\"");
+    assert_eq!(error("enum e { A B };"), "expected `}`, found \"B\"");
+    assert_eq!(
+        error("struct S { x; };"),
+        "expected member declaration, found \"x\""
+    );
+    assert_eq!(error("enum { };"), "expected identifier, found \"}\"");
+}
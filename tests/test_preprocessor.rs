@@ -0,0 +1,997 @@
+use std::path::Path;
+
+use scanner::{
+    Arena, DiagnosticPragma, IncludeResolver, KeywordType, LexError, PackPragma, Pragma,
+    PragmaSwitch, PreprocessError, Preprocessor, SkipReason, Span, Standard, Target, Token,
+    VirtualFileSystem,
+};
+
+/// Preprocesses `src`, spelling the output with a space between tokens.
+fn preprocess(src: &str) -> (String, Vec<PreprocessError>) {
+    let arena = Arena::new();
+    let mut preprocessor = Preprocessor::new(src, &arena);
+    let spellings: Vec<&str> = preprocessor.by_ref().map(|t| t.spelling()).collect();
+    (
+        spellings.join(" ").trim_end().to_string(),
+        preprocessor.errors().to_vec(),
+    )
+}
+
+fn expand(src: &str) -> String {
+    let (output, errors) = preprocess(src);
+    assert_eq!(errors, []);
+    output
+}
+
+fn without_spaces(text: &str) -> String {
+    text.split_whitespace().collect()
+}
+
+#[test]
+fn test_object_like_macros() {
+    let src = "#define N 10\n#define EMPTY\nint a[N] EMPTY;\n#undef N\nN";
+    assert_eq!(expand(src), "int a [ 10 ] ; N");
+
+    let arena = Arena::new();
+    let tokens: Vec<Token> = Preprocessor::new("#define T int\nT x;", &arena)
+        .map(|t| t.token().clone())
+        .collect();
+    assert_eq!(
+        tokens,
+        [
+            Token::Keyword(KeywordType::Int),
+            Token::Id("x"),
+            Token::SemiColon,
+            Token::Eof
+        ]
+    );
+}
+
+#[test]
+fn test_function_like_macros() {
+    let src = "
+#define max(a, b) ((a) > (b) ? (a) : (b))
+#define f(x) x
+max(1, f(2)) f + f
+(3) f(
+  (4,5))";
+    assert_eq!(
+        without_spaces(&expand(src)),
+        without_spaces("((1) > (2) ? (1) : (2)) f + 3 (4,5)")
+    );
+}
+
+#[test]
+fn test_macros_do_not_expand_recursively() {
+    assert_eq!(expand("#define foo foo + 1\nfoo"), "foo + 1");
+    assert_eq!(expand("#define a b\n#define b a\na b"), "a b");
+    assert_eq!(
+        expand("#define f(x) f(x) + g\nf(f(1))"),
+        "f ( f ( 1 ) + g ) + g"
+    );
+}
+
+#[test]
+fn test_standard_example_3() {
+    let src = r#"
+#define x 3
+#define f(a) f(x * (a))
+#undef x
+#define x 2
+#define g f
+#define z z[0]
+#define h g(~
+#define m(a) a(w)
+#define w 0,1
+#define t(a) a
+#define p() int
+#define q(x) x
+#define r(x,y) x ## y
+#define str(x) # x
+f(y+1) + f(f(z)) % t(t(g)(0) + t)(1);
+g(x+(3,4)-w) | h 5) & m
+(f)^m(m);
+p() i[q()] = { q(1), r(2,3), r(4,), r(,5), r(,) };
+char c[2][6] = { str(hello), str() };
+"#;
+    let expected = r#"
+f(2 * (y+1)) + f(2 * (f(2 * (z[0])))) % f(2 * (0)) + t(1);
+f(2 * (2+(3,4)-0,1)) | f(2 * (~ 5)) & f(2 * (0,1))^m(0,1);
+int i[] = { 1, 23, 4, 5, };
+char c[2][6] = { "hello", "" };
+"#;
+    assert_eq!(without_spaces(&expand(src)), without_spaces(expected));
+}
+
+#[test]
+fn test_standard_example_4() {
+    let src = r#"
+#define str(s) # s
+#define xstr(s) str(s)
+#define debug(s, t) printf("x" # s "= %d, x" # t "= %s", \
+ x ## s, x ## t)
+#define INCFILE(n) vers ## n
+#define glue(a, b) a ## b
+#define xglue(a, b) glue(a, b)
+#define HIGHLOW "hello"
+#define LOW LOW ", world"
+debug(1, 2);
+fputs(str(strncmp("abc\0d", "abc", '\4') // this goes away
+ == 0) str(: @\n), s);
+xstr(INCFILE(2).h)
+glue(HIGH, LOW);
+xglue(HIGH, LOW)
+"#;
+    let (output, errors) = preprocess(src);
+    let expected = [
+        r#"printf ( "x" "1" "= %d, x" "2" "= %s" , x1 , x2 ) ;"#,
+        r#"fputs ( "strncmp(\"abc\\0d\", \"abc\", '\\4') == 0" ": @\n" , s ) ;"#,
+        r#""vers2.h""#,
+        r#""hello" ;"#,
+        r#""hello" ", world""#,
+    ];
+    assert_eq!(output, expected.join(" "));
+    assert!(errors
+        .iter()
+        .all(|error| matches!(error, PreprocessError::Lex(LexError::InvalidSymbol { .. }))));
+}
+
+#[test]
+fn test_variadic_macros() {
+    let src = r#"
+#define debug(...) fprintf(stderr, __VA_ARGS__)
+#define showlist(...) puts(#__VA_ARGS__)
+#define report(test, ...) ((test)?puts(#test):\
+ printf(__VA_ARGS__))
+debug("Flag");
+debug("X = %d\n", x);
+showlist(The first, second, and third items.);
+report(x>y, "x is %d but y is %d", x, y);
+"#;
+    let expected = r#"
+fprintf(stderr, "Flag" );
+fprintf(stderr, "X = %d\n", x );
+puts( "The first, second, and third items." );
+((x>y)?puts("x>y"): printf("x is %d but y is %d", x, y));
+"#;
+    assert_eq!(without_spaces(&expand(src)), without_spaces(expected));
+}
+
+#[test]
+fn test_va_opt() {
+    let src = "
+#define F(...) f(0 __VA_OPT__(,) __VA_ARGS__)
+#define G(X, ...) f(0, X __VA_OPT__(,) __VA_ARGS__)
+#define SDEF(sname, ...) S sname __VA_OPT__(= { __VA_ARGS__ })
+#define EMP
+F(a,b,c) F() F(EMP)
+G(a,b,c) G(a,) G(a)
+SDEF(foo); SDEF(bar, 1, 2);
+";
+    let expected = "
+f(0, a, b, c) f(0) f(0)
+f(0, a, b, c) f(0, a) f(0, a)
+S foo; S bar = { 1, 2 };
+";
+    assert_eq!(without_spaces(&expand(src)), without_spaces(expected));
+}
+
+#[test]
+fn test_expansion_trace() {
+    let src = "#define ONE 1\n#define TWO ONE + ONE\nx = TWO;";
+    let arena = Arena::new();
+    let tokens: Vec<_> = Preprocessor::new(src, &arena).collect();
+
+    assert_eq!(tokens[0].spelling(), "x");
+    assert!(tokens[0].expansion().is_none());
+
+    let one = tokens[2].expansion().unwrap();
+    assert_eq!(tokens[2].spelling(), "1");
+    assert_eq!(tokens[2].span(), Span::new(12, 13, 1, 13));
+    assert_eq!(one.name(), "ONE");
+    assert_eq!(one.definition(), Span::new(8, 11, 1, 9));
+    assert_eq!(one.invocation(), Span::new(26, 29, 2, 13));
+
+    let two = one.parent().unwrap();
+    assert_eq!(two.name(), "TWO");
+    assert_eq!(two.definition(), Span::new(22, 25, 2, 9));
+    assert_eq!(two.invocation(), Span::new(40, 43, 3, 5));
+    assert!(two.parent().is_none());
+
+    assert_eq!(tokens[3].expansion().map(|e| e.name()), Some("TWO"));
+}
+
+#[test]
+fn test_macro_errors() {
+    let errors = |src| preprocess(src).1;
+
+    assert_eq!(
+        errors("#define f(a, b) a\nf(1)"),
+        [PreprocessError::WrongArgumentCount {
+            name: "f".to_string(),
+            expected: 2,
+            found: 1,
+            span: Span::new(18, 19, 2, 1)
+        }]
+    );
+    assert_eq!(
+        errors("#define f(a) a\nf(1, 2)")[0].to_string(),
+        "macro \"f\" passed 2 arguments, but takes just 1"
+    );
+    assert_eq!(
+        errors("#define f(a) a\nf(1"),
+        [PreprocessError::UnterminatedMacroCall {
+            name: "f".to_string(),
+            span: Span::new(15, 16, 2, 1)
+        }]
+    );
+    assert_eq!(
+        errors("#define f(a) ## a"),
+        [PreprocessError::PasteAtEdge {
+            span: Span::new(13, 15, 1, 14)
+        }]
+    );
+    assert_eq!(
+        errors("#define V(...) __VA_OPT__(x ##)\nV(1)"),
+        [PreprocessError::PasteAtEdge {
+            span: Span::new(28, 30, 1, 29)
+        }]
+    );
+    assert_eq!(
+        errors("#define f(a) #b"),
+        [PreprocessError::StringifyNonParameter {
+            span: Span::new(13, 14, 1, 14)
+        }]
+    );
+    assert_eq!(
+        errors("#define f(a, a) a"),
+        [PreprocessError::InvalidParameterList {
+            span: Span::new(13, 14, 1, 14)
+        }]
+    );
+    assert_eq!(
+        errors("#define f __VA_ARGS__"),
+        [PreprocessError::VariadicOutsideVariadicMacro {
+            span: Span::new(10, 21, 1, 11)
+        }]
+    );
+    assert_eq!(
+        errors("#define 1\n#define defined\n#undef"),
+        [
+            PreprocessError::InvalidMacroName {
+                span: Span::new(8, 9, 1, 9)
+            },
+            PreprocessError::InvalidMacroName {
+                span: Span::new(18, 25, 2, 9)
+            },
+            PreprocessError::InvalidMacroName {
+                span: Span::new(27, 32, 3, 2)
+            },
+        ]
+    );
+    assert_eq!(
+        errors("#foo bar"),
+        [PreprocessError::InvalidDirective {
+            name: "foo".to_string(),
+            span: Span::new(1, 4, 1, 2)
+        }]
+    );
+}
+
+#[test]
+fn test_redefinitions() {
+    assert_eq!(
+        preprocess("#define A 1 + 2\n#define A 1  +  2\n# \nA").1,
+        []
+    );
+    assert_eq!(
+        preprocess("#define A 1 + 2\n#define A 1+2\nA"),
+        (
+            "1 + 2".to_string(),
+            vec![PreprocessError::MacroRedefined {
+                name: "A".to_string(),
+                span: Span::new(24, 25, 2, 9)
+            }]
+        )
+    );
+}
+
+#[test]
+fn test_nested_calls() {
+    let nested = |depth| {
+        format!(
+            "#define id(x) x\n{}1{}",
+            "id(".repeat(depth),
+            ")".repeat(depth)
+        )
+    };
+    assert_eq!(expand(&nested(200)), "1");
+    assert_eq!(
+        expand("#define N 1\n#define f(x) x #x x\nf(f(N))"),
+        "1 \"N\" 1 \"f(N)\" 1 \"N\" 1"
+    );
+
+    let (_, errors) = preprocess(&nested(300));
+    assert_eq!(
+        errors,
+        [PreprocessError::MacroNestingTooDeep {
+            span: Span::new(787, 789, 2, 772)
+        }]
+    );
+}
+
+#[test]
+fn test_empty_paste_operands() {
+    let src = "#define F(a, b, c) x a ## b ## c\nF(,,z) F(y,,z) F(,y,) F(,,)";
+    assert_eq!(expand(src), "x z x yz x y x");
+}
+
+#[test]
+fn test_expansions_start_lines() {
+    let arena = Arena::new();
+    let src = "#define F(a) a\n#define H # define\nint x;\nF(1)\nH y";
+    let tokens: Vec<_> = Preprocessor::new(src, &arena)
+        .map(|t| (t.spelling(), t.starts_line()))
+        .collect();
+    assert_eq!(
+        tokens,
+        [
+            ("int", true),
+            ("x", false),
+            (";", false),
+            ("1", true),
+            ("#", true),
+            ("define", false),
+            ("y", false),
+            ("", false)
+        ]
+    );
+}
+
+#[test]
+fn test_invalid_paste() {
+    let (output, errors) = preprocess("#define cat(a, b) a ## b\ncat(+, -) cat(x, 1)");
+    assert_eq!(output, "+ - x1");
+    assert_eq!(
+        errors,
+        [PreprocessError::InvalidPaste {
+            text: "+-".to_string(),
+            span: Span::new(29, 30, 2, 5)
+        }]
+    );
+}
+
+#[test]
+fn test_preprocessing_numbers() {
+    // Only the numbers left after expansion have to be valid constants.
+    let arena = Arena::new();
+    let src = "#define CAT(a, b) a##b\n#define E 1e\nCAT(0x, 1F) CAT(1e, 3)";
+    let mut preprocessor = Preprocessor::new(src, &arena);
+    let tokens: Vec<Token> = preprocessor.by_ref().map(|t| t.token().clone()).collect();
+    assert_eq!(preprocessor.errors(), []);
+    let numbers: Vec<_> = tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Number(number) => Some(number.raw()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(numbers, ["0x1F", "1e3"]);
+
+    let (output, errors) = preprocess("#define E 1e\nE; 0x");
+    assert_eq!(output, "1e ; 0x");
+    assert_eq!(
+        errors,
+        [
+            PreprocessError::Lex(LexError::InvalidNumber {
+                text: "1e".to_string(),
+                span: Span::new(10, 12, 1, 11)
+            }),
+            PreprocessError::Lex(LexError::InvalidNumber {
+                text: "0x".to_string(),
+                span: Span::new(16, 18, 2, 4)
+            })
+        ]
+    );
+    assert!(matches!(
+        preprocess("#if 1e\n#endif").1[..],
+        [PreprocessError::Lex(LexError::InvalidNumber { .. })]
+    ));
+}
+
+#[test]
+fn test_conditional_groups() {
+    let src = "
+#define A 1
+#ifdef A
+a
+#else
+not_a
+#endif
+#ifndef B
+not_b
+#endif
+#if 0
+zero
+#elif defined A && !defined(B)
+elif
+#else
+else
+#endif
+#if 0
+#elifdef A
+elifdef
+#endif
+";
+    assert_eq!(expand(src), "a not_b elif elifdef");
+}
+
+#[test]
+fn test_nested_conditionals() {
+    let src = "
+#if 1
+  #if 0
+    a
+  #elif 1
+    b
+    #if 1
+      c
+    #endif
+  #endif
+#else
+  #if 1
+    d
+  #else
+    e
+  #endif
+#endif
+";
+    assert_eq!(expand(src), "b c");
+}
+
+#[test]
+fn test_if_expressions() {
+    let cases = [
+        ("1 + 2 * 3 == 7", true),
+        ("(1 + 2) * 3 == 7", false),
+        ("10 / 3 == 3 && 10 % 3 == 1", true),
+        ("-1 < 0", true),
+        ("-1 < 0u", false),
+        ("-1 > 0 ? 1 : 0u", false),
+        ("0xFFFFFFFFFFFFFFFF == -1", true),
+        ("1 << 4 == 16 && -16 >> 2 == -4", true),
+        (
+            "~0 == -1 && (5 & 3) == 1 && (5 | 3) == 7 && (5 ^ 3) == 6",
+            true,
+        ),
+        ("'A' == 65", true),
+        ("UNDEFINED == 0", true),
+        ("FOUR == 4 && TWICE(3) == 6", true),
+        ("0 && 1 / 0", false),
+        ("1 || 1 / 0", true),
+        ("1 ? 2 : 1 / 0", true),
+        ("(2, 0)", false),
+        ("true", false),
+        ("defined FOUR && !defined(UNDEFINED)", true),
+        ("__has_include(<stdio.h>)", false),
+    ];
+    for (expression, expected) in cases {
+        let src = format!(
+            "#define FOUR 4\n#define TWICE(x) ((x) * 2)\n#if {}\nyes\n#else\nno\n#endif",
+            expression
+        );
+        let expected = if expected { "yes" } else { "no" };
+        assert_eq!(expand(&src), expected, "{}", expression);
+    }
+
+    // `true` is only a keyword since C23.
+    for (standard, expected) in [(Standard::C99, "no"), (Standard::C23, "yes")] {
+        let arena = Arena::new();
+        let output: Vec<&str> = Preprocessor::new("#if true\nyes\n#else\nno\n#endif", &arena)
+            .with_standard(standard)
+            .map(|t| t.spelling())
+            .collect();
+        assert_eq!(output, [expected, ""], "{:?}", standard);
+    }
+}
+
+#[test]
+fn test_if_expression_errors() {
+    let errors = |src| preprocess(src).1;
+
+    assert_eq!(
+        errors("#if 1 / 0\n#endif"),
+        [PreprocessError::DivisionByZero {
+            span: Span::new(6, 7, 1, 7)
+        }]
+    );
+    assert_eq!(
+        errors("#if\n#endif"),
+        [PreprocessError::InvalidExpression {
+            span: Span::new(1, 3, 1, 2)
+        }]
+    );
+    assert_eq!(
+        errors("#if 1 +\n#endif"),
+        [PreprocessError::InvalidExpression {
+            span: Span::new(6, 7, 1, 7)
+        }]
+    );
+    assert_eq!(
+        errors("#if 1.5\n#endif"),
+        [PreprocessError::InvalidExpression {
+            span: Span::new(4, 7, 1, 5)
+        }]
+    );
+    assert_eq!(
+        errors("#if (1\n#endif"),
+        [PreprocessError::InvalidExpression {
+            span: Span::new(5, 6, 1, 6)
+        }]
+    );
+    assert_eq!(
+        errors("#if defined(\n#endif"),
+        [PreprocessError::InvalidExpression {
+            span: Span::new(11, 12, 1, 12)
+        }]
+    );
+    assert_eq!(
+        errors("#ifdef\n#endif"),
+        [PreprocessError::InvalidMacroName {
+            span: Span::new(1, 6, 1, 2)
+        }]
+    );
+}
+
+#[test]
+fn test_unbalanced_conditionals() {
+    let errors = |src| preprocess(src).1;
+
+    assert_eq!(
+        errors("#endif\n#else"),
+        [
+            PreprocessError::UnbalancedConditional {
+                directive: "endif".to_string(),
+                span: Span::new(1, 6, 1, 2)
+            },
+            PreprocessError::UnbalancedConditional {
+                directive: "else".to_string(),
+                span: Span::new(8, 12, 2, 2)
+            },
+        ]
+    );
+    assert_eq!(
+        errors("#if 1\n#else\n#elif 1\n#endif"),
+        [PreprocessError::ElseAfterElse {
+            directive: "elif".to_string(),
+            span: Span::new(13, 17, 3, 2)
+        }]
+    );
+    assert_eq!(
+        errors("#ifdef A\n#if 1\n"),
+        [
+            PreprocessError::UnterminatedConditional {
+                directive: "ifdef".to_string(),
+                span: Span::new(1, 6, 1, 2)
+            },
+            PreprocessError::UnterminatedConditional {
+                directive: "if".to_string(),
+                span: Span::new(10, 12, 2, 2)
+            },
+        ]
+    );
+    assert_eq!(errors("#if 1\n#endif\n")[..], [][..]);
+}
+
+#[test]
+fn test_skipped_groups() {
+    let src = "#if 0\ndon't @ \"unterminated\n#define A\n#bogus\n#else\nA\n#endif\nx\n#ifdef B\ny";
+    let arena = Arena::new();
+    let mut preprocessor = Preprocessor::new(src, &arena);
+    let output: Vec<&str> = preprocessor.by_ref().map(|t| t.spelling()).collect();
+    assert_eq!(output, ["A", "x", ""]);
+    assert_eq!(
+        preprocessor.inactive_ranges(),
+        [Span::new(6, 45, 2, 1), Span::new(71, 72, 10, 1)]
+    );
+    assert_eq!(
+        preprocessor.errors(),
+        [PreprocessError::UnterminatedConditional {
+            directive: "ifdef".to_string(),
+            span: Span::new(63, 68, 9, 2)
+        }]
+    );
+}
+
+/// A project with its own headers in `src`, `include` and `lib`, and system
+/// headers in `sys` and `sys2`.
+fn resolver() -> IncludeResolver {
+    let files = VirtualFileSystem::new()
+        .with_file("src/local.h", "local_src")
+        .with_file("include/local.h", "local_include")
+        .with_file(
+            "include/config.h",
+            "#include_next <config.h>\nconfig_include",
+        )
+        .with_file("sys/config.h", "#include_next <config.h>\nconfig_sys")
+        .with_file("sys2/config.h", "config_sys2")
+        .with_file("sys/stdio.h", "#include \"../include/local.h\"\nstdio")
+        .with_file(
+            "include/guarded.h",
+            "// comment\n#ifndef GUARDED_H\n#define GUARDED_H\nguarded\n#endif\n",
+        )
+        .with_file("include/once.h", "#pragma once\nonce")
+        .with_file("include/a.h", "a\n#include \"b.h\"\n")
+        .with_file("include/b.h", "b\n#include \"a.h\"\n");
+    IncludeResolver::new(files)
+        .with_user_dir("include")
+        .with_user_dir("lib")
+        .with_system_dir("sys")
+        .with_system_dir("sys2")
+}
+
+fn include(src: &str) -> (String, Vec<PreprocessError>) {
+    let arena = Arena::new();
+    let mut preprocessor = Preprocessor::new(src, &arena)
+        .with_path("src/main.c")
+        .with_include_resolver(resolver());
+    let spellings: Vec<&str> = preprocessor.by_ref().map(|t| t.spelling()).collect();
+    (
+        spellings.join(" ").trim_end().to_string(),
+        preprocessor.errors().to_vec(),
+    )
+}
+
+#[test]
+fn test_include_search_order() {
+    assert_eq!(include("#include \"local.h\"\n").0, "local_src");
+    assert_eq!(include("#include <local.h>\n").0, "local_include");
+    assert_eq!(include("#include <stdio.h>\n").0, "local_include stdio");
+    assert_eq!(
+        include("#include <config.h>\n").0,
+        "config_sys2 config_sys config_include"
+    );
+    assert_eq!(
+        include("#define HEADER <local.h>\n#include HEADER\n").0,
+        "local_include"
+    );
+    assert_eq!(
+        include("#define HEADER \"local.h\"\n#include HEADER\nx").0,
+        "local_src x"
+    );
+
+    assert_eq!(
+        include("#include <missing.h>\n#include\n"),
+        (
+            String::new(),
+            vec![
+                PreprocessError::IncludeNotFound {
+                    name: "missing.h".to_string(),
+                    span: Span::new(9, 20, 1, 10)
+                },
+                PreprocessError::InvalidInclude {
+                    span: Span::new(22, 29, 2, 2)
+                },
+            ]
+        )
+    );
+}
+
+#[test]
+fn test_has_include() {
+    let src = "
+#if __has_include(<local.h>) && !__has_include(\"missing.h\")
+yes
+#endif
+#if __has_include_next(<config.h>)
+next
+#endif
+";
+    assert_eq!(include(src), ("yes next".to_string(), vec![]));
+}
+
+#[test]
+fn test_include_once() {
+    let src = "
+#include \"guarded.h\"
+#include <guarded.h>
+#include <once.h>
+#include <once.h>
+x
+";
+    let arena = Arena::new();
+    let mut preprocessor = Preprocessor::new(src, &arena)
+        .with_path("src/main.c")
+        .with_include_resolver(resolver());
+    let output: Vec<&str> = preprocessor.by_ref().map(|t| t.spelling()).collect();
+    assert_eq!(output, ["guarded", "#pragma once", "once", "x", ""]);
+    assert_eq!(preprocessor.errors(), []);
+    assert_eq!(
+        preprocessor.include_guard("include/guarded.h"),
+        Some("GUARDED_H")
+    );
+    assert_eq!(preprocessor.include_guard("include/once.h"), None);
+
+    let includes = preprocessor.include_graph().includes();
+    assert_eq!(
+        includes[2].skipped(),
+        Some(&SkipReason::IncludeGuard("GUARDED_H".to_string()))
+    );
+    assert_eq!(includes[4].skipped(), Some(&SkipReason::PragmaOnce));
+    assert_eq!(
+        preprocessor.include_graph().to_string(),
+        "\
+src/main.c
+  include/guarded.h
+  include/guarded.h (guarded by GUARDED_H)
+  include/once.h
+  include/once.h (#pragma once)
+"
+    );
+}
+
+#[test]
+fn test_include_cycle() {
+    let arena = Arena::new();
+    let mut preprocessor =
+        Preprocessor::new("#include <a.h>\n", &arena).with_include_resolver(resolver());
+    let output: Vec<&str> = preprocessor.by_ref().map(|t| t.spelling()).collect();
+    assert_eq!(output, ["a", "b", ""]);
+
+    let [PreprocessError::IncludeCycle { path, span }] = preprocessor.errors() else {
+        panic!("{:?}", preprocessor.errors());
+    };
+    assert_eq!(path, "include/a.h");
+    assert_eq!(
+        preprocessor.file_path(*span),
+        Some(Path::new("include/b.h"))
+    );
+    assert_eq!((span.line(), span.column()), (2, 10));
+    assert_eq!(
+        preprocessor.include_graph().to_string(),
+        "\
+<input>
+  include/a.h
+    include/b.h
+      include/a.h (cycle)
+"
+    );
+}
+
+#[test]
+fn test_include_spans() {
+    let src = "#include <local.h>\n#if 1\nx";
+    let arena = Arena::new();
+    let mut preprocessor = Preprocessor::new(src, &arena)
+        .with_path("src/main.c")
+        .with_include_resolver(resolver());
+    let tokens: Vec<_> = preprocessor.by_ref().collect();
+    let paths: Vec<_> = tokens
+        .iter()
+        .map(|t| preprocessor.file_path(t.span()).unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(paths, ["include/local.h", "src/main.c", "src/main.c"]);
+    assert_eq!(tokens[0].span().line(), 1);
+    assert_eq!(
+        preprocessor.errors(),
+        [PreprocessError::UnterminatedConditional {
+            directive: "if".to_string(),
+            span: Span::new(20, 22, 2, 2)
+        }]
+    );
+}
+
+#[test]
+fn test_predefined_macros() {
+    let src = "
+__STDC__ __STDC_VERSION__ __LINE__
+#define LINE __LINE__
+#define f(x) x __LINE__
+f(
+LINE) __COUNTER__ __COUNTER__
+#if defined(__LINE__) && __x86_64__ && __SIZEOF_LONG__ == 8 && __linux__
+ok
+#endif
+";
+    assert_eq!(expand(src), "1 201112L 2 6 5 0 1 ok");
+
+    let arena = Arena::new();
+    let mut preprocessor = Preprocessor::new("__FILE__ __DATE__ __TIME__", &arena)
+        .with_path("dir/file.c")
+        .with_timestamp(1_709_254_861);
+    let output: Vec<&str> = preprocessor.by_ref().map(|t| t.spelling()).collect();
+    assert_eq!(
+        output,
+        ["\"dir/file.c\"", "\"Mar  1 2024\"", "\"01:01:01\"", ""]
+    );
+
+    let arena = Arena::new();
+    let src = "__STDC_VERSION__ __SIZEOF_POINTER__ __i386__ __x86_64__";
+    let output: Vec<&str> = Preprocessor::new(src, &arena)
+        .with_standard(Standard::C89)
+        .with_target(Target::I686Linux)
+        .map(|t| t.spelling())
+        .collect();
+    assert_eq!(output, ["__STDC_VERSION__", "4", "1", "__x86_64__", ""]);
+}
+
+#[test]
+fn test_command_line_definitions() {
+    let src = "A B C(2) __linux__ D";
+    let arena = Arena::new();
+    let mut preprocessor = Preprocessor::new(src, &arena)
+        .with_define("A")
+        .with_define("B=x y")
+        .with_define("C(v)=v*v")
+        .with_define("D=1")
+        .with_undef("D")
+        .with_undef("__linux__");
+    let output: Vec<&str> = preprocessor.by_ref().map(|t| t.spelling()).collect();
+    assert_eq!(output, ["1", "x", "y", "2", "*", "2", "__linux__", "D", ""]);
+    assert_eq!(preprocessor.errors(), []);
+
+    let arena = Arena::new();
+    let mut preprocessor = Preprocessor::new("x", &arena).with_define("1=2");
+    preprocessor.by_ref().for_each(drop);
+    let [PreprocessError::InvalidMacroName { span }] = preprocessor.errors() else {
+        panic!("{:?}", preprocessor.errors());
+    };
+    assert_eq!(
+        preprocessor.file_path(*span),
+        Some(Path::new("<command-line>"))
+    );
+}
+
+#[test]
+fn test_line_directives() {
+    let src = "a\n#line 42 \"grammar.y\"\nb\n#define L 7\n#line L\nc\n# 100 \"other.c\" 1 3\nd __LINE__ __FILE__";
+    let arena = Arena::new();
+    let mut preprocessor = Preprocessor::new(src, &arena);
+    let tokens: Vec<_> = preprocessor.by_ref().collect();
+    let spellings: Vec<&str> = tokens.iter().map(|t| t.spelling()).collect();
+    assert_eq!(spellings, ["a", "b", "c", "d", "100", "\"other.c\"", ""]);
+
+    let lines: Vec<usize> = tokens.iter().map(|t| t.span().line()).collect();
+    assert_eq!(lines, [1, 42, 7, 100, 100, 100, 100]);
+    let physical: Vec<usize> = tokens
+        .iter()
+        .map(|t| preprocessor.physical_span(t.span()).line())
+        .collect();
+    assert_eq!(physical, [1, 3, 6, 8, 8, 8, 8]);
+    let paths: Vec<&str> = tokens[..4]
+        .iter()
+        .map(|t| {
+            preprocessor
+                .presumed_path(t.span())
+                .unwrap()
+                .to_str()
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(paths, ["<input>", "grammar.y", "grammar.y", "other.c"]);
+    assert_eq!(
+        preprocessor.file_path(tokens[3].span()),
+        Some(Path::new("<input>"))
+    );
+    assert_eq!(preprocessor.errors(), []);
+
+    let errors = |src| preprocess(src).1;
+    for src in [
+        "#line 0\n",
+        "#line x\n",
+        "#line 1 2\n",
+        "#line 1 L\"f\"\n",
+        "#line\n",
+    ] {
+        assert!(
+            matches!(
+                errors(src)[..],
+                [PreprocessError::InvalidLineDirective { .. }]
+            ),
+            "{:?}",
+            src
+        );
+    }
+    assert_eq!(errors("# 0 \"f\" 2\n#line 2147483647\n")[..], [][..]);
+
+    // A macro gives the file and line of where it is used, not defined.
+    let src = "#define F __FILE__ __LINE__\n#line 50 \"renamed.c\"\nF";
+    assert_eq!(expand(src), "\"renamed.c\" 50");
+}
+
+#[test]
+fn test_pragmas() {
+    let src = r#"
+#pragma once
+#pragma pack(push, 4)
+#pragma pack(push, saved)
+#pragma pack(pop, saved)
+#pragma pack()
+#pragma pack(2)
+#pragma pack(show)
+#pragma GCC diagnostic push
+#pragma GCC diagnostic ignored "-Wunused-variable"
+#pragma clang diagnostic error "-Wformat"
+#pragma GCC diagnostic pop
+#pragma STDC FP_CONTRACT OFF
+#pragma omp parallel   for
+#define DO_PRAGMA(x) _Pragma(#x)
+DO_PRAGMA(GCC diagnostic warning "-Wshadow") int x;
+_Pragma("STDC FP_CONTRACT ON")
+"#;
+    let arena = Arena::new();
+    let mut preprocessor = Preprocessor::new(src, &arena);
+    let pragmas: Vec<Pragma> = preprocessor
+        .by_ref()
+        .filter_map(|t| match t.token() {
+            Token::Pragma(pragma) => Some(pragma.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(preprocessor.errors(), []);
+    assert_eq!(
+        pragmas,
+        [
+            Pragma::Once,
+            Pragma::Pack(PackPragma::Push {
+                name: None,
+                alignment: Some(4)
+            }),
+            Pragma::Pack(PackPragma::Push {
+                name: Some("saved"),
+                alignment: None
+            }),
+            Pragma::Pack(PackPragma::Pop {
+                name: Some("saved")
+            }),
+            Pragma::Pack(PackPragma::Set(None)),
+            Pragma::Pack(PackPragma::Set(Some(2))),
+            Pragma::Pack(PackPragma::Show),
+            Pragma::Diagnostic(DiagnosticPragma::Push),
+            Pragma::Diagnostic(DiagnosticPragma::Ignored("-Wunused-variable")),
+            Pragma::Diagnostic(DiagnosticPragma::Error("-Wformat")),
+            Pragma::Diagnostic(DiagnosticPragma::Pop),
+            Pragma::FpContract(PragmaSwitch::Off),
+            Pragma::Unknown("omp parallel for"),
+            Pragma::Diagnostic(DiagnosticPragma::Warning("-Wshadow")),
+            Pragma::FpContract(PragmaSwitch::On),
+        ]
+    );
+
+    let (output, _) = preprocess("#if 0\n#pragma once\n#endif\n_Pragma(\"foo\") x");
+    assert_eq!(output, "#pragma foo x");
+
+    for src in [
+        "#pragma pack(3)\n",
+        "#pragma pack(pop, 4)\n",
+        "#pragma once more\n",
+        "#pragma GCC diagnostic ignored\n",
+        "#pragma STDC FP_CONTRACT MAYBE\n",
+        "_Pragma(x)",
+        "_Pragma",
+    ] {
+        assert!(
+            matches!(
+                preprocess(src).1[..],
+                [PreprocessError::InvalidPragma { .. }]
+            ),
+            "{:?}",
+            src
+        );
+    }
+}
+
+#[test]
+fn test_phases_1_and_2() {
+    let src = "??=def\\\nine A(x) x ??/\n + 1\n#define S \"a\\\nb\"\nA(2) S // c\\\nd\n";
+    let arena = Arena::new();
+    let mut preprocessor = Preprocessor::new(src, &arena).with_trigraphs(true);
+    let tokens: Vec<_> = preprocessor.by_ref().collect();
+    let output: Vec<&str> = tokens.iter().map(|t| t.spelling()).collect();
+    assert_eq!(output, ["2", "+", "1", "\"ab\"", ""]);
+    assert_eq!(preprocessor.errors(), []);
+    assert_eq!(tokens[0].span(), Span::new(47, 48, 6, 3));
+
+    let (output, _) = preprocess("??=define A 1\nA");
+    assert_eq!(output, "? ? = define A 1 A");
+}
@@ -1,4 +1,27 @@
-use scanner::{KeywordType, OperatorType, Scanner, Token};
+use scanner::{
+    attach_doc_comments, CharLiteral, CodeUnits, Encoding, FloatSuffix, IntegerSuffix, KeywordType,
+    LexError, NumberKind, NumberLiteral, OperatorType, Radix, Scanner, SourceText, Span,
+    SpannedToken, Standard, StringLiteral, Token, Trivia, TriviaKind,
+};
+
+fn int(raw: &str, value: u64) -> Token<'_> {
+    let kind = NumberKind::Integer {
+        value,
+        suffix: IntegerSuffix::None,
+    };
+    Token::Number(NumberLiteral::new(raw, Radix::Decimal, kind))
+}
+
+fn float(raw: &str, value: f64, suffix: FloatSuffix) -> Token<'_> {
+    let kind = NumberKind::Float { value, suffix };
+    Token::Number(NumberLiteral::new(raw, Radix::Decimal, kind))
+}
+
+fn scan(src: &str) -> (Vec<Token<'_>>, Vec<LexError>) {
+    let mut scanner = Scanner::new(src);
+    let tokens = scanner.by_ref().map(SpannedToken::into_token).collect();
+    (tokens, scanner.errors().to_vec())
+}
 
 #[test]
 fn test_tokenize_assign_int() {
@@ -7,9 +30,9 @@ fn test_tokenize_assign_int() {
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Int),
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::Assign),
-            Token::Number("10".to_string()),
+            int("10", 10),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -23,9 +46,9 @@ fn test_tokenize_assign_float_with_f_postfix() {
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Float),
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::Assign),
-            Token::Number("10.5".to_string()),
+            float("10.5f", 10.5, FloatSuffix::Float),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -39,9 +62,9 @@ fn test_tokenize_assign_float_without_f_postfix() {
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Float),
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::Assign),
-            Token::Number("10.5".to_string()),
+            float("10.5", 10.5, FloatSuffix::None),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -55,9 +78,9 @@ fn test_tokenize_assign_double_without_f_postfix() {
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Double),
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::Assign),
-            Token::Number("10.5".to_string()),
+            float("10.5", 10.5, FloatSuffix::None),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -71,9 +94,9 @@ fn test_tokenize_assign_double_with_f_postfix() {
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Double),
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::Assign),
-            Token::Number("10.5".to_string()),
+            float("10.5f", 10.5, FloatSuffix::Float),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -87,9 +110,9 @@ fn test_tokenize_float_number_whiout_integer_part() {
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Float),
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::Assign),
-            Token::Number(".5".to_string()),
+            float(".5", 0.5, FloatSuffix::None),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -103,11 +126,11 @@ fn test_tokenize_add() {
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Int),
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::Assign),
-            Token::Number("10".to_string()),
+            int("10", 10),
             Token::Operator(OperatorType::Plus),
-            Token::Number("32".to_string()),
+            int("32", 32),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -121,11 +144,11 @@ fn test_tokenize_subtract() {
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Int),
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::Assign),
-            Token::Number("10".to_string()),
+            int("10", 10),
             Token::Operator(OperatorType::Minus),
-            Token::Number("32".to_string()),
+            int("32", 32),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -139,11 +162,11 @@ fn test_tokenize_multiply() {
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Int),
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::Assign),
-            Token::Number("10".to_string()),
+            int("10", 10),
             Token::Operator(OperatorType::Asterisk),
-            Token::Number("32".to_string()),
+            int("32", 32),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -157,11 +180,11 @@ fn test_tokenize_divide() {
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Int),
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::Assign),
-            Token::Number("10".to_string()),
+            int("10", 10),
             Token::Operator(OperatorType::Divide),
-            Token::Number("32".to_string()),
+            int("32", 32),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -174,9 +197,9 @@ fn test_tokenize_add_equal() {
     assert_eq!(
         scanner.tokens(),
         vec![
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::PlusEqual),
-            Token::Number("32".to_string()),
+            int("32", 32),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -189,9 +212,9 @@ fn test_tokenize_subtract_equal() {
     assert_eq!(
         scanner.tokens(),
         vec![
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::MinusEqual),
-            Token::Number("32".to_string()),
+            int("32", 32),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -204,9 +227,9 @@ fn test_tokenize_divide_equal() {
     assert_eq!(
         scanner.tokens(),
         vec![
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::DivideEqual),
-            Token::Number("32".to_string()),
+            int("32", 32),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -219,9 +242,9 @@ fn test_tokenize_multiply_equal() {
     assert_eq!(
         scanner.tokens(),
         vec![
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::AsteriskEqual),
-            Token::Number("32".to_string()),
+            int("32", 32),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -236,9 +259,12 @@ fn test_tokenize_string() {
         vec![
             Token::Keyword(KeywordType::Char),
             Token::Operator(OperatorType::Asterisk),
-            Token::Id("str".to_string()),
+            Token::Id("str"),
             Token::Operator(OperatorType::Assign),
-            Token::String("Hello World!\0".to_string()),
+            Token::String(StringLiteral::new(
+                "\"Hello World!\"",
+                "Hello World!".into()
+            )),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -252,9 +278,9 @@ fn test_tokenize_char() {
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Char),
-            Token::Id("c".to_string()),
+            Token::Id("c"),
             Token::Operator(OperatorType::Assign),
-            Token::Char('0'),
+            Token::Char(CharLiteral::new("'0'", '0'.into())),
             Token::SemiColon,
             Token::Eof,
         ]
@@ -263,16 +289,24 @@ fn test_tokenize_char() {
 
 #[test]
 fn test_tokenize_invalid_id() {
-    let scanner = Scanner::new("int 12343abc;");
+    let (tokens, errors) = scan("int 12343abc;");
     assert_eq!(
-        scanner.tokens(),
+        tokens,
         vec![
             Token::Keyword(KeywordType::Int),
-            Token::Invalid("Invalid identifier \"12343abc\"!".to_string()),
+            Token::Invalid("12343abc"),
             Token::SemiColon,
             Token::Eof,
         ]
-    )
+    );
+    assert_eq!(
+        errors,
+        [LexError::InvalidIdentifier {
+            text: "12343abc".to_string(),
+            span: Span::new(4, 12, 1, 5),
+        }]
+    );
+    assert_eq!(errors[0].to_string(), "Invalid identifier \"12343abc\"!");
 }
 
 #[test]
@@ -287,13 +321,16 @@ printf(\"Hello World!\");
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Int),
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::Assign),
-            Token::Number("42".to_string()),
+            int("42", 42),
             Token::SemiColon,
-            Token::Id("printf".to_string()),
+            Token::Id("printf"),
             Token::OpenParen,
-            Token::String("Hello World!\0".to_string()),
+            Token::String(StringLiteral::new(
+                "\"Hello World!\"",
+                "Hello World!".into()
+            )),
             Token::CloseParen,
             Token::SemiColon,
             Token::Eof
@@ -320,13 +357,16 @@ printf(\"Hello World!\");
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Int),
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::Assign),
-            Token::Number("42".to_string()),
+            int("42", 42),
             Token::SemiColon,
-            Token::Id("printf".to_string()),
+            Token::Id("printf"),
             Token::OpenParen,
-            Token::String("Hello World!\0".to_string()),
+            Token::String(StringLiteral::new(
+                "\"Hello World!\"",
+                "Hello World!".into()
+            )),
             Token::CloseParen,
             Token::SemiColon,
             Token::Eof
@@ -344,9 +384,9 @@ int x = /* Weird place for a comment */ 42;
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Int),
-            Token::Id("x".to_string()),
+            Token::Id("x"),
             Token::Operator(OperatorType::Assign),
-            Token::Number("42".to_string()),
+            int("42", 42),
             Token::SemiColon,
             Token::Eof
         ]
@@ -388,53 +428,1369 @@ int main() {
         scanner.tokens(),
         vec![
             Token::Keyword(KeywordType::Int),
-            Token::Id("main".to_string()),
+            Token::Id("main"),
             Token::OpenParen,
             Token::CloseParen,
             Token::OpenBrace,
             Token::Keyword(KeywordType::Int),
-            Token::Id("a".to_string()),
+            Token::Id("a"),
             Token::Operator(OperatorType::Assign),
-            Token::Number("10".to_string()),
+            int("10", 10),
             Token::SemiColon,
             Token::Keyword(KeywordType::Int),
-            Token::Id("c".to_string()),
+            Token::Id("c"),
             Token::SemiColon,
             Token::Keyword(KeywordType::For),
             Token::OpenParen,
             Token::Keyword(KeywordType::Int),
-            Token::Id("i".to_string()),
+            Token::Id("i"),
             Token::Operator(OperatorType::Assign),
-            Token::Number("0".to_string()),
+            int("0", 0),
             Token::SemiColon,
-            Token::Id("i".to_string()),
+            Token::Id("i"),
             Token::Operator(OperatorType::LessThanOrEqual),
-            Token::Number("10".to_string()),
+            int("10", 10),
             Token::SemiColon,
-            Token::Id("i".to_string()),
+            Token::Id("i"),
             Token::Operator(OperatorType::Increment),
             Token::CloseParen,
             Token::OpenBrace,
             Token::Keyword(KeywordType::If),
             Token::OpenParen,
-            Token::Id("i".to_string()),
+            Token::Id("i"),
             Token::Operator(OperatorType::Plus),
-            Token::Id("a".to_string()),
+            Token::Id("a"),
             Token::Operator(OperatorType::LessThanOrEqual),
-            Token::Number("15".to_string()),
+            int("15", 15),
             Token::CloseParen,
             Token::OpenBrace,
-            Token::Id("c".to_string()),
+            Token::Id("c"),
             Token::Operator(OperatorType::Assign),
-            Token::Id("i".to_string()),
+            Token::Id("i"),
             Token::SemiColon,
             Token::CloseBrace,
             Token::CloseBrace,
             Token::Keyword(KeywordType::Return),
-            Token::Number("0".to_string()),
+            int("0", 0),
             Token::SemiColon,
             Token::CloseBrace,
             Token::Eof,
         ]
     )
 }
+
+#[test]
+fn test_spans_single_line() {
+    let spans: Vec<Span> = Scanner::new("int x = 10;").map(|t| t.span()).collect();
+    assert_eq!(
+        spans,
+        vec![
+            Span::new(0, 3, 1, 1),
+            Span::new(4, 5, 1, 5),
+            Span::new(6, 7, 1, 7),
+            Span::new(8, 10, 1, 9),
+            Span::new(10, 11, 1, 11),
+            Span::new(11, 11, 1, 12),
+        ]
+    )
+}
+
+#[test]
+fn test_spans_multiline_with_comments() {
+    let src = "int a;\n// comment\n  /* block\n comment */ a += 1;\n";
+    let tokens: Vec<SpannedToken> = Scanner::new(src).collect();
+    assert_eq!(tokens[3].token(), &Token::Id("a"));
+    assert_eq!(tokens[3].span(), Span::new(41, 42, 4, 13));
+    assert_eq!(&src[tokens[4].span().start()..tokens[4].span().end()], "+=");
+    assert_eq!(tokens[4].span(), Span::new(43, 45, 4, 15));
+    assert_eq!(
+        tokens.last().unwrap().span(),
+        Span::new(src.len(), src.len(), 5, 1)
+    );
+}
+
+#[test]
+fn test_spans_count_utf8_columns() {
+    let src = "char* s = \"ção\"; x;";
+    let tokens: Vec<SpannedToken> = Scanner::new(src).collect();
+    assert_eq!(tokens[4].span(), Span::new(10, 17, 1, 11));
+    assert_eq!(tokens[5].span(), Span::new(17, 18, 1, 16));
+    assert_eq!(tokens[6].token(), &Token::Id("x"));
+    assert_eq!(tokens[6].span(), Span::new(19, 20, 1, 18));
+}
+
+#[test]
+fn test_errors_invalid_symbol() {
+    let (tokens, errors) = scan("x = $;\ny @ 1;");
+    assert_eq!(
+        errors,
+        [
+            LexError::InvalidSymbol {
+                symbol: '$',
+                span: Span::new(4, 5, 1, 5),
+            },
+            LexError::InvalidSymbol {
+                symbol: '@',
+                span: Span::new(9, 10, 2, 3),
+            },
+        ]
+    );
+    assert_eq!(tokens[2], Token::Invalid("$"));
+}
+
+#[test]
+fn test_errors_multi_character_char() {
+    let (tokens, errors) = scan("char c = u'ab';");
+    assert_eq!(
+        errors,
+        [LexError::MultiCharacterChar {
+            span: Span::new(9, 14, 1, 10),
+        }]
+    );
+    assert_eq!(tokens[3], Token::Invalid("u'ab'"));
+}
+
+#[test]
+fn test_errors_empty_char() {
+    let (tokens, errors) = scan("char c = '';");
+    assert_eq!(
+        errors,
+        [LexError::EmptyChar {
+            span: Span::new(9, 11, 1, 10),
+        }]
+    );
+    assert_eq!(tokens[3], Token::Invalid("''"));
+    assert_eq!(errors[0].to_string(), "empty character constant");
+}
+
+#[test]
+fn test_no_errors_for_valid_input() {
+    let (_, errors) = scan("int x = 10;");
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn test_unterminated_string() {
+    let (tokens, errors) = scan("char* s = \"abc\nint x;");
+    assert_eq!(
+        errors,
+        [LexError::UnterminatedString {
+            span: Span::new(10, 14, 1, 11),
+        }]
+    );
+    assert_eq!(
+        tokens[4..],
+        [
+            Token::Invalid("\"abc"),
+            Token::Keyword(KeywordType::Int),
+            Token::Id("x"),
+            Token::SemiColon,
+            Token::Eof,
+        ]
+    );
+}
+
+#[test]
+fn test_unterminated_block_comment() {
+    let (tokens, errors) = scan("x; /* never closed");
+    assert_eq!(
+        errors,
+        [LexError::UnterminatedComment {
+            span: Span::new(3, 18, 1, 4),
+        }]
+    );
+    assert_eq!(tokens, vec![Token::Id("x"), Token::SemiColon, Token::Eof]);
+}
+
+#[test]
+fn test_line_comment_without_trailing_newline() {
+    let (tokens, errors) = scan("x; // last line");
+    assert!(errors.is_empty());
+    assert_eq!(tokens, vec![Token::Id("x"), Token::SemiColon, Token::Eof]);
+}
+
+#[test]
+fn test_truncated_input_at_end() {
+    for (src, last) in [
+        ("int x", Token::Id("x")),
+        ("x = 10", int("10", 10)),
+        ("x = 1.", float("1.", 1.0, FloatSuffix::None)),
+        ("a+", Token::Operator(OperatorType::Plus)),
+        ("a <", Token::Operator(OperatorType::LessThan)),
+    ] {
+        let tokens = Scanner::new(src).tokens();
+        assert_eq!(tokens[tokens.len() - 2..], [last, Token::Eof], "{:?}", src);
+    }
+}
+
+#[test]
+fn test_unterminated_char() {
+    for src in ["'", "'a", "'a\n'"] {
+        let (_, errors) = scan(src);
+        assert!(
+            matches!(errors[0], LexError::UnterminatedChar { .. }),
+            "{:?}",
+            src
+        );
+    }
+}
+
+#[test]
+fn test_scanner_is_lazy() {
+    let mut scanner = Scanner::new("int x = $; y = @;");
+    assert_eq!(scanner.next_token(), Some(Token::Keyword(KeywordType::Int)));
+    assert!(scanner.errors().is_empty());
+    assert_eq!(
+        scanner.nth(2).map(SpannedToken::into_token),
+        Some(Token::Invalid("$"))
+    );
+    assert_eq!(scanner.errors().len(), 1);
+}
+
+#[test]
+fn test_peek_does_not_consume() {
+    let mut scanner = Scanner::new("a = b;");
+    assert_eq!(scanner.peek().map(|t| t.token()), Some(&Token::Id("a")));
+    assert_eq!(
+        scanner.peek_nth(2).map(|t| t.token()),
+        Some(&Token::Id("b"))
+    );
+    assert_eq!(scanner.peek_nth(4).map(|t| t.token()), Some(&Token::Eof));
+    assert_eq!(scanner.peek_nth(5), None);
+    assert_eq!(scanner.next_token(), Some(Token::Id("a")));
+    assert_eq!(
+        scanner.peek().map(|t| t.span()),
+        Some(Span::new(2, 3, 1, 3))
+    );
+    assert_eq!(
+        scanner.tokens(),
+        vec![
+            Token::Operator(OperatorType::Assign),
+            Token::Id("b"),
+            Token::SemiColon,
+            Token::Eof,
+        ]
+    );
+}
+
+#[test]
+fn test_scanner_is_exhausted_after_eof() {
+    let mut scanner = Scanner::new("");
+    assert_eq!(scanner.next_token(), Some(Token::Eof));
+    assert_eq!(scanner.next_token(), None);
+    assert_eq!(scanner.peek(), None);
+    assert_eq!(scanner.next_token(), None);
+}
+
+#[test]
+fn test_tokens_borrow_from_source() {
+    let src = String::from("char* name = \"value\"; x = 42;");
+    let tokens = Scanner::new(&src).tokens();
+    let range = src.as_bytes().as_ptr_range();
+    for token in &tokens {
+        let text = match token {
+            Token::Id(text) => text.as_bytes(),
+            Token::Number(number) => number.raw().as_bytes(),
+            Token::String(string) => match string.units() {
+                CodeUnits::Bytes(bytes) => bytes,
+                units => panic!("{:?}", units),
+            },
+            _ => continue,
+        };
+        assert!(range.contains(&text.as_ptr()), "{:?}", token);
+    }
+    assert_eq!(
+        tokens[4],
+        Token::String(StringLiteral::new("\"value\"", "value".into()))
+    );
+}
+
+#[test]
+fn test_c11_keywords() {
+    let tokens = Scanner::new(
+        "typedef struct { unsigned short x; } s; static inline void f(void); _Bool b; \
+         _Static_assert(sizeof(s) == 2, \"\");",
+    )
+    .tokens();
+    let keywords: Vec<KeywordType> = tokens
+        .into_iter()
+        .filter_map(|t| match t {
+            Token::Keyword(keyword) => Some(keyword),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        keywords,
+        vec![
+            KeywordType::Typedef,
+            KeywordType::Struct,
+            KeywordType::Unsigned,
+            KeywordType::Short,
+            KeywordType::Static,
+            KeywordType::Inline,
+            KeywordType::Void,
+            KeywordType::Void,
+            KeywordType::Bool,
+            KeywordType::StaticAssert,
+            KeywordType::Sizeof,
+        ]
+    );
+}
+
+#[test]
+fn test_keywords_depend_on_standard() {
+    let src = "bool inline _Thread_local nullptr";
+    assert_eq!(
+        Scanner::new(src).with_standard(Standard::C89).tokens(),
+        vec![
+            Token::Id("bool"),
+            Token::Id("inline"),
+            Token::Id("_Thread_local"),
+            Token::Id("nullptr"),
+            Token::Eof,
+        ]
+    );
+    assert_eq!(
+        Scanner::new(src).tokens(),
+        vec![
+            Token::Id("bool"),
+            Token::Keyword(KeywordType::Inline),
+            Token::Keyword(KeywordType::ThreadLocal),
+            Token::Id("nullptr"),
+            Token::Eof,
+        ]
+    );
+    assert_eq!(
+        Scanner::new(src).with_standard(Standard::C23).tokens(),
+        vec![
+            Token::Keyword(KeywordType::Bool),
+            Token::Keyword(KeywordType::Inline),
+            Token::Keyword(KeywordType::ThreadLocal),
+            Token::Keyword(KeywordType::Nullptr),
+            Token::Eof,
+        ]
+    );
+}
+
+#[test]
+fn test_c23_keywords_share_variants_with_c11_spellings() {
+    let src = "_Bool bool _Static_assert static_assert _Alignas alignas true false";
+    assert_eq!(
+        Scanner::new(src).with_standard(Standard::C23).tokens(),
+        vec![
+            Token::Keyword(KeywordType::Bool),
+            Token::Keyword(KeywordType::Bool),
+            Token::Keyword(KeywordType::StaticAssert),
+            Token::Keyword(KeywordType::StaticAssert),
+            Token::Keyword(KeywordType::Alignas),
+            Token::Keyword(KeywordType::Alignas),
+            Token::Keyword(KeywordType::True),
+            Token::Keyword(KeywordType::False),
+            Token::Eof,
+        ]
+    );
+}
+
+#[test]
+fn test_every_punctuator() {
+    use OperatorType::*;
+
+    let cases = [
+        ("[", Token::OpenBracket),
+        ("]", Token::CloseBracket),
+        ("(", Token::OpenParen),
+        (")", Token::CloseParen),
+        ("{", Token::OpenBrace),
+        ("}", Token::CloseBrace),
+        (".", Token::Operator(Dot)),
+        ("->", Token::Operator(Arrow)),
+        ("++", Token::Operator(Increment)),
+        ("--", Token::Operator(Decrement)),
+        ("&", Token::Operator(BitwiseAnd)),
+        ("*", Token::Operator(Asterisk)),
+        ("+", Token::Operator(Plus)),
+        ("-", Token::Operator(Minus)),
+        ("~", Token::Operator(BitwiseNot)),
+        ("!", Token::Operator(ExclamationPoint)),
+        ("/", Token::Operator(Divide)),
+        ("%", Token::Operator(Modulo)),
+        ("<<", Token::Operator(ShiftLeft)),
+        (">>", Token::Operator(ShiftRight)),
+        ("<", Token::Operator(LessThan)),
+        (">", Token::Operator(GreaterThan)),
+        ("<=", Token::Operator(LessThanOrEqual)),
+        (">=", Token::Operator(GreaterThanOrEqual)),
+        ("==", Token::Operator(Equals)),
+        ("!=", Token::Operator(NotEqual)),
+        ("^", Token::Operator(BitwiseXor)),
+        ("|", Token::Operator(BitwiseOr)),
+        ("&&", Token::Operator(And)),
+        ("||", Token::Operator(Or)),
+        ("?", Token::Operator(Question)),
+        (":", Token::Colon),
+        (";", Token::SemiColon),
+        ("...", Token::Ellipsis),
+        ("=", Token::Operator(Assign)),
+        ("*=", Token::Operator(AsteriskEqual)),
+        ("/=", Token::Operator(DivideEqual)),
+        ("%=", Token::Operator(ModuloEqual)),
+        ("+=", Token::Operator(PlusEqual)),
+        ("-=", Token::Operator(MinusEqual)),
+        ("<<=", Token::Operator(ShiftLeftEqual)),
+        (">>=", Token::Operator(ShiftRightEqual)),
+        ("&=", Token::Operator(BitwiseAndEqual)),
+        ("^=", Token::Operator(BitwiseXorEqual)),
+        ("|=", Token::Operator(BitwiseOrEqual)),
+        (",", Token::Comma),
+        ("#", Token::Hash),
+        ("##", Token::HashHash),
+        ("<:", Token::OpenBracket),
+        (":>", Token::CloseBracket),
+        ("<%", Token::OpenBrace),
+        ("%>", Token::CloseBrace),
+        ("%:", Token::Hash),
+        ("%:%:", Token::HashHash),
+    ];
+    for (src, token) in cases {
+        let (tokens, errors) = scan(src);
+        assert_eq!(tokens, vec![token, Token::Eof], "{:?}", src);
+        assert!(errors.is_empty(), "{:?}", src);
+    }
+}
+
+#[test]
+fn test_punctuators_use_maximal_munch() {
+    use OperatorType::*;
+
+    assert_eq!(
+        Scanner::new("a<<=b>>=c...d..e%:%f").tokens(),
+        vec![
+            Token::Id("a"),
+            Token::Operator(ShiftLeftEqual),
+            Token::Id("b"),
+            Token::Operator(ShiftRightEqual),
+            Token::Id("c"),
+            Token::Ellipsis,
+            Token::Id("d"),
+            Token::Operator(Dot),
+            Token::Operator(Dot),
+            Token::Id("e"),
+            Token::Hash,
+            Token::Operator(Modulo),
+            Token::Id("f"),
+            Token::Eof,
+        ]
+    );
+    assert_eq!(
+        Scanner::new("x--->y").tokens(),
+        vec![
+            Token::Id("x"),
+            Token::Operator(Decrement),
+            Token::Operator(Arrow),
+            Token::Id("y"),
+            Token::Eof,
+        ]
+    );
+}
+
+#[test]
+fn test_comma_in_for_loop() {
+    let (tokens, errors) = scan("for (i = 0, j = 1; i < j; i++, j--)");
+    assert!(errors.is_empty());
+    assert_eq!(tokens[5], Token::Comma);
+    assert_eq!(tokens[16], Token::Comma);
+}
+
+#[test]
+fn test_member_access_and_fraction() {
+    assert_eq!(
+        Scanner::new("s.x = .5;").tokens(),
+        vec![
+            Token::Id("s"),
+            Token::Operator(OperatorType::Dot),
+            Token::Id("x"),
+            Token::Operator(OperatorType::Assign),
+            float(".5", 0.5, FloatSuffix::None),
+            Token::SemiColon,
+            Token::Eof,
+        ]
+    );
+}
+
+fn string_value(token: &Token) -> String {
+    match token {
+        Token::String(string) => string.value().unwrap().into_owned(),
+        _ => panic!("expected a string, got {:?}", token),
+    }
+}
+
+fn char_value(token: &Token) -> u32 {
+    match token {
+        Token::Char(char) => char.value(),
+        _ => panic!("expected a char, got {:?}", token),
+    }
+}
+
+#[test]
+fn test_string_with_escaped_quotes() {
+    let (tokens, errors) = scan(r#"s = "say \"hi\"";"#);
+    assert!(errors.is_empty());
+    match &tokens[2] {
+        Token::String(string) => {
+            assert_eq!(string.raw(), r#""say \"hi\"""#);
+            assert_eq!(string.value().as_deref(), Some("say \"hi\""));
+        }
+        token => panic!("expected a string, got {:?}", token),
+    }
+    assert_eq!(tokens[3], Token::SemiColon);
+}
+
+#[test]
+fn test_simple_escapes() {
+    let (tokens, errors) = scan(r#""\n\t\\\'\"\?\a\b\f\r\v""#);
+    assert!(errors.is_empty());
+    assert_eq!(string_value(&tokens[0]), "\n\t\\'\"?\u{7}\u{8}\u{c}\r\u{b}");
+}
+
+#[test]
+fn test_numeric_escapes() {
+    let (tokens, errors) = scan(r#""\0\101\1234\x41\x7e" '\377' '\xff' '\0'"#);
+    assert!(errors.is_empty());
+    assert_eq!(string_value(&tokens[0]), "\0AS4A~");
+    assert_eq!(char_value(&tokens[1]), 0o377);
+    assert_eq!(char_value(&tokens[2]), 0xFF);
+    assert_eq!(char_value(&tokens[3]), 0);
+}
+
+#[test]
+fn test_universal_character_names() {
+    let (tokens, errors) = scan(r#""ç\U0001F600" '$'"#);
+    assert!(errors.is_empty());
+    assert_eq!(string_value(&tokens[0]), "ç😀");
+    assert_eq!(char_value(&tokens[1]), '$'.into());
+}
+
+#[test]
+fn test_escaped_char_literals() {
+    let (tokens, errors) = scan(r"'\n' '\'' '\\'");
+    assert!(errors.is_empty());
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Char(CharLiteral::new(r"'\n'", '\n'.into())),
+            Token::Char(CharLiteral::new(r"'\''", '\''.into())),
+            Token::Char(CharLiteral::new(r"'\\'", '\\'.into())),
+            Token::Eof,
+        ]
+    );
+}
+
+#[test]
+fn test_invalid_escapes() {
+    for (src, sequence) in [
+        (r#""\q""#, r"\q"),
+        (r#""\x""#, r"\x"),
+        (r#""\u12""#, r"\u12"),
+        (r#""\U0000D800""#, r"\U0000D800"),
+        (r#""\u0041""#, r"\u0041"),
+        (r"'\z'", r"\z"),
+    ] {
+        let (tokens, errors) = scan(src);
+        assert_eq!(tokens, vec![Token::Invalid(src), Token::Eof], "{}", src);
+        match &errors[..] {
+            [LexError::InvalidEscape {
+                sequence: found,
+                span,
+            }] => {
+                assert_eq!(found, sequence, "{}", src);
+                assert_eq!(span.start(), 1);
+            }
+            errors => panic!("unexpected errors {:?} for {}", errors, src),
+        }
+    }
+}
+
+#[test]
+fn test_escapes_out_of_range() {
+    let (tokens, errors) = scan(r#"'\400' "ab\x100""#);
+    assert_eq!(
+        errors,
+        vec![
+            LexError::EscapeOutOfRange {
+                sequence: r"\400".to_string(),
+                span: Span::new(1, 5, 1, 2),
+            },
+            LexError::EscapeOutOfRange {
+                sequence: r"\x100".to_string(),
+                span: Span::new(10, 15, 1, 11),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[1].to_string(),
+        r#"escape sequence "\x100" out of range"#
+    );
+    assert_eq!(tokens.len(), 3);
+}
+
+fn number(src: &str) -> NumberLiteral<'_> {
+    let (mut tokens, errors) = scan(src);
+    assert!(errors.is_empty(), "{}: {:?}", src, errors);
+    assert_eq!(tokens.len(), 2, "{}: {:?}", src, tokens);
+    match tokens.remove(0) {
+        Token::Number(number) => number,
+        token => panic!("expected a number, got {:?}", token),
+    }
+}
+
+#[test]
+fn test_integer_radixes() {
+    for (src, radix, value) in [
+        ("0", Radix::Decimal, 0),
+        ("1234", Radix::Decimal, 1234),
+        ("0755", Radix::Octal, 0o755),
+        ("0x1F", Radix::Hexadecimal, 0x1F),
+        ("0XdeadBEEF", Radix::Hexadecimal, 0xDEAD_BEEF),
+        ("0b1011", Radix::Binary, 0b1011),
+        ("18446744073709551615", Radix::Decimal, u64::MAX),
+    ] {
+        let number = number(src);
+        assert_eq!(number.raw(), src);
+        assert_eq!(number.radix(), radix, "{}", src);
+        assert_eq!(
+            number.kind(),
+            NumberKind::Integer {
+                value,
+                suffix: IntegerSuffix::None
+            },
+            "{}",
+            src
+        );
+    }
+}
+
+#[test]
+fn test_integer_suffixes() {
+    for (src, suffix) in [
+        ("10u", IntegerSuffix::Unsigned),
+        ("10U", IntegerSuffix::Unsigned),
+        ("10l", IntegerSuffix::Long),
+        ("42UL", IntegerSuffix::UnsignedLong),
+        ("42lu", IntegerSuffix::UnsignedLong),
+        ("7ll", IntegerSuffix::LongLong),
+        ("7ULL", IntegerSuffix::UnsignedLongLong),
+        ("7llu", IntegerSuffix::UnsignedLongLong),
+        ("0xFFu", IntegerSuffix::Unsigned),
+    ] {
+        match number(src).kind() {
+            NumberKind::Integer { suffix: found, .. } => assert_eq!(found, suffix, "{}", src),
+            kind => panic!("expected an integer for {}, got {:?}", src, kind),
+        }
+    }
+}
+
+#[test]
+fn test_floating_constants() {
+    for (src, value, suffix) in [
+        ("10.5", 10.5, FloatSuffix::None),
+        ("10.5f", 10.5, FloatSuffix::Float),
+        ("10.5L", 10.5, FloatSuffix::LongDouble),
+        ("1e10", 1e10, FloatSuffix::None),
+        ("1.5E-3f", 1.5e-3, FloatSuffix::Float),
+        ("2.e+2", 200.0, FloatSuffix::None),
+        ("0x1.8p3", 12.0, FloatSuffix::None),
+        ("0x10P-2f", 4.0, FloatSuffix::Float),
+        ("0x.8p1", 1.0, FloatSuffix::None),
+        ("09.5", 9.5, FloatSuffix::None),
+        ("0x1p-99999999999", 0.0, FloatSuffix::None),
+        ("1e-99999999999", 0.0, FloatSuffix::None),
+    ] {
+        let number = number(src);
+        assert!(!number.is_integer());
+        assert_eq!(
+            number.kind(),
+            NumberKind::Float { value, suffix },
+            "{}",
+            src
+        );
+    }
+}
+
+#[test]
+fn test_digit_separators() {
+    let scanner = Scanner::new("1'000'000 0x7F'FF").with_standard(Standard::C23);
+    let tokens = scanner.tokens();
+    match &tokens[..] {
+        [Token::Number(million), Token::Number(hex), Token::Eof] => {
+            assert_eq!(million.raw(), "1'000'000");
+            assert_eq!(
+                million.kind(),
+                NumberKind::Integer {
+                    value: 1_000_000,
+                    suffix: IntegerSuffix::None
+                }
+            );
+            assert_eq!(
+                hex.kind(),
+                NumberKind::Integer {
+                    value: 0x7FFF,
+                    suffix: IntegerSuffix::None
+                }
+            );
+        }
+        tokens => panic!("unexpected tokens {:?}", tokens),
+    }
+}
+
+#[test]
+fn test_invalid_numbers() {
+    for src in ["0x", "0b102", "089", "1e", "1e+", "0x1.8", "1.2.3"] {
+        let (tokens, errors) = scan(src);
+        assert_eq!(tokens, vec![Token::Invalid(src), Token::Eof], "{}", src);
+        assert_eq!(
+            errors,
+            vec![LexError::InvalidNumber {
+                text: src.to_string(),
+                span: Span::new(0, src.len(), 1, 1),
+            }],
+            "{}",
+            src
+        );
+    }
+    for src in ["10lL", "1.5u", "10f", "0x1Fz"] {
+        let (_, errors) = scan(src);
+        assert!(
+            matches!(errors[..], [LexError::InvalidIdentifier { .. }]),
+            "{}: {:?}",
+            src,
+            errors
+        );
+    }
+}
+
+#[test]
+fn test_integer_overflow() {
+    let (tokens, errors) = scan("x = 18446744073709551616;");
+    assert_eq!(tokens[2], Token::Invalid("18446744073709551616"));
+    assert_eq!(
+        errors,
+        vec![LexError::IntegerOverflow {
+            text: "18446744073709551616".to_string(),
+            span: Span::new(4, 24, 1, 5),
+        }]
+    );
+}
+
+#[test]
+fn test_float_overflow() {
+    let (tokens, errors) = scan("x = 1e999;");
+    assert_eq!(tokens[2], Token::Invalid("1e999"));
+    assert_eq!(
+        errors,
+        vec![LexError::FloatOverflow {
+            text: "1e999".to_string(),
+            span: Span::new(4, 9, 1, 5),
+        }]
+    );
+    for src in ["0x1p99999999999", "1e39f", "0x1p128f"] {
+        let (_, errors) = scan(src);
+        assert!(
+            matches!(errors[..], [LexError::FloatOverflow { .. }]),
+            "{}: {:?}",
+            src,
+            errors
+        );
+    }
+    let (_, errors) = scan("1e38f");
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn test_string_prefixes() {
+    let (tokens, errors) = scan(r#"L"wide" u8"utf8" u"utf16" U"utf32" "plain""#);
+    assert!(errors.is_empty());
+    let strings: Vec<(Encoding, &str, String)> = tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::String(string) => Some((
+                string.encoding(),
+                string.raw(),
+                string.value().unwrap().into_owned(),
+            )),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        strings,
+        vec![
+            (Encoding::Wide, r#"L"wide""#, "wide".into()),
+            (Encoding::Utf8, r#"u8"utf8""#, "utf8".into()),
+            (Encoding::Utf16, r#"u"utf16""#, "utf16".into()),
+            (Encoding::Utf32, r#"U"utf32""#, "utf32".into()),
+            (Encoding::Plain, r#""plain""#, "plain".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_char_prefixes() {
+    let (tokens, errors) = scan("L'x' u'\\xFFFF' U'😀' 'x'");
+    assert!(errors.is_empty());
+    let chars: Vec<(Encoding, u32)> = tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Char(char) => Some((char.encoding(), char.value())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        chars,
+        vec![
+            (Encoding::Wide, 'x'.into()),
+            (Encoding::Utf16, 0xFFFF),
+            (Encoding::Utf32, 0x1F600),
+            (Encoding::Plain, 'x'.into()),
+        ]
+    );
+}
+
+#[test]
+fn test_prefix_letters_are_still_identifiers() {
+    assert_eq!(
+        Scanner::new("L u U u8 Lx = u8'a';").tokens(),
+        vec![
+            Token::Id("L"),
+            Token::Id("u"),
+            Token::Id("U"),
+            Token::Id("u8"),
+            Token::Id("Lx"),
+            Token::Operator(OperatorType::Assign),
+            Token::Id("u8"),
+            Token::Char(CharLiteral::new("'a'", 'a'.into())),
+            Token::SemiColon,
+            Token::Eof,
+        ]
+    );
+    let tokens = Scanner::new("u8'a'").with_standard(Standard::C23).tokens();
+    assert_eq!(
+        tokens[0],
+        Token::Char(CharLiteral::new("u8'a'", 'a'.into()))
+    );
+}
+
+#[test]
+fn test_multi_character_constants() {
+    let (tokens, errors) = scan("'ab' 'abcd' L'ab'");
+    assert_eq!(char_value(&tokens[0]), 0x6162);
+    assert_eq!(char_value(&tokens[1]), 0x6162_6364);
+    assert_eq!(tokens[2], Token::Invalid("L'ab'"));
+    assert!(matches!(errors[..], [LexError::MultiCharacterChar { .. }]));
+}
+
+#[test]
+fn test_escape_range_depends_on_encoding() {
+    let (_, errors) = scan(r#"u"\xFFFF" U"\x10FFFF" L"\xFFFFFFFF""#);
+    assert!(errors.is_empty(), "{:?}", errors);
+    let (_, errors) = scan(r#"u8"\x100" u"\x10000""#);
+    assert!(matches!(
+        errors[..],
+        [
+            LexError::EscapeOutOfRange { .. },
+            LexError::EscapeOutOfRange { .. }
+        ]
+    ));
+}
+
+fn units<'a>(token: &'a Token) -> &'a CodeUnits<'a> {
+    match token {
+        Token::String(string) => string.units(),
+        _ => panic!("expected a string, got {:?}", token),
+    }
+}
+
+#[test]
+fn test_string_code_units() {
+    let (tokens, errors) = scan(r#""\xff\377" "éé" u"\xD800\U0001F600" L"\xFFFFFFFF""#);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(
+        units(&tokens[0]),
+        &CodeUnits::Bytes(vec![0xFF, 0xFF].into())
+    );
+    assert_eq!(
+        units(&tokens[1]),
+        &CodeUnits::Bytes(vec![0xC3, 0xA9, 0xC3, 0xA9].into())
+    );
+    assert_eq!(
+        units(&tokens[2]),
+        &CodeUnits::Wide(vec![0xD800, 0xD83D, 0xDE00])
+    );
+    assert_eq!(units(&tokens[3]), &CodeUnits::Wide(vec![0xFFFF_FFFF]));
+
+    // These aren't text.
+    let values: Vec<_> = tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::String(string) => Some(string.value()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(values, [None, Some("éé".into()), None, None]);
+
+    // A plain part keeps its bytes in a wider literal.
+    let tokens: Vec<Token> = Scanner::new(r#""a\xff" u"\U0001F600""#)
+        .concat_strings()
+        .map(SpannedToken::into_token)
+        .collect();
+    assert_eq!(
+        units(&tokens[0]),
+        &CodeUnits::Wide(vec![0x61, 0xFF, 0xD83D, 0xDE00])
+    );
+}
+
+#[test]
+fn test_concat_adjacent_strings() {
+    let src = "puts(\"foo\" \"bar\"\n     /* gap */ \"baz\");";
+    let tokens: Vec<SpannedToken> = Scanner::new(src).concat_strings().collect();
+    assert_eq!(tokens.len(), 6);
+    match tokens[2].token() {
+        Token::String(string) => {
+            assert_eq!(string.value().as_deref(), Some("foobarbaz"));
+            assert_eq!(string.raw(), &src[5..37]);
+            assert_eq!(string.encoding(), Encoding::Plain);
+        }
+        token => panic!("expected a string, got {:?}", token),
+    }
+    assert_eq!(tokens[2].span(), Span::new(5, 37, 1, 6));
+    assert_eq!(tokens[3].token(), &Token::CloseParen);
+}
+
+#[test]
+fn test_concat_keeps_single_strings_untouched() {
+    let src = "\"a\"; \"b\"";
+    let merged: Vec<SpannedToken> = Scanner::new(src).concat_strings().collect();
+    let plain: Vec<SpannedToken> = Scanner::new(src).collect();
+    assert_eq!(merged, plain);
+}
+
+#[test]
+fn test_concat_takes_prefix_of_prefixed_part() {
+    let tokens: Vec<Token> = Scanner::new(r#""a\n" L"b" "c""#)
+        .concat_strings()
+        .map(SpannedToken::into_token)
+        .collect();
+    match &tokens[..] {
+        [Token::String(string), Token::Eof] => {
+            assert_eq!(string.encoding(), Encoding::Wide);
+            assert_eq!(string.value().as_deref(), Some("a\nbc"));
+        }
+        tokens => panic!("unexpected tokens {:?}", tokens),
+    }
+}
+
+#[test]
+fn test_concat_reports_mismatched_prefixes() {
+    let src = r#"x = u"a" U"b";"#;
+    let mut strings = Scanner::new(src).concat_strings();
+    let tokens: Vec<Token> = strings.by_ref().map(SpannedToken::into_token).collect();
+    assert_eq!(tokens[2], Token::Invalid(r#"u"a" U"b""#));
+    assert_eq!(
+        strings.errors(),
+        [LexError::IncompatibleStringEncodings {
+            span: Span::new(4, 13, 1, 5),
+        }]
+    );
+}
+
+fn rebuild(src: &str) -> String {
+    Scanner::new(src)
+        .with_trivia(true)
+        .map(|token| token.to_string())
+        .collect()
+}
+
+#[test]
+fn test_lossless_round_trip() {
+    let programs = [
+        "int x = 42;// This is a one line comment!\n// Another one\nprintf(\"Hello World!\");\n",
+        "\n        /*\n        * Multiline\n        * Comment!!\n        */\nint x = 42;\n",
+        "int x = /* Weird place for a comment */ 42;\r\n",
+        "\nint main() {\n    int a = 10;\n    for (int i = 0; i <= 10; i++) {\n        if (i + a <= 15) {\n            c = i;\n        }\n    }\n    return 0;\n}",
+        "x = $ @ 'ab' \"unterminated\n /* unterminated",
+        "",
+        "   \n\t ",
+    ];
+    for src in programs {
+        assert_eq!(rebuild(src), src);
+    }
+    let this_file = include_str!("test_scanner.rs");
+    assert_eq!(rebuild(this_file), this_file);
+}
+
+#[test]
+fn test_leading_and_trailing_trivia() {
+    let src = "  int x; // x\n/* y */ y;";
+    let tokens: Vec<SpannedToken> = Scanner::new(src).with_trivia(true).collect();
+
+    let kinds = |trivia: &[Trivia]| trivia.iter().map(Trivia::kind).collect::<Vec<_>>();
+    assert_eq!(kinds(tokens[0].leading_trivia()), [TriviaKind::Whitespace]);
+    assert_eq!(tokens[0].spelling(), "int");
+    assert!(tokens[1].leading_trivia().is_empty());
+    assert_eq!(
+        kinds(tokens[2].trailing_trivia()),
+        [TriviaKind::Whitespace, TriviaKind::LineComment]
+    );
+    assert_eq!(tokens[2].trailing_trivia()[1].text(), "// x");
+    assert_eq!(
+        kinds(tokens[3].leading_trivia()),
+        [
+            TriviaKind::Newline,
+            TriviaKind::BlockComment,
+            TriviaKind::Whitespace
+        ]
+    );
+    assert_eq!(
+        tokens[3].leading_trivia()[1].span(),
+        Span::new(14, 21, 2, 1)
+    );
+    assert_eq!(tokens[5].token(), &Token::Eof);
+}
+
+#[test]
+fn test_trivia_is_dropped_by_default() {
+    let tokens: Vec<SpannedToken> = Scanner::new(" x /* c */ ;\n").collect();
+    assert!(tokens
+        .iter()
+        .all(|t| t.leading_trivia().is_empty() && t.trailing_trivia().is_empty()));
+}
+
+#[test]
+fn test_lossless_round_trip_with_concatenated_strings() {
+    let src = "s = \"a\" /* b */ \"c\"; // d\n";
+    let rebuilt: String = Scanner::new(src)
+        .with_trivia(true)
+        .concat_strings()
+        .map(|token| token.to_string())
+        .collect();
+    assert_eq!(rebuilt, src);
+}
+
+#[test]
+fn test_doc_comments() {
+    let src = "/** Adds. */\n/*! Bang */ /// Line\n//! Inner\nint x;";
+    let tokens: Vec<SpannedToken> = Scanner::new(src).with_doc_comments(true).collect();
+    let kinds: Vec<&Token> = tokens.iter().map(SpannedToken::token).collect();
+    assert_eq!(
+        kinds,
+        [
+            &Token::DocComment(" Adds. "),
+            &Token::DocComment(" Bang "),
+            &Token::DocComment(" Line"),
+            &Token::DocComment(" Inner"),
+            &Token::Keyword(KeywordType::Int),
+            &Token::Id("x"),
+            &Token::SemiColon,
+            &Token::Eof,
+        ]
+    );
+    assert_eq!(tokens[0].span(), Span::new(0, 12, 1, 1));
+    assert_eq!(tokens[0].spelling(), "/** Adds. */");
+    assert_eq!(tokens[2].span(), Span::new(25, 33, 2, 13));
+
+    // Without the option they are plain comments.
+    assert_eq!(Scanner::new(src).tokens().len(), 4);
+}
+
+#[test]
+fn test_regular_comments_are_not_doc_comments() {
+    let src = "/**/ /* a */ /***** banner */ //// rule\n// b\nx";
+    let tokens = Scanner::new(src).with_doc_comments(true).tokens();
+    assert_eq!(tokens, [Token::Id("x"), Token::Eof]);
+}
+
+#[test]
+fn test_unterminated_doc_comment() {
+    let (tokens, errors) = {
+        let mut scanner = Scanner::new("/** doc").with_doc_comments(true);
+        let tokens: Vec<Token> = scanner.by_ref().map(SpannedToken::into_token).collect();
+        (tokens, scanner.errors().to_vec())
+    };
+    assert_eq!(tokens, [Token::Invalid("/** doc"), Token::Eof]);
+    assert_eq!(
+        errors,
+        [LexError::UnterminatedComment {
+            span: Span::new(0, 7, 1, 1)
+        }]
+    );
+}
+
+#[test]
+fn test_attach_doc_comments() {
+    let src = "
+/** Adds two numbers. */
+static inline int add(int a, int b);
+
+/// A point.
+/// Second line.
+struct point {
+    int x; ///< Abscissa.
+    int y; /**< Ordinate. */
+};
+
+/** Colours. */
+enum { RED, GREEN };
+
+/** Dangling. */";
+    let tokens: Vec<SpannedToken> = Scanner::new(src).with_doc_comments(true).collect();
+    let pairs: Vec<(&str, &str)> = attach_doc_comments(&tokens)
+        .into_iter()
+        .map(|(comment, declaration)| (comment.spelling(), declaration.spelling()))
+        .collect();
+    assert_eq!(
+        pairs,
+        [
+            ("/** Adds two numbers. */", "add"),
+            ("/// A point.", "point"),
+            ("/// Second line.", "point"),
+            ("///< Abscissa.", "x"),
+            ("/**< Ordinate. */", "y"),
+            ("/** Colours. */", "enum"),
+        ]
+    );
+}
+
+#[test]
+fn test_doc_comments_round_trip_losslessly() {
+    let src = "/** a */\nint x; ///< b\n";
+    let rebuilt: String = Scanner::new(src)
+        .with_doc_comments(true)
+        .with_trivia(true)
+        .map(|token| token.to_string())
+        .collect();
+    assert_eq!(rebuilt, src);
+}
+
+fn pp_tokens(src: &str) -> Vec<Token<'_>> {
+    Scanner::new(src).with_preprocessing(true).tokens()
+}
+
+#[test]
+fn test_preprocessing_directives() {
+    let src = "#include <stdio.h>\n  # include \"dir\\file.h\"\n#define MAX(a, b) a ## b\nint";
+    assert_eq!(
+        pp_tokens(src),
+        [
+            Token::Hash,
+            Token::Id("include"),
+            Token::HeaderName("<stdio.h>"),
+            Token::Newline,
+            Token::Hash,
+            Token::Id("include"),
+            Token::HeaderName("\"dir\\file.h\""),
+            Token::Newline,
+            Token::Hash,
+            Token::Id("define"),
+            Token::Id("MAX"),
+            Token::OpenParen,
+            Token::Id("a"),
+            Token::Comma,
+            Token::Id("b"),
+            Token::CloseParen,
+            Token::Id("a"),
+            Token::HashHash,
+            Token::Id("b"),
+            Token::Newline,
+            Token::Id("int"),
+            Token::Eof,
+        ]
+    );
+}
+
+#[test]
+fn test_header_names_only_follow_includes() {
+    assert_eq!(
+        pp_tokens("a < b > c\nx # include <y>\n"),
+        [
+            Token::Id("a"),
+            Token::Operator(OperatorType::LessThan),
+            Token::Id("b"),
+            Token::Operator(OperatorType::GreaterThan),
+            Token::Id("c"),
+            Token::Newline,
+            Token::Id("x"),
+            Token::Hash,
+            Token::Id("include"),
+            Token::Operator(OperatorType::LessThan),
+            Token::Id("y"),
+            Token::Operator(OperatorType::GreaterThan),
+            Token::Newline,
+            Token::Eof,
+        ]
+    );
+    assert_eq!(
+        pp_tokens("#if __has_include(<a.h>)\n#include <unclosed\n"),
+        [
+            Token::Hash,
+            Token::Id("if"),
+            Token::Id("__has_include"),
+            Token::OpenParen,
+            Token::HeaderName("<a.h>"),
+            Token::CloseParen,
+            Token::Newline,
+            Token::Hash,
+            Token::Id("include"),
+            Token::Operator(OperatorType::LessThan),
+            Token::Id("unclosed"),
+            Token::Newline,
+            Token::Eof,
+        ]
+    );
+}
+
+#[test]
+fn test_preprocessing_numbers() {
+    let mut scanner = Scanner::new("0x 1e .5f 1e+ 08").with_preprocessing(true);
+    let tokens: Vec<Token> = scanner.by_ref().map(SpannedToken::into_token).collect();
+    assert_eq!(
+        tokens,
+        [
+            Token::PpNumber("0x"),
+            Token::PpNumber("1e"),
+            Token::PpNumber(".5f"),
+            Token::PpNumber("1e+"),
+            Token::PpNumber("08"),
+            Token::Eof,
+        ]
+    );
+    assert_eq!(scanner.errors(), []);
+}
+
+#[test]
+fn test_line_continuations() {
+    let src = "#define ONE \\\n  1\r\n/* a\nb */ two \\\r\n three";
+    assert_eq!(
+        pp_tokens(src),
+        [
+            Token::Hash,
+            Token::Id("define"),
+            Token::Id("ONE"),
+            Token::PpNumber("1"),
+            Token::Newline,
+            Token::Id("two"),
+            Token::Id("three"),
+            Token::Eof,
+        ]
+    );
+
+    let tokens: Vec<SpannedToken> = Scanner::new(src).with_trivia(true).collect();
+    assert_eq!(
+        tokens[2].trailing_trivia()[1].kind(),
+        TriviaKind::LineContinuation
+    );
+    assert_eq!(
+        tokens.iter().map(|t| t.to_string()).collect::<String>(),
+        src
+    );
+}
+
+#[test]
+fn test_newlines_are_trivia_outside_preprocessing() {
+    assert_eq!(
+        Scanner::new("#include <a.h>\n").tokens(),
+        [
+            Token::Hash,
+            Token::Id("include"),
+            Token::Operator(OperatorType::LessThan),
+            Token::Id("a"),
+            Token::Operator(OperatorType::Dot),
+            Token::Id("h"),
+            Token::Operator(OperatorType::GreaterThan),
+            Token::Eof,
+        ]
+    );
+}
+
+fn spans<'a>(source: &'a SourceText) -> Vec<(Token<'a>, Span)> {
+    Scanner::from_source(source)
+        .map(|t| (t.token().clone(), t.span()))
+        .collect()
+}
+
+#[test]
+fn test_line_splices_inside_tokens() {
+    let source = SourceText::new("in\\\nt x = \"ab\\\r\ncd\"; // comment \\\nint y;\n@");
+    assert_eq!(source.text(), "int x = \"abcd\"; // comment int y;\n@");
+    assert_eq!(
+        spans(&source),
+        [
+            (Token::Keyword(KeywordType::Int), Span::new(0, 5, 1, 1)),
+            (Token::Id("x"), Span::new(6, 7, 2, 3)),
+            (Token::Operator(OperatorType::Assign), Span::new(8, 9, 2, 5)),
+            (
+                Token::String(StringLiteral::new("\"abcd\"", "abcd".into())),
+                Span::new(10, 19, 2, 7)
+            ),
+            (Token::SemiColon, Span::new(19, 20, 3, 4)),
+            (Token::Invalid("@"), Span::new(41, 42, 5, 1)),
+            (Token::Eof, Span::new(42, 42, 5, 2)),
+        ]
+    );
+
+    let mut scanner = Scanner::from_source(&source);
+    scanner.by_ref().for_each(drop);
+    assert_eq!(
+        scanner.errors(),
+        [LexError::InvalidSymbol {
+            symbol: '@',
+            span: Span::new(41, 42, 5, 1)
+        }]
+    );
+    assert_eq!(source.original_offset(3), 5);
+
+    let rebuilt: String = Scanner::from_source(&source)
+        .with_trivia(true)
+        .map(|t| t.to_string())
+        .collect();
+    assert_eq!(rebuilt, source.text());
+}
+
+#[test]
+fn test_concat_strings_after_line_splices() {
+    let source = SourceText::new("int a;\\\n\\\n\\\n\\\n\\\n\\\n\"ab\" \"c\\\nd\" \"ef\";");
+    let tokens: Vec<SpannedToken> = Scanner::from_source(&source).concat_strings().collect();
+    match tokens[3].token() {
+        Token::String(string) => {
+            assert_eq!(string.raw(), "\"ab\" \"cd\" \"ef\"");
+            assert_eq!(string.value().as_deref(), Some("abcdef"));
+        }
+        token => panic!("expected a string, got {:?}", token),
+    }
+    assert_eq!(tokens[3].span(), Span::new(18, 34, 7, 1));
+}
+
+#[test]
+fn test_trigraphs() {
+    let src = "??=define X ??( ??) ??'??<??!??>??- \"??/\"\" ??/\nY ??";
+    let source = SourceText::new(src).with_trigraphs(true);
+    assert_eq!(source.text(), "#define X [ ] ^{|}~ \"\\\"\" Y ??");
+    let tokens = spans(&source);
+    assert_eq!(tokens[0], (Token::Hash, Span::new(0, 3, 1, 1)));
+    assert_eq!(tokens[1], (Token::Id("define"), Span::new(3, 9, 1, 4)));
+    assert_eq!(tokens[3], (Token::OpenBracket, Span::new(12, 15, 1, 13)));
+    assert_eq!(tokens[4], (Token::CloseBracket, Span::new(16, 19, 1, 17)));
+    assert_eq!(
+        tokens[5],
+        (
+            Token::Operator(OperatorType::BitwiseXor),
+            Span::new(20, 23, 1, 21)
+        )
+    );
+    assert_eq!(
+        tokens[10].0,
+        Token::String(StringLiteral::new("\"\\\"\"", "\"".into()))
+    );
+    assert_eq!(tokens[11], (Token::Id("Y"), Span::new(47, 48, 2, 1)));
+
+    let source = SourceText::new(src);
+    assert_eq!(source.text(), src);
+    assert_eq!(spans(&source)[0].0, Token::Operator(OperatorType::Question));
+}